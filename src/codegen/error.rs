@@ -1,20 +1,67 @@
+//! Errors from generating code or writing the module to disk.
+
+use crate::diagnostic::{Diagnostic, Label};
+use crate::span::Span;
+
+/// An error from generating code or writing the module to disk
 #[derive(Debug)]
 pub struct CodegenError {
     message: String,
+    span: Option<Span>,
+    labels: Vec<Label>,
+    notes: Vec<String>,
 }
 
 impl CodegenError {
+    /// Creates an error with the given message
     pub fn new<T: Into<String>>(message: T) -> Self {
         Self {
             message: message.into(),
+            span: None,
+            labels: Vec::new(),
+            notes: Vec::new(),
         }
     }
 
+    /// Same as [`CodegenError::new`]
     pub fn error<T: Into<String>>(message: T) -> Self {
         Self::new(message)
     }
 
+    /// Sets the span of the code causing the error
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Adds a secondary label pointing at related code
+    pub fn with_label<T: Into<String>>(mut self, span: Span, message: T) -> Self {
+        self.labels.push(Label::secondary(span, message));
+        self
+    }
+
+    /// Adds a note explaining the error
+    pub fn with_note<T: Into<String>>(mut self, note: T) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// The message describing the error
     pub fn message(&self) -> &str {
         &self.message
     }
+
+    /// The span of the code causing the error, if known
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+}
+
+impl From<&CodegenError> for Diagnostic {
+    fn from(error: &CodegenError) -> Self {
+        Diagnostic::error(error.message.as_str())
+            .with_labels(error.span.map(|span| Label::primary(span, "")))
+            .with_labels(error.labels.iter().cloned())
+            .with_notes(error.notes.iter().cloned())
+    }
 }
@@ -1,16 +1,36 @@
+//! Generation of the llvm IR of a single function.
+
 use super::*;
-use llvm::LLVMIntPredicate;
+use llvm::{LLVMIntPredicate, LLVMRealPredicate, LLVMTypeKind};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::ptr;
+
+type BuildBinaryOp =
+    unsafe extern "C" fn(LLVMBuilderRef, LLVMValueRef, LLVMValueRef, *const c_char) -> LLVMValueRef;
 
 struct FunctionEnvironment {
-    variables: HashMap<String, LLVMValueRef>,
+    /// The variables of each block being built, the innermost last
+    variables: Vec<HashMap<String, LLVMValueRef>>,
     basic_blocks: HashMap<String, LLVMBasicBlockRef>,
     global: Environment,
+    global_variables: Globals,
+    structs: Structs,
+    source_map: Option<Rc<SourceMap>>,
 }
 
 impl FunctionEnvironment {
-    pub fn new(global: Environment) -> Self {
+    pub fn new(
+        global: Environment,
+        global_variables: Globals,
+        structs: Structs,
+        source_map: Option<Rc<SourceMap>>,
+    ) -> Self {
         Self {
             global,
+            global_variables,
+            structs,
+            source_map,
             ..Default::default()
         }
     }
@@ -19,37 +39,62 @@ impl FunctionEnvironment {
 impl Default for FunctionEnvironment {
     fn default() -> Self {
         Self {
-            variables: HashMap::new(),
+            // The arguments are in the outermost scope
+            variables: vec![HashMap::new()],
             basic_blocks: HashMap::new(),
             global: Environment::default(),
+            global_variables: Globals::default(),
+            structs: Structs::default(),
+            source_map: None,
         }
     }
 }
 
+/// Where `continue` and `break` jump to inside a loop
+struct LoopTarget {
+    continue_block: LLVMBasicBlockRef,
+    break_block: LLVMBasicBlockRef,
+    /// The values given to `break`, and the blocks they come from
+    breaks: Vec<(LLVMBasicBlockRef, LLVMValueRef)>,
+}
+
+/// A function of the module being generated
 pub struct Function {
+    /// The module the function is added to
     pub module: Rc<RefCell<module::Module>>,
+    /// The builder shared by all functions of the module
     pub builder: LLVMBuilderRef,
+    /// The llvm context of the module
     pub context: LLVMContextRef,
     environment: FunctionEnvironment,
     value: LLVMValueRef,
     name: String,
     current_basic_block: Option<LLVMBasicBlockRef>,
+    loops: Vec<LoopTarget>,
 }
 
 impl Function {
+    /// Adds a function with the given type and name to the module of the codegen
     pub fn new(codegen: &mut Codegen, function_type: Types, name: &str) -> Self {
         let value = build_function(codegen, function_type, name);
         Self {
             module: codegen.module.clone(),
             builder: codegen.builder,
             context: codegen.context,
-            environment: FunctionEnvironment::new(codegen.environment.clone()),
+            environment: FunctionEnvironment::new(
+                codegen.environment.clone(),
+                codegen.globals.clone(),
+                codegen.structs.clone(),
+                codegen.source_map.clone(),
+            ),
             value,
             name: String::from(name),
             current_basic_block: None,
+            loops: Vec::new(),
         }
     }
 
+    /// Builds a call of this function
     pub fn call(&self, args: &mut [LLVMValueRef], name: &str) -> LLVMValueRef {
         unsafe {
             LLVMBuildCall(
@@ -62,6 +107,7 @@ impl Function {
         }
     }
 
+    /// Appends a new basic block to the function
     pub fn basic_block(&mut self, name: &str) -> LLVMBasicBlockRef {
         unsafe {
             self.environment.basic_blocks.insert(
@@ -86,7 +132,7 @@ impl Function {
     //    //}
     //}
 
-    pub fn position_at_block_ref(&mut self, block_ref: LLVMBasicBlockRef) {
+    pub(crate) fn position_at_block_ref(&mut self, block_ref: LLVMBasicBlockRef) {
         unsafe { LLVMPositionBuilderAtEnd(self.builder, block_ref) }
         self.current_basic_block = Some(block_ref);
     }
@@ -100,216 +146,1075 @@ impl Function {
         unsafe { LLVMBuildCondBr(self.builder, condition, then, else_block) }
     }
 
+    /// Builds a statement, resulting in its value
     pub fn build_statement(&mut self, statement: &Statement) -> LLVMValueRef {
         match statement {
             Statement::VarDecl(var_decl) => {
-                let ptr = self.build_stack_ptr(Types::Int, &var_decl.identifier);
                 let value = self.build_expression(&var_decl.expression);
+                let var_type = match &var_decl.var_type {
+                    Some(var_type) => self.types(var_type).to_llvm(self.context),
+                    None => unsafe { LLVMTypeOf(value) },
+                };
+                let ptr = self.build_stack_slot(var_type, &var_decl.identifier);
                 self.assign(&var_decl.identifier, ptr);
                 self.build_store(value, ptr)
             }
             Statement::Expression(expr) => self.build_expression(expr),
-            Statement::Empty => self.build_null(Types::Void),
+            Statement::Empty => self.build_void(),
+            Statement::Error(_) => panic!("Programs with syntax errors can not be compiled"),
         }
     }
 
+    /// Builds an expression, resulting in its value
     pub fn build_expression(&mut self, expr: &Expression) -> LLVMValueRef {
         unsafe {
-            match expr {
-                Expression::Addition(left, right) => LLVMBuildAdd(
-                    self.builder,
-                    self.build_expression(left),
-                    self.build_expression(right),
-                    self.module.borrow().empty_string(),
+            match &expr.kind {
+                ExpressionKind::Addition(left, right) => {
+                    self.build_arithmetic(left, right, LLVMBuildAdd, LLVMBuildFAdd)
+                }
+                ExpressionKind::Subtraction(left, right) => {
+                    self.build_arithmetic(left, right, LLVMBuildSub, LLVMBuildFSub)
+                }
+                ExpressionKind::Multiplication(left, right) => {
+                    self.build_arithmetic(left, right, LLVMBuildMul, LLVMBuildFMul)
+                }
+                ExpressionKind::Division(left, right) => {
+                    self.build_arithmetic(left, right, LLVMBuildSDiv, LLVMBuildFDiv)
+                }
+                ExpressionKind::Modulus(left, right) => {
+                    self.build_arithmetic(left, right, LLVMBuildSRem, LLVMBuildFRem)
+                }
+                ExpressionKind::Equality(left, right) => self.build_compare(
+                    left,
+                    right,
+                    LLVMIntPredicate::LLVMIntEQ,
+                    LLVMRealPredicate::LLVMRealOEQ,
                 ),
-                Expression::Subtraction(left, right) => LLVMBuildSub(
-                    self.builder,
-                    self.build_expression(left),
-                    self.build_expression(right),
-                    self.module.borrow().empty_string(),
+                ExpressionKind::NotEq(left, right) => self.build_compare(
+                    left,
+                    right,
+                    LLVMIntPredicate::LLVMIntNE,
+                    LLVMRealPredicate::LLVMRealUNE,
                 ),
-                Expression::Multiplication(left, right) => LLVMBuildMul(
-                    self.builder,
-                    self.build_expression(left),
-                    self.build_expression(right),
-                    self.module.borrow().empty_string(),
+                ExpressionKind::LessThan(left, right) => self.build_compare(
+                    left,
+                    right,
+                    LLVMIntPredicate::LLVMIntSLT,
+                    LLVMRealPredicate::LLVMRealOLT,
                 ),
-                Expression::Division(left, right) => LLVMBuildSDiv(
-                    self.builder,
-                    self.build_expression(left),
-                    self.build_expression(right),
-                    self.module.borrow().empty_string(),
+                ExpressionKind::GreaterThan(left, right) => self.build_compare(
+                    left,
+                    right,
+                    LLVMIntPredicate::LLVMIntSGT,
+                    LLVMRealPredicate::LLVMRealOGT,
                 ),
-                Expression::Modulus(left, right) => LLVMBuildOr(
+                ExpressionKind::LessEq(left, right) => self.build_compare(
+                    left,
+                    right,
+                    LLVMIntPredicate::LLVMIntSLE,
+                    LLVMRealPredicate::LLVMRealOLE,
+                ),
+                ExpressionKind::GreaterEq(left, right) => self.build_compare(
+                    left,
+                    right,
+                    LLVMIntPredicate::LLVMIntSGE,
+                    LLVMRealPredicate::LLVMRealOGE,
+                ),
+                ExpressionKind::And(left, right) => self.build_short_circuit(left, right, true),
+                ExpressionKind::Or(left, right) => self.build_short_circuit(left, right, false),
+                ExpressionKind::Not(expr) => LLVMBuildNot(
                     self.builder,
-                    self.build_expression(left),
-                    self.build_expression(right),
+                    self.build_expression(expr),
                     self.module.borrow().empty_string(),
                 ),
-                Expression::Equality(left, right) => {
-                    let left = self.build_expression(left);
-                    let right = self.build_expression(right);
-                    self.build_icmp(LLVMIntPredicate::LLVMIntEQ, left, right)
+                ExpressionKind::Neg(expr) => {
+                    let value = self.build_expression(expr);
+                    let build_neg = if is_floating_point(value) {
+                        LLVMBuildFNeg
+                    } else {
+                        LLVMBuildNeg
+                    };
+                    build_neg(self.builder, value, self.module.borrow().empty_string())
                 }
-                Expression::NotEq(left, right) => {
-                    let left = self.build_expression(left);
-                    let right = self.build_expression(right);
-                    self.build_icmp(LLVMIntPredicate::LLVMIntNE, left, right)
+                ExpressionKind::Field(value, name) => match self.build_place(expr) {
+                    Some(ptr) => self.build_load(ptr),
+                    None => {
+                        let value = self.build_expression(value);
+                        LLVMBuildExtractValue(
+                            self.builder,
+                            value,
+                            self.field_index(LLVMTypeOf(value), name),
+                            self.module.borrow_mut().new_string_ptr(name),
+                        )
+                    }
+                },
+                ExpressionKind::MethodCall(object, call) => {
+                    let ptr = match self.build_place(object) {
+                        Some(ptr) => ptr,
+                        None => {
+                            // Temporary objects are stored on the stack, as methods take a pointer
+                            let value = self.build_expression(object);
+                            let ptr = self.build_stack_slot(LLVMTypeOf(value), "object");
+                            self.build_store(value, ptr);
+                            ptr
+                        }
+                    };
+                    let class =
+                        CStr::from_ptr(LLVMGetStructName(LLVMGetElementType(LLVMTypeOf(ptr))));
+                    let method = ClassDecl::mangle(class.to_str().unwrap(), &call.name);
+                    let mut args = vec![ptr];
+                    for arg in call.arguments.iter() {
+                        args.push(self.build_expression(arg));
+                    }
+                    self.call_other(&method, &mut args, "")
                 }
-                Expression::LessThan(left, right) => {
-                    let left = self.build_expression(left);
-                    let right = self.build_expression(right);
-                    self.build_icmp(LLVMIntPredicate::LLVMIntSLT, left, right)
+                ExpressionKind::Index(value, index) => match self.build_place(expr) {
+                    Some(ptr) => self.build_load(ptr),
+                    None => {
+                        // Stored on the stack, as only arrays in memory can be indexed at runtime
+                        let value = self.build_expression(value);
+                        let ptr = self.build_stack_slot(LLVMTypeOf(value), "array");
+                        self.build_store(value, ptr);
+                        let element = self.build_element_ptr(ptr, index, expr.span);
+                        self.build_load(element)
+                    }
+                },
+                ExpressionKind::Assignment(target, value) => {
+                    let value = self.build_expression(value);
+                    let ptr = self
+                        .build_place(target)
+                        .expect("Only variables and their fields can be assigned to");
+                    self.build_store(value, ptr);
+                    self.build_void()
                 }
-                Expression::GreaterThan(left, right) => {
-                    let left = self.build_expression(left);
-                    let right = self.build_expression(right);
-                    self.build_icmp(LLVMIntPredicate::LLVMIntSGT, left, right)
+                ExpressionKind::CompoundAssignment(op, target, value) => {
+                    let value = self.build_expression(value);
+                    // The place is only built once, so an index with side effects runs once
+                    let ptr = self
+                        .build_place(target)
+                        .expect("Only variables and their fields can be assigned to");
+                    let current = self.build_load(ptr);
+                    let (int_op, float_op): (BuildBinaryOp, BuildBinaryOp) = match op {
+                        AssignmentOp::Add => (LLVMBuildAdd, LLVMBuildFAdd),
+                        AssignmentOp::Sub => (LLVMBuildSub, LLVMBuildFSub),
+                        AssignmentOp::Mul => (LLVMBuildMul, LLVMBuildFMul),
+                        AssignmentOp::Div => (LLVMBuildSDiv, LLVMBuildFDiv),
+                        AssignmentOp::Mod => (LLVMBuildSRem, LLVMBuildFRem),
+                    };
+                    let result = self.build_binary_op(current, value, int_op, float_op);
+                    self.build_store(result, ptr);
+                    self.build_void()
                 }
-                Expression::LessEq(left, right) => {
-                    let left = self.build_expression(left);
-                    let right = self.build_expression(right);
-                    self.build_icmp(LLVMIntPredicate::LLVMIntSLE, left, right)
+                ExpressionKind::If(if_expression) => self.build_if_expression(if_expression),
+                ExpressionKind::Match(match_expression) => self.build_match(match_expression),
+                ExpressionKind::While(condition, body) => self.build_while(condition, body),
+                ExpressionKind::Loop(loop_expression) => self.build_loop(loop_expression),
+                ExpressionKind::Break(value) => {
+                    let value = match value {
+                        Some(value) => self.build_expression(value),
+                        None => self.build_void(),
+                    };
+                    let current_basic_block = self.current_basic_block.unwrap();
+                    let target = self
+                        .loops
+                        .last_mut()
+                        .expect("break is always inside a loop");
+                    target.breaks.push((current_basic_block, value));
+                    let break_block = target.break_block;
+                    self.build_br(break_block);
+                    self.build_dead_block()
                 }
-                Expression::GreaterEq(left, right) => {
-                    let left = self.build_expression(left);
-                    let right = self.build_expression(right);
-                    self.build_icmp(LLVMIntPredicate::LLVMIntSGE, left, right)
+                ExpressionKind::Continue => {
+                    let target = self.loops.last().expect("continue is always inside a loop");
+                    let continue_block = target.continue_block;
+                    self.build_br(continue_block);
+                    self.build_dead_block()
                 }
-                Expression::And(left, right) => LLVMBuildAnd(
-                    self.builder,
-                    self.build_expression(left),
-                    self.build_expression(right),
-                    self.module.borrow().empty_string(),
-                ),
-                Expression::Or(left, right) => LLVMBuildOr(
-                    self.builder,
-                    self.build_expression(left),
-                    self.build_expression(right),
-                    self.module.borrow().empty_string(),
-                ),
-                Expression::Not(expr) => LLVMBuildNot(
-                    self.builder,
-                    self.build_expression(expr),
-                    self.module.borrow().empty_string(),
-                ),
-                Expression::If(if_expression) => self.build_if_expression(if_expression),
-                Expression::Block(block) => self.build_block(block),
-                Expression::Value(value) => match value {
-                    Value::Literal(Literal::Number(Number::Int(int))) => {
-                        LLVMConstInt(Types::Int.to_llvm(self.context), *int as u64, 1)
+                ExpressionKind::Return(value) => {
+                    let value = match value {
+                        Some(value) => self.build_expression(value),
+                        None => self.build_void(),
+                    };
+                    if is_void(LLVMTypeOf(value)) {
+                        self.build_ret_void();
+                    } else {
+                        self.build_ret(value);
                     }
-                    Value::Literal(Literal::Boolean(boolean)) => LLVMConstInt(
-                        Types::Bool.to_llvm(self.context),
-                        if *boolean { 1 } else { 0 },
-                        0,
-                    ),
-                    Value::Literal(Literal::String(string)) => self.build_global_string(&string),
-                    Value::Variable(name) => {
-                        if let Some(var) = self.var(name) {
-                            self.build_load(var)
-                        } else {
-                            panic!("Variable '{}' has not been declared yet", name);
+                    self.build_dead_block()
+                }
+                ExpressionKind::Block(block) => self.build_block(block),
+                ExpressionKind::Value(value) => match value {
+                    Value::Literal(literal) => self.build_literal(literal),
+                    Value::Variable(name) => match self.var(name) {
+                        // Constants are known at compile time, so the value is used directly.
+                        // This also folds constants computed from other constants.
+                        Some(var)
+                            if !LLVMIsAGlobalVariable(var).is_null()
+                                && LLVMIsGlobalConstant(var) != 0 =>
+                        {
+                            LLVMGetInitializer(var)
                         }
+                        Some(var) => self.build_load(var),
+                        None => panic!("Variable '{}' has not been declared yet", name),
+                    },
+                    Value::FunctionCall(func) if func.name == "len" && !self.declared("len") => {
+                        let array = self.build_expression(&func.arguments[0]);
+                        LLVMConstInt(
+                            Types::Int.to_llvm(self.context),
+                            LLVMGetArrayLength(LLVMTypeOf(array)) as u64,
+                            0,
+                        )
                     }
                     Value::FunctionCall(func) => {
                         let mut args = Vec::new();
                         for arg in func.arguments.iter() {
-                            args.push(self.build_expression(&arg));
+                            args.push(self.build_expression(arg));
+                        }
+                        // Objects are created by calling the class like a function
+                        let constructor = ClassDecl::mangle(&func.name, "new");
+                        if self.declared(&constructor) {
+                            self.call_other(&constructor, &mut args, "")
+                        } else {
+                            self.call_other(&func.name, &mut args, "")
                         }
-                        self.call_other(&func.name, &mut args, "")
                     }
-                    _ => panic!("Not yet implemented!"),
+                    Value::Struct(literal) => {
+                        let struct_type = self
+                            .types(&Type::UserDefined(literal.name.clone()))
+                            .to_llvm(self.context);
+                        let mut value = LLVMGetUndef(struct_type);
+                        for field in literal.fields.iter() {
+                            let field_value = self.build_expression(&field.value);
+                            value = LLVMBuildInsertValue(
+                                self.builder,
+                                value,
+                                field_value,
+                                self.field_index(struct_type, &field.name),
+                                self.module.borrow().empty_string(),
+                            );
+                        }
+                        value
+                    }
+                    Value::Variant(literal) => {
+                        let enum_type = self
+                            .types(&Type::UserDefined(literal.enum_name.clone()))
+                            .to_llvm(self.context);
+                        let (tag, variant_type) =
+                            self.variant(&literal.enum_name, &literal.variant);
+                        let slot = self.build_stack_slot(enum_type, &literal.variant);
+                        let tag_ptr = LLVMBuildStructGEP(
+                            self.builder,
+                            slot,
+                            0,
+                            self.module.borrow_mut().new_string_ptr("tag"),
+                        );
+                        self.build_store(tag, tag_ptr);
+                        let payload = self.build_payload_ptr(slot, variant_type);
+                        for (i, argument) in literal.arguments.iter().enumerate() {
+                            let argument = self.build_expression(argument);
+                            let field = LLVMBuildStructGEP(
+                                self.builder,
+                                payload,
+                                i as c_uint,
+                                self.module.borrow().empty_string(),
+                            );
+                            self.build_store(argument, field);
+                        }
+                        self.build_load(slot)
+                    }
+                    Value::Array(elements) => {
+                        let elements: Vec<LLVMValueRef> = elements
+                            .iter()
+                            .map(|element| self.build_expression(element))
+                            .collect();
+                        let array_type =
+                            LLVMArrayType(LLVMTypeOf(elements[0]), elements.len() as c_uint);
+                        let mut value = LLVMGetUndef(array_type);
+                        for (i, element) in elements.into_iter().enumerate() {
+                            value = LLVMBuildInsertValue(
+                                self.builder,
+                                value,
+                                element,
+                                i as c_uint,
+                                self.module.borrow().empty_string(),
+                            );
+                        }
+                        value
+                    }
                 },
+                ExpressionKind::Error => panic!("Programs with syntax errors can not be compiled"),
+            }
+        }
+    }
+
+    fn build_literal(&mut self, literal: &Literal) -> LLVMValueRef {
+        unsafe {
+            match literal {
+                Literal::Number(Number::Int(int)) => {
+                    LLVMConstInt(Types::Int.to_llvm(self.context), *int as u64, 1)
+                }
+                Literal::Number(Number::Float(float)) => {
+                    LLVMConstReal(Types::Float.to_llvm(self.context), *float as f64)
+                }
+                Literal::Number(Number::Double(double)) => {
+                    LLVMConstReal(Types::Double.to_llvm(self.context), *double)
+                }
+                Literal::Boolean(boolean) => LLVMConstInt(
+                    Types::Bool.to_llvm(self.context),
+                    if *boolean { 1 } else { 0 },
+                    0,
+                ),
+                Literal::String(string) => self.build_global_string(string),
+            }
+        }
+    }
+
+    /// Builds a pointer to the variable, or field of a variable, that `expr` refers to.
+    /// Other expressions are values without a place in memory.
+    fn build_place(&mut self, expr: &Expression) -> Option<LLVMValueRef> {
+        match &expr.kind {
+            ExpressionKind::Value(Value::Variable(name)) => Some(
+                self.var(name)
+                    .unwrap_or_else(|| panic!("Variable '{}' has not been declared yet", name)),
+            ),
+            ExpressionKind::Field(value, name) => {
+                let ptr = self.build_place(value)?;
+                unsafe {
+                    let index = self.field_index(LLVMGetElementType(LLVMTypeOf(ptr)), name);
+                    Some(LLVMBuildStructGEP(
+                        self.builder,
+                        ptr,
+                        index,
+                        self.module.borrow_mut().new_string_ptr(name),
+                    ))
+                }
+            }
+            ExpressionKind::Index(value, index) => {
+                let ptr = self.build_place(value)?;
+                Some(self.build_element_ptr(ptr, index, expr.span))
+            }
+            _ => None,
+        }
+    }
+
+    /// Builds a pointer to an element of the array `ptr` points to,
+    /// aborting the program when the index is out of bounds
+    fn build_element_ptr(
+        &mut self,
+        ptr: LLVMValueRef,
+        index: &Expression,
+        span: Span,
+    ) -> LLVMValueRef {
+        unsafe {
+            let index = self.build_expression(index);
+            let int_type = Types::Int.to_llvm(self.context);
+            let length = LLVMGetArrayLength(LLVMGetElementType(LLVMTypeOf(ptr))) as u64;
+            // Constant indices known to be in bounds need no check
+            let checked =
+                LLVMIsAConstantInt(index).is_null() || LLVMConstIntGetZExtValue(index) >= length;
+            if checked {
+                let length = LLVMConstInt(int_type, length, 0);
+                // Negative indices become large unsigned numbers, so one comparison is enough
+                let in_bounds = LLVMBuildICmp(
+                    self.builder,
+                    LLVMIntPredicate::LLVMIntULT,
+                    index,
+                    length,
+                    self.module.borrow().empty_string(),
+                );
+                let out_of_bounds_block = self.basic_block("out_of_bounds");
+                let in_bounds_block = self.basic_block("in_bounds");
+                self.build_cond_br(in_bounds, in_bounds_block, out_of_bounds_block);
+
+                self.position_at_block_ref(out_of_bounds_block);
+                self.build_out_of_bounds(index, length, span);
+
+                self.position_at_block_ref(in_bounds_block);
+            }
+            let mut indices = [LLVMConstInt(int_type, 0, 0), index];
+            LLVMBuildInBoundsGEP(
+                self.builder,
+                ptr,
+                indices.as_mut_ptr(),
+                indices.len() as c_uint,
+                self.module.borrow().empty_string(),
+            )
+        }
+    }
+
+    /// Prints which index was out of bounds, and where, to stderr before aborting.
+    fn build_out_of_bounds(&mut self, index: LLVMValueRef, length: LLVMValueRef, span: Span) {
+        let message = format!(
+            "{}: index %lld is out of bounds for an array of length %lld\n",
+            self.location(span).replace('%', "%%")
+        );
+        unsafe {
+            let int32 = LLVMInt32TypeInContext(self.context);
+            let mut dprintf_args = [int32, Types::String.to_llvm(self.context)];
+            let dprintf = self.runtime_function(
+                "dprintf",
+                LLVMFunctionType(int32, dprintf_args.as_mut_ptr(), 2, 1),
+            );
+            let mut fflush_args = [Types::String.to_llvm(self.context)];
+            let fflush = self.runtime_function(
+                "fflush",
+                LLVMFunctionType(int32, fflush_args.as_mut_ptr(), 1, 0),
+            );
+            let abort = self.runtime_function(
+                "abort",
+                LLVMFunctionType(Types::Void.to_llvm(self.context), ptr::null_mut(), 0, 0),
+            );
+
+            // Output printed before the error is not lost
+            let mut streams = [LLVMConstNull(fflush_args[0])];
+            LLVMBuildCall(
+                self.builder,
+                fflush,
+                streams.as_mut_ptr(),
+                1,
+                self.module.borrow().empty_string(),
+            );
+            let stderr = LLVMConstInt(int32, 2, 0);
+            let format = self.build_global_string(&message);
+            let mut args = [stderr, format, index, length];
+            LLVMBuildCall(
+                self.builder,
+                dprintf,
+                args.as_mut_ptr(),
+                args.len() as c_uint,
+                self.module.borrow().empty_string(),
+            );
+            LLVMBuildCall(
+                self.builder,
+                abort,
+                ptr::null_mut(),
+                0,
+                self.module.borrow().empty_string(),
+            );
+            LLVMBuildUnreachable(self.builder);
+        }
+    }
+
+    /// Gets the C library function `name`, declaring it unless the program already has.
+    /// A declaration by the program with another type is cast to `function_type`.
+    fn runtime_function(&self, name: &str, function_type: LLVMTypeRef) -> LLVMValueRef {
+        unsafe {
+            let mut module = self.module.borrow_mut();
+            let name = module.new_string_ptr(name);
+            let function = LLVMGetNamedFunction(module.module, name);
+            if function.is_null() {
+                LLVMAddFunction(module.module, name, function_type)
+            } else if LLVMGetElementType(LLVMTypeOf(function)) != function_type {
+                LLVMConstBitCast(function, LLVMPointerType(function_type, 0))
+            } else {
+                function
+            }
+        }
+    }
+
+    /// Describes where `span` starts, as `file:line:column` when the source is known
+    fn location(&self, span: Span) -> String {
+        let file = self
+            .environment
+            .source_map
+            .as_ref()
+            .and_then(|source_map| source_map.get(span.file));
+        match file {
+            Some(file) => {
+                let (line, column) = file.line_and_column(span.start);
+                format!("{}:{}:{}", file.name(), line, column)
             }
+            None => format!("{}", span),
         }
     }
 
-    unsafe fn build_icmp(
+    /// Whether the program has a function called `name`
+    fn declared(&self, name: &str) -> bool {
+        name == self.name || self.environment.global.borrow().contains_key(name)
+    }
+
+    /// The index of the field `name` in the llvm struct type
+    fn field_index(&self, struct_type: LLVMTypeRef, name: &str) -> c_uint {
+        let struct_name = unsafe { CStr::from_ptr(LLVMGetStructName(struct_type)) };
+        self.environment.structs.borrow()[struct_name.to_str().unwrap()].field_index(name)
+    }
+
+    /// The tag of the variant `name` of an enum, and the llvm struct of its values
+    fn variant(&self, enum_name: &str, name: &str) -> (LLVMValueRef, LLVMTypeRef) {
+        let structs = self.environment.structs.borrow();
+        let (index, variant) = structs[enum_name].variant(name);
+        let tag = unsafe { LLVMConstInt(Types::Int.to_llvm(self.context), index as u64, 0) };
+        (tag, variant.llvm_type)
+    }
+
+    /// Casts the payload of the enum behind `ptr` to a pointer to the struct of a variant
+    unsafe fn build_payload_ptr(
+        &mut self,
+        ptr: LLVMValueRef,
+        variant_type: LLVMTypeRef,
+    ) -> LLVMValueRef {
+        let payload = LLVMBuildStructGEP(
+            self.builder,
+            ptr,
+            1,
+            self.module.borrow_mut().new_string_ptr("payload"),
+        );
+        self.build_bit_cast(
+            payload,
+            Types::Pointer(Box::new(Types::Struct(variant_type))),
+        )
+    }
+
+    /// Converts a type of the language, which may be one of the declared structs or enums
+    pub fn types(&self, value_type: &Type) -> Types {
+        Types::from_type(value_type.clone(), &self.environment.structs.borrow())
+    }
+
+    /// Builds an arithmetic instruction on the values of the expressions `left` and `right`
+    unsafe fn build_arithmetic(
+        &mut self,
+        left: &Expression,
+        right: &Expression,
+        int_op: BuildBinaryOp,
+        float_op: BuildBinaryOp,
+    ) -> LLVMValueRef {
+        let left = self.build_expression(left);
+        let right = self.build_expression(right);
+        self.build_binary_op(left, right, int_op, float_op)
+    }
+
+    /// Builds `int_op`, or `float_op` when the operands are floating point
+    unsafe fn build_binary_op(
         &mut self,
-        op: LLVMIntPredicate,
         left: LLVMValueRef,
         right: LLVMValueRef,
+        int_op: BuildBinaryOp,
+        float_op: BuildBinaryOp,
     ) -> LLVMValueRef {
-        LLVMBuildICmp(
+        let op = if is_floating_point(left) {
+            float_op
+        } else {
+            int_op
+        };
+        op(
             self.builder,
-            op,
             left,
             right,
             self.module.borrow().empty_string(),
         )
     }
 
+    /// Builds a comparison, using `real_op` when the operands are floating point
+    unsafe fn build_compare(
+        &mut self,
+        left: &Expression,
+        right: &Expression,
+        int_op: LLVMIntPredicate,
+        real_op: LLVMRealPredicate,
+    ) -> LLVMValueRef {
+        let left = self.build_expression(left);
+        let right = self.build_expression(right);
+        if is_floating_point(left) {
+            LLVMBuildFCmp(
+                self.builder,
+                real_op,
+                left,
+                right,
+                self.module.borrow().empty_string(),
+            )
+        } else {
+            LLVMBuildICmp(
+                self.builder,
+                int_op,
+                left,
+                right,
+                self.module.borrow().empty_string(),
+            )
+        }
+    }
+
+    /// Stack allocations are always placed at the start of the entry block,
+    /// as this is where `mem2reg` expects to find them.
+    pub(crate) fn build_stack_slot(&mut self, slot_type: LLVMTypeRef, name: &str) -> LLVMValueRef {
+        unsafe {
+            let builder = LLVMCreateBuilderInContext(self.context);
+            let entry = LLVMGetEntryBasicBlock(self.value());
+            let first = LLVMGetFirstInstruction(entry);
+            if first.is_null() {
+                LLVMPositionBuilderAtEnd(builder, entry);
+            } else {
+                LLVMPositionBuilderBefore(builder, first);
+            }
+            let ptr = LLVMBuildAlloca(
+                builder,
+                slot_type,
+                self.module.borrow_mut().new_string_ptr(name),
+            );
+            LLVMDisposeBuilder(builder);
+            ptr
+        }
+    }
+
+    /// Like [`Builder::build_phi`], but for types only known to llvm
+    pub(crate) fn build_phi_of_type(
+        &mut self,
+        value_type: LLVMTypeRef,
+        incoming: Vec<(LLVMBasicBlockRef, LLVMValueRef)>,
+    ) -> LLVMValueRef {
+        unsafe {
+            let value = LLVMBuildPhi(
+                self.builder,
+                value_type,
+                self.module.borrow().empty_string(),
+            );
+            let count = incoming.len() as u32;
+            let (mut blocks, mut values): (Vec<_>, Vec<_>) = incoming.into_iter().unzip();
+            LLVMAddIncoming(value, values.as_mut_ptr(), blocks.as_mut_ptr(), count);
+            value
+        }
+    }
+
+    /// Placeholder for the result of statements and expressions without a value.
+    /// It must never be used as an operand.
+    pub fn build_void(&self) -> LLVMValueRef {
+        unsafe { LLVMGetUndef(Types::Void.to_llvm(self.context)) }
+    }
+
+    /// Builds the statements of a block in a new scope, resulting in the value of the last one
     pub fn build_block(&mut self, block: &Block) -> LLVMValueRef {
+        self.environment.variables.push(HashMap::new());
         for statement in block.iter().take(block.len() - 1) {
             self.build_statement(statement);
         }
-        self.build_statement(block.last().unwrap())
+        let value = self.build_statement(block.last().unwrap());
+        self.environment.variables.pop();
+        value
     }
 
+    /// Builds an if expression, resulting in the value of the branch taken
     pub fn build_if_expression(&mut self, if_expression: &IfExpression) -> LLVMValueRef {
-        let current_basic_block = self.current_basic_block.unwrap();
-        let after = self.basic_block("after");
         let condition = self.build_expression(&if_expression.condition);
         let if_block = self.basic_block("if");
+        let else_block = self.basic_block("else");
+        let after = self.basic_block("after");
+        self.build_cond_br(condition, if_block, else_block);
+
         self.position_at_block_ref(if_block);
         let if_body = self.build_block(&if_expression.body);
-        let mut incoming = vec![(self.current_basic_block.unwrap(), if_body)];
-        self.build_br(after);
-        let else_block = match &if_expression.else_expression {
-            ElseExpression::Block(block) => {
-                let basic_block = self.basic_block("else");
-                self.position_at_block_ref(basic_block);
-                incoming.push((basic_block, self.build_block(&block)));
-                self.build_br(after);
-                basic_block
+        // Falls back to the type of the body when the type system has not been run
+        let result_type = match (&if_expression.result_type, &if_expression.else_expression) {
+            (Some(result_type), _) => self.types(result_type).to_llvm(self.context),
+            (None, ElseExpression::None) => Types::Void.to_llvm(self.context),
+            (None, _) => unsafe { LLVMTypeOf(if_body) },
+        };
+        let has_value = !is_void(result_type);
+        let mut incoming = Vec::new();
+        self.end_branch(after, if_body, has_value, &mut incoming);
+
+        self.position_at_block_ref(else_block);
+        let else_body = match &if_expression.else_expression {
+            ElseExpression::Block(block) => self.build_block(block),
+            ElseExpression::IfExpression(expr) => self.build_if_expression(expr),
+            ElseExpression::None => self.build_void(),
+        };
+        self.end_branch(after, else_body, has_value, &mut incoming);
+
+        self.position_at_block_ref(after);
+        if has_value {
+            self.build_phi_of_type(result_type, incoming)
+        } else {
+            self.build_void()
+        }
+    }
+
+    /// Ends a branch of an if expression by jumping to `after` with the value of the branch.
+    /// Branches without a value when one is expected, e.g. those ending with `return`,
+    /// never reach `after`.
+    fn end_branch(
+        &mut self,
+        after: LLVMBasicBlockRef,
+        value: LLVMValueRef,
+        has_value: bool,
+        incoming: &mut Vec<(LLVMBasicBlockRef, LLVMValueRef)>,
+    ) {
+        if has_value && is_void(unsafe { LLVMTypeOf(value) }) {
+            self.build_unreachable();
+        } else {
+            if has_value {
+                incoming.push((self.current_basic_block.unwrap(), value));
             }
-            ElseExpression::IfExpression(expr) => {
-                let val = self.build_if_expression(&expr);
-                self.build_br(after);
-                incoming.push((self.current_basic_block.unwrap(), val));
-                self.current_basic_block.unwrap()
+            self.build_br(after);
+        }
+    }
+
+    /// Builds a switch on the tag of an enum, or on the int or bool value being matched.
+    /// Each case tries the arms which can match it in order, checking the values inside
+    /// their patterns, and the type system makes sure one of them does.
+    /// The values bound by a pattern are stored in stack slots of the arm.
+    pub fn build_match(&mut self, match_expression: &MatchExpression) -> LLVMValueRef {
+        let value = self.build_expression(&match_expression.value);
+        let arms = &match_expression.arms;
+        let arm_blocks: Vec<LLVMBasicBlockRef> =
+            arms.iter().map(|_| self.basic_block("arm")).collect();
+        let mut slots: Vec<HashMap<String, LLVMValueRef>> = vec![HashMap::new(); arms.len()];
+
+        // The arms matching a single value are the cases of the switch
+        let keys: Vec<Option<LLVMValueRef>> = arms
+            .iter()
+            .map(|arm| self.case_value(&arm.pattern))
+            .collect();
+        let mut cases: Vec<LLVMValueRef> = Vec::new();
+        for key in keys.iter().flatten() {
+            if !cases.contains(key) {
+                cases.push(*key);
             }
-            ElseExpression::None => {
-                let noop_block = self.basic_block("noop");
-                incoming.push((noop_block, self.build_const_int(0)));
-                self.position_at_block_ref(noop_block);
-                self.build_br(after);
-                noop_block
+        }
+        let default = self.basic_block("default");
+        if cases.is_empty() {
+            self.build_br(default);
+        } else {
+            let is_enum = arms
+                .iter()
+                .any(|arm| matches!(arm.pattern.kind, PatternKind::Variant(_, _, _)));
+            unsafe {
+                let discriminant = if is_enum {
+                    LLVMBuildExtractValue(
+                        self.builder,
+                        value,
+                        0,
+                        self.module.borrow_mut().new_string_ptr("tag"),
+                    )
+                } else {
+                    value
+                };
+                let switch =
+                    LLVMBuildSwitch(self.builder, discriminant, default, cases.len() as c_uint);
+                for case in cases {
+                    let block = self.basic_block("case");
+                    LLVMAddCase(switch, case, block);
+                    self.position_at_block_ref(block);
+                    let candidates = (0..arms.len()).filter(|&i| match keys[i] {
+                        Some(key) => key == case,
+                        None => true,
+                    });
+                    self.build_arm_tests(candidates, arms, value, &arm_blocks, &mut slots);
+                }
             }
+        }
+        self.position_at_block_ref(default);
+        let candidates = (0..arms.len()).filter(|&i| keys[i].is_none());
+        self.build_arm_tests(candidates, arms, value, &arm_blocks, &mut slots);
+
+        // Falls back to no value when the type system has not been run
+        let result_type = match &match_expression.result_type {
+            Some(result_type) => self.types(result_type).to_llvm(self.context),
+            None => Types::Void.to_llvm(self.context),
         };
-        self.position_at_block_ref(current_basic_block);
-        self.build_cond_br(condition, if_block, else_block);
+        let has_value = !is_void(result_type);
+        let after = self.basic_block("after");
+        let mut incoming = Vec::new();
+        for ((arm, block), slots) in arms.iter().zip(arm_blocks).zip(slots) {
+            // Keeps the blocks in the order they are built
+            unsafe { LLVMMoveBasicBlockBefore(block, after) };
+            self.position_at_block_ref(block);
+            self.environment.variables.push(slots);
+            let body = self.build_expression(&arm.body);
+            self.environment.variables.pop();
+            self.end_branch(after, body, has_value, &mut incoming);
+        }
+
         self.position_at_block_ref(after);
-        self.build_phi(Types::Int, incoming)
+        if has_value {
+            self.build_phi_of_type(result_type, incoming)
+        } else {
+            self.build_void()
+        }
     }
 
+    /// The value of the switch case the pattern matches, if it only matches one
+    fn case_value(&mut self, pattern: &Pattern) -> Option<LLVMValueRef> {
+        match &pattern.kind {
+            PatternKind::Variant(enum_name, name, _) => Some(self.variant(enum_name, name).0),
+            PatternKind::Literal(literal) => Some(self.build_literal(literal)),
+            PatternKind::Wildcard | PatternKind::Binding(_) => None,
+        }
+    }
+
+    /// Tries the candidate arms in order, jumping to the first one whose pattern matches
+    fn build_arm_tests(
+        &mut self,
+        candidates: impl Iterator<Item = usize>,
+        arms: &[MatchArm],
+        value: LLVMValueRef,
+        arm_blocks: &[LLVMBasicBlockRef],
+        slots: &mut [HashMap<String, LLVMValueRef>],
+    ) {
+        for i in candidates {
+            let always_matches = match &arms[i].pattern.kind {
+                PatternKind::Variant(_, _, patterns) => patterns.iter().all(is_irrefutable),
+                _ => true,
+            };
+            let next = if always_matches {
+                None
+            } else {
+                Some(self.basic_block("next"))
+            };
+            let mut bindings = Vec::new();
+            self.build_pattern_fields(&arms[i].pattern, value, next, &mut bindings);
+            for (name, value) in bindings {
+                let slot = match slots[i].get(&name) {
+                    Some(slot) => *slot,
+                    None => {
+                        let slot = self.build_stack_slot(unsafe { LLVMTypeOf(value) }, &name);
+                        slots[i].insert(name, slot);
+                        slot
+                    }
+                };
+                self.build_store(value, slot);
+            }
+            self.build_br(arm_blocks[i]);
+            match next {
+                Some(next) => self.position_at_block_ref(next),
+                // The following arms can not be reached from here
+                None => return,
+            }
+        }
+        self.build_unreachable();
+    }
+
+    /// Checks that `value` matches the pattern, jumping to `fail` if it does not.
+    /// The values to bind are added to `bindings`.
+    fn build_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: LLVMValueRef,
+        fail: Option<LLVMBasicBlockRef>,
+        bindings: &mut Vec<(String, LLVMValueRef)>,
+    ) {
+        let condition = unsafe {
+            match &pattern.kind {
+                PatternKind::Variant(enum_name, name, _) => {
+                    let (tag, _) = self.variant(enum_name, name);
+                    let found = LLVMBuildExtractValue(
+                        self.builder,
+                        value,
+                        0,
+                        self.module.borrow_mut().new_string_ptr("tag"),
+                    );
+                    Some(LLVMBuildICmp(
+                        self.builder,
+                        LLVMIntPredicate::LLVMIntEQ,
+                        found,
+                        tag,
+                        self.module.borrow().empty_string(),
+                    ))
+                }
+                PatternKind::Literal(literal) => Some(LLVMBuildICmp(
+                    self.builder,
+                    LLVMIntPredicate::LLVMIntEQ,
+                    value,
+                    self.build_literal(literal),
+                    self.module.borrow().empty_string(),
+                )),
+                PatternKind::Wildcard | PatternKind::Binding(_) => None,
+            }
+        };
+        if let (Some(condition), Some(fail)) = (condition, fail) {
+            let matched = self.basic_block("matched");
+            self.build_cond_br(condition, matched, fail);
+            self.position_at_block_ref(matched);
+        }
+        self.build_pattern_fields(pattern, value, fail, bindings);
+    }
+
+    /// Like [`Function::build_pattern`], when `value` is known to be of the variant
+    /// or literal of the pattern
+    fn build_pattern_fields(
+        &mut self,
+        pattern: &Pattern,
+        value: LLVMValueRef,
+        fail: Option<LLVMBasicBlockRef>,
+        bindings: &mut Vec<(String, LLVMValueRef)>,
+    ) {
+        match &pattern.kind {
+            PatternKind::Variant(_, _, patterns) if patterns.is_empty() => (),
+            PatternKind::Variant(enum_name, name, patterns) => {
+                let (_, variant_type) = self.variant(enum_name, name);
+                // The payload can only be cast in memory
+                let slot = self.build_stack_slot(unsafe { LLVMTypeOf(value) }, name);
+                self.build_store(value, slot);
+                let payload = unsafe { self.build_payload_ptr(slot, variant_type) };
+                for (i, pattern) in patterns.iter().enumerate() {
+                    let field = unsafe {
+                        let ptr = LLVMBuildStructGEP(
+                            self.builder,
+                            payload,
+                            i as c_uint,
+                            self.module.borrow().empty_string(),
+                        );
+                        self.build_load(ptr)
+                    };
+                    self.build_pattern(pattern, field, fail, bindings);
+                }
+            }
+            PatternKind::Binding(name) => bindings.push((name.clone(), value)),
+            PatternKind::Wildcard | PatternKind::Literal(_) => (),
+        }
+    }
+
+    /// Builds `&&` when `is_and`, otherwise `||`.
+    /// The right operand is only evaluated when the left one does not decide the result.
+    pub fn build_short_circuit(
+        &mut self,
+        left: &Expression,
+        right: &Expression,
+        is_and: bool,
+    ) -> LLVMValueRef {
+        let left = self.build_expression(left);
+        unsafe {
+            // Folds constant operands, e.g. in the values of global constants
+            if !LLVMIsAConstantInt(left).is_null() {
+                let left_is_true = LLVMConstIntGetZExtValue(left) != 0;
+                return if left_is_true == is_and {
+                    self.build_expression(right)
+                } else {
+                    left
+                };
+            }
+        }
+
+        let left_block = self.current_basic_block.unwrap();
+        let right_block = self.basic_block(if is_and { "and" } else { "or" });
+        let after = self.basic_block("after");
+        if is_and {
+            self.build_cond_br(left, right_block, after);
+        } else {
+            self.build_cond_br(left, after, right_block);
+        }
+
+        self.position_at_block_ref(right_block);
+        let right = self.build_expression(right);
+        let right_block = self.current_basic_block.unwrap();
+        self.build_br(after);
+
+        self.position_at_block_ref(after);
+        let decided = unsafe { LLVMConstInt(Types::Bool.to_llvm(self.context), !is_and as u64, 0) };
+        self.build_phi_of_type(
+            Types::Bool.to_llvm(self.context),
+            vec![(left_block, decided), (right_block, right)],
+        )
+    }
+
+    /// Builds `while condition { body }`
+    pub fn build_while(&mut self, condition: &Expression, body: &Block) -> LLVMValueRef {
+        let condition_block = self.basic_block("while");
+        let body_block = self.basic_block("body");
+        let after = self.basic_block("after");
+        self.build_br(condition_block);
+        self.position_at_block_ref(condition_block);
+        let condition = self.build_expression(condition);
+        self.build_cond_br(condition, body_block, after);
+
+        self.position_at_block_ref(body_block);
+        self.loops.push(LoopTarget {
+            continue_block: condition_block,
+            break_block: after,
+            breaks: Vec::new(),
+        });
+        self.build_block(body);
+        self.loops.pop();
+        self.build_br(condition_block);
+        self.position_at_block_ref(after);
+        self.build_void()
+    }
+
+    /// Builds `loop { body }`, resulting in the value given to `break`
+    pub fn build_loop(&mut self, loop_expression: &LoopExpression) -> LLVMValueRef {
+        let body_block = self.basic_block("loop");
+        let after = self.basic_block("after");
+        self.build_br(body_block);
+        self.position_at_block_ref(body_block);
+        self.loops.push(LoopTarget {
+            continue_block: body_block,
+            break_block: after,
+            breaks: Vec::new(),
+        });
+        self.build_block(&loop_expression.body);
+        let target = self.loops.pop().unwrap();
+        self.build_br(body_block);
+        self.position_at_block_ref(after);
+
+        // Falls back to the type of the first break when the type system has not been run
+        let result_type = match &loop_expression.result_type {
+            Some(result_type) => self.types(result_type).to_llvm(self.context),
+            None => match target.breaks.first() {
+                Some((_, value)) => unsafe { LLVMTypeOf(*value) },
+                None => Types::Void.to_llvm(self.context),
+            },
+        };
+        if is_void(result_type) {
+            self.build_void()
+        } else {
+            self.build_phi_of_type(result_type, target.breaks)
+        }
+    }
+
+    /// Marks the current position as unreachable
+    pub fn build_unreachable(&mut self) -> LLVMValueRef {
+        unsafe { LLVMBuildUnreachable(self.builder) }
+    }
+
+    /// Continues in a new block which can never be reached,
+    /// as code can still follow `return`, `break` and `continue`.
+    fn build_dead_block(&mut self) -> LLVMValueRef {
+        let dead = self.basic_block("dead");
+        self.position_at_block_ref(dead);
+        self.build_void()
+    }
+
+    /// Builds the body of the function using `builder`
     pub fn build(&mut self, builder: &dyn Fn(&mut Function)) {
         builder(self);
 
         #[cfg(feature = "codegen-debug")]
-        println!("Error in {}: {}", self.name(), self.verify());
+        eprintln!("Error in {}: {}", self.name(), self.verify());
     }
 
+    /// Name of the function
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// Declares the variable in the innermost scope, with `value` pointing at its storage
     pub fn assign(&mut self, var: &str, value: LLVMValueRef) {
-        self.environment.variables.insert(var.to_string(), value);
+        self.environment
+            .variables
+            .last_mut()
+            .expect("There is always a scope")
+            .insert(var.to_string(), value);
     }
 
+    /// Finds the pointer to the variable in the innermost scope declaring it,
+    /// or else to a global variable
     pub fn var(&self, var: &str) -> Option<LLVMValueRef> {
-        match self.environment.variables.get(var) {
+        match self
+            .environment
+            .variables
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(var))
+        {
             Some(val) => Some(*val),
-            None => None,
+            None => self.environment.global_variables.borrow().get(var).copied(),
         }
     }
 
+    /// The llvm function
     pub fn value(&self) -> LLVMValueRef {
         self.value
     }
 
+    /// Builds a call of another function of the module, or of this one
     pub fn call_other(
         &self,
         function_name: &str,
@@ -317,7 +1222,8 @@ impl Function {
         name: &str,
     ) -> LLVMValueRef {
         unsafe {
-            println!("function_name: {}, self.name: {}", function_name, self.name);
+            #[cfg(feature = "codegen-debug")]
+            eprintln!("function_name: {}, self.name: {}", function_name, self.name);
             let other = if function_name == self.name {
                 self.value
             } else {
@@ -325,9 +1231,24 @@ impl Function {
                     .global
                     .borrow()
                     .get(function_name)
-                    .unwrap()
+                    .unwrap_or_else(|| panic!("Function '{}' has not been declared", function_name))
                     .value
             };
+            // Variadic arguments follow the C promotion rules, so floats are passed as doubles
+            let function_type = LLVMGetElementType(LLVMTypeOf(other));
+            if LLVMIsFunctionVarArg(function_type) != 0 {
+                let fixed = LLVMCountParamTypes(function_type) as usize;
+                for arg in args.iter_mut().skip(fixed) {
+                    if LLVMGetTypeKind(LLVMTypeOf(*arg)) == LLVMTypeKind::LLVMFloatTypeKind {
+                        *arg = LLVMBuildFPExt(
+                            self.builder,
+                            *arg,
+                            Types::Double.to_llvm(self.context),
+                            self.module.borrow().empty_string(),
+                        );
+                    }
+                }
+            }
             LLVMBuildCall(
                 self.builder,
                 other,
@@ -338,10 +1259,65 @@ impl Function {
         }
     }
 
+    /// The llvm type of the values the function returns
+    pub(crate) fn return_type(&self) -> LLVMTypeRef {
+        unsafe { LLVMGetReturnType(LLVMGetElementType(LLVMTypeOf(self.value))) }
+    }
+
+    /// The parameter of the function at `index`
     pub fn get_param(&mut self, index: c_uint) -> LLVMValueRef {
         unsafe { LLVMGetParam(self.value, index) }
     }
 
+    /// Stores the argument count and the arguments C passes to `main` in `argc` and `argv`.
+    /// The array `argv` gets as many of the arguments as fit, and null for the rest.
+    pub(crate) fn build_main_args(&mut self, argc: &ArgDecl, argv: &ArgDecl) {
+        let length = match &argv.arg_type {
+            Type::Array(_, length) => *length,
+            t => panic!("The arguments of main can not be stored in {}", t),
+        };
+        unsafe {
+            let int = Types::Int.to_llvm(self.context);
+            let count = LLVMBuildSExt(
+                self.builder,
+                self.get_param(0),
+                int,
+                self.module.borrow().empty_string(),
+            );
+            let argc_ptr = self.build_stack_ptr(Types::Int, &argc.name);
+            self.assign(&argc.name, argc_ptr);
+            self.build_store(count, argc_ptr);
+
+            let argv_type = self.types(&argv.arg_type).to_llvm(self.context);
+            let argv_ptr = self.build_stack_slot(argv_type, &argv.name);
+            self.assign(&argv.name, argv_ptr);
+            self.build_store(LLVMConstNull(argv_type), argv_ptr);
+            let length = LLVMConstInt(int, length as u64, 0);
+            let fits = LLVMBuildICmp(
+                self.builder,
+                LLVMIntPredicate::LLVMIntSLT,
+                count,
+                length,
+                self.module.borrow().empty_string(),
+            );
+            let copied = LLVMBuildSelect(
+                self.builder,
+                fits,
+                count,
+                length,
+                self.module.borrow().empty_string(),
+            );
+            let size = LLVMBuildMul(
+                self.builder,
+                copied,
+                LLVMSizeOf(Types::String.to_llvm(self.context)),
+                self.module.borrow().empty_string(),
+            );
+            LLVMBuildMemCpy(self.builder, argv_ptr, 8, self.get_param(1), 8, size);
+        }
+    }
+
+    /// Checks that the generated function is valid llvm IR
     #[cfg(feature = "codegen-debug")]
     pub fn verify(&self) -> bool {
         unsafe {
@@ -353,6 +1329,25 @@ impl Function {
     }
 }
 
+pub(crate) fn is_void(value_type: LLVMTypeRef) -> bool {
+    unsafe { LLVMGetTypeKind(value_type) == LLVMTypeKind::LLVMVoidTypeKind }
+}
+
+/// Whether the pattern matches any value of its type
+fn is_irrefutable(pattern: &Pattern) -> bool {
+    matches!(
+        pattern.kind,
+        PatternKind::Wildcard | PatternKind::Binding(_)
+    )
+}
+
+fn is_floating_point(value: LLVMValueRef) -> bool {
+    matches!(
+        unsafe { LLVMGetTypeKind(LLVMTypeOf(value)) },
+        LLVMTypeKind::LLVMFloatTypeKind | LLVMTypeKind::LLVMDoubleTypeKind
+    )
+}
+
 fn build_function(codegen: &mut Codegen, function_type: Types, name: &str) -> LLVMValueRef {
     let mut module = codegen.module.borrow_mut();
     unsafe {
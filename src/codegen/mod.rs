@@ -1,6 +1,11 @@
-//! This module takes care of converting the AST into llvm IR
+//! This module takes care of converting the AST into llvm IR.
+//!
+//! The generated module can be serialised as textual IR (`.ll`) using
+//! [`Codegen::print_to_string`] and [`Codegen::print_to_file`], or as bitcode (`.bc`)
+//! using [`Codegen::write_bitcode_to_memory`] and [`Codegen::write_bitcode_to_file`].
 
 use crate::parser::ast::*;
+use crate::span::{SourceMap, Span};
 #[cfg(feature = "codegen-debug")]
 use llvm::analysis::*;
 use llvm::core::*;
@@ -10,17 +15,23 @@ use std::collections::HashMap;
 use std::ffi::CString;
 use std::ops::Deref;
 use std::os::raw::c_uint;
+use std::path::Path;
 use std::rc::Rc;
 
 pub mod error;
 pub mod function;
 pub mod module;
+pub mod optimization;
 mod traits;
 pub mod types;
 
+#[cfg(test)]
+mod test;
+
 use traits::Builder;
-use types::Types;
+use types::{StructType, Types, Variant};
 
+/// The functions of the module, by name
 #[derive(Clone)]
 pub struct Environment(Rc<RefCell<HashMap<String, function::Function>>>);
 
@@ -38,14 +49,31 @@ impl Deref for Environment {
     }
 }
 
+/// Pointers to the global variables of the module
+pub type Globals = Rc<RefCell<HashMap<String, LLVMValueRef>>>;
+
+/// The llvm types of the structs and enums of the module
+pub type Structs = Rc<RefCell<HashMap<String, StructType>>>;
+
+/// Name of the generated function initializing the global variables
+pub const MODULE_INIT: &str = "__poop_module_init";
+
+/// Generates an llvm module from a checked program
 pub struct Codegen {
     context: LLVMContextRef,
     module: Rc<RefCell<module::Module>>,
     builder: LLVMBuilderRef,
     environment: Environment,
+    globals: Globals,
+    structs: Structs,
+    /// Used for describing where runtime errors happen
+    source_map: Option<Rc<SourceMap>>,
+    /// Called at the start of `main`, if any global needs to be initialized at runtime
+    module_init: Option<function::Function>,
 }
 
 impl Codegen {
+    /// Creates a code generator for a new module in its own llvm context
     pub fn new(module_name: &str) -> Self {
         unsafe {
             let context = LLVMContextCreate();
@@ -53,7 +81,12 @@ impl Codegen {
         }
     }
 
-    pub fn new_with_context(context: LLVMContextRef, module_name: &str) -> Self {
+    /// Like [`Codegen::new`], but builds the module in an existing context.
+    ///
+    /// # Safety
+    ///
+    /// `context` must be a valid llvm context, which outlives the codegen.
+    pub unsafe fn new_with_context(context: LLVMContextRef, module_name: &str) -> Self {
         unsafe {
             let module = Rc::new(RefCell::new(module::Module::new(context, module_name)));
             let builder = LLVMCreateBuilderInContext(context);
@@ -63,36 +96,90 @@ impl Codegen {
                 module,
                 builder,
                 environment: Environment::default(),
+                globals: Globals::default(),
+                structs: Structs::default(),
+                source_map: None,
+                module_init: None,
             }
         }
     }
 
+    /// Sets the source files of the program, so runtime errors can tell the file, line
+    /// and column they happen at. Otherwise only the byte offsets are known.
+    pub fn with_source_map(mut self, source_map: Rc<SourceMap>) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+
+    /// The module being generated
     pub fn module(&self) -> Rc<RefCell<module::Module>> {
         self.module.clone()
     }
 
-    fn build_function(&mut self, function: FuncDecl) {
-        let ft = Types::Func(
-            Box::new(function.return_type.clone().into()),
-            function
-                .args
-                .iter()
-                .map(|arg| arg.arg_type.clone().into())
-                .collect(),
-            false,
-        );
+    /// Optimizes the module using the standard passes of the optimization level
+    pub fn optimize(&self, level: optimization::OptimizationLevel) {
+        optimization::PassManager::new(level).run(&self.module.borrow());
+    }
+
+    /// Runs the given optimization passes on the module in order.
+    /// See [`optimization::PASSES`] for the available passes.
+    pub fn run_passes(&self, passes: &[&str]) -> Result<(), error::CodegenError> {
+        optimization::PassManager::with_passes(passes)?.run(&self.module.borrow());
+        Ok(())
+    }
+
+    /// Returns the module as textual llvm IR
+    pub fn print_to_string(&self) -> String {
+        self.module.borrow().print_to_string()
+    }
+
+    /// Writes the module as textual llvm IR to `path`
+    pub fn print_to_file(&self, path: &Path) -> Result<(), error::CodegenError> {
+        self.module.borrow().print_to_file(path)
+    }
+
+    /// Returns the module as llvm bitcode
+    pub fn write_bitcode_to_memory(&self) -> Vec<u8> {
+        self.module.borrow().write_bitcode_to_memory()
+    }
+
+    /// Writes the module as llvm bitcode to `path`
+    pub fn write_bitcode_to_file(&self, path: &Path) -> Result<(), error::CodegenError> {
+        self.module.borrow().write_bitcode_to_file(path)
+    }
 
-        let mut func = function::Function::new(self, ft, &function.name);
+    /// Builds the body of a declared function. Methods get a pointer to their object
+    /// as the first parameter, which is used as the place of `self`.
+    fn build_function(&mut self, function: FuncDecl, method: bool) {
+        let mut func = self
+            .environment
+            .borrow_mut()
+            .remove(&function.name)
+            .expect("Functions are declared before they are built");
+        let module_init = if function.name == "main" {
+            self.module_init.take()
+        } else {
+            None
+        };
         func.build(&|func| {
             // Function setup
             let entry = func.basic_block("entry");
             func.position_at_block_ref(entry);
-            for (i, arg) in function.args.iter().enumerate() {
-                println!("Setting param: {} to name: {}", i, &arg.name);
-                let ptr = func.build_stack_ptr(arg.arg_type.clone().into(), &arg.name);
-                let val = func.get_param(i as u32);
-                func.assign(&arg.name, ptr);
-                func.build_store(val, ptr);
+            if method {
+                let object = func.get_param(0);
+                func.assign("self", object);
+            }
+            if function.name == "main" && !function.args.is_empty() {
+                func.build_main_args(&function.args[0], &function.args[1]);
+            } else {
+                for (i, arg) in function.args.iter().enumerate() {
+                    #[cfg(feature = "codegen-debug")]
+                    eprintln!("Setting param: {} to name: {}", i, &arg.name);
+                    let ptr = func.build_stack_ptr(func.types(&arg.arg_type), &arg.name);
+                    let val = func.get_param(i as u32 + method as u32);
+                    func.assign(&arg.name, ptr);
+                    func.build_store(val, ptr);
+                }
             }
 
             // Actual body
@@ -100,9 +187,15 @@ impl Codegen {
             // Transition to new bb
             func.build_br(block);
             func.position_at_block_ref(block);
+            if let Some(module_init) = &module_init {
+                module_init.call(&mut [], "");
+            }
             let ret = func.build_block(&function.body);
             if let Type::Void = function.return_type {
                 func.build_ret_void();
+            } else if function::is_void(unsafe { LLVMTypeOf(ret) }) {
+                // The body ends with e.g. `return`, so the end is never reached
+                func.build_unreachable();
             } else {
                 func.build_ret(ret);
             }
@@ -111,6 +204,263 @@ impl Codegen {
         self.environment.borrow_mut().insert(function.name, func);
     }
 
+    /// Declares all the functions of the program, so they can be called in any order
+    fn declare_program(&mut self, program: &Program) {
+        self.declare_types(program);
+
+        let mut program = program;
+        while let Program::Decl(decl, rest) = program {
+            match decl {
+                // `main` returns the exit code and gets the arguments as C passes them
+                Decl::FuncDecl(func_decl) if func_decl.name == "main" => {
+                    let function_type = if func_decl.args.is_empty() {
+                        Types::main()
+                    } else {
+                        Types::main_with_args()
+                    };
+                    self.declare_function(function_type, &func_decl.name)
+                }
+                Decl::FuncDecl(func_decl) => {
+                    let function_type =
+                        self.function_type(&func_decl.args, &func_decl.return_type, false);
+                    self.declare_function(function_type, &func_decl.name)
+                }
+                Decl::ExternDecl(extern_decl) => {
+                    let function_type = self.function_type(
+                        &extern_decl.args,
+                        &extern_decl.return_type,
+                        extern_decl.variadic,
+                    );
+                    self.declare_function(function_type, &extern_decl.name)
+                }
+                Decl::ClassDecl(class_decl) => {
+                    if let Some(constructor) = &class_decl.constructor {
+                        let function_type =
+                            self.function_type(&constructor.args, &constructor.return_type, false);
+                        let name = ClassDecl::mangle(&class_decl.name, &constructor.name);
+                        self.declare_function(function_type, &name)
+                    }
+                    for method in class_decl.methods.iter() {
+                        let function_type = self.method_type(&class_decl.name, &method.func_decl);
+                        let name = ClassDecl::mangle(&class_decl.name, &method.func_decl.name);
+                        self.declare_function(function_type, &name)
+                    }
+                }
+                Decl::VarDecl(var_decl) | Decl::ConstDecl(var_decl) => {
+                    self.declare_global(var_decl)
+                }
+                Decl::StructDecl(_) | Decl::EnumDecl(_) | Decl::Error(_) => (),
+            }
+            program = rest;
+        }
+    }
+
+    /// Creates a named llvm struct for every struct, class, enum and variant of an enum.
+    /// The bodies are set once all of them are created, as the fields can be of any of the types.
+    fn declare_types(&mut self, program: &Program) {
+        let mut layouts: Vec<(&String, Layout)> = Vec::new();
+        let mut program = program;
+        while let Program::Decl(decl, rest) = program {
+            match decl {
+                Decl::StructDecl(struct_decl) => layouts.push((
+                    &struct_decl.name,
+                    Layout::Struct(
+                        struct_decl
+                            .fields
+                            .iter()
+                            .map(|field| (&field.name, &field.arg_type))
+                            .collect(),
+                    ),
+                )),
+                Decl::ClassDecl(class_decl) => layouts.push((
+                    &class_decl.name,
+                    Layout::Struct(
+                        class_decl
+                            .fields
+                            .iter()
+                            .map(|field| (&field.name, &field.arg_type))
+                            .collect(),
+                    ),
+                )),
+                Decl::EnumDecl(enum_decl) => layouts.push((
+                    &enum_decl.name,
+                    Layout::Enum(
+                        enum_decl
+                            .variants
+                            .iter()
+                            .map(|variant| (&variant.name, variant.fields.iter().collect()))
+                            .collect(),
+                    ),
+                )),
+                _ => (),
+            }
+            program = rest;
+        }
+
+        let mut bodies: Vec<(LLVMTypeRef, Vec<Type>)> = Vec::new();
+        for (name, layout) in layouts.iter() {
+            let llvm_type = self.create_named_struct(name);
+            let struct_type = match layout {
+                Layout::Struct(fields) => {
+                    bodies.push((
+                        llvm_type,
+                        fields.iter().map(|(_, field)| (*field).clone()).collect(),
+                    ));
+                    StructType {
+                        llvm_type,
+                        fields: fields.iter().map(|(name, _)| (*name).clone()).collect(),
+                        variants: Vec::new(),
+                    }
+                }
+                Layout::Enum(variants) => {
+                    let payload =
+                        Type::Array(Box::new(Type::Int), payload_words(variants, &layouts));
+                    bodies.push((llvm_type, vec![Type::Int, payload]));
+                    let mut enum_variants = Vec::new();
+                    for (variant_name, fields) in variants.iter() {
+                        let variant_type =
+                            self.create_named_struct(&format!("{}.{}", name, variant_name));
+                        bodies.push((
+                            variant_type,
+                            fields.iter().map(|field| (*field).clone()).collect(),
+                        ));
+                        enum_variants.push(Variant {
+                            name: (*variant_name).clone(),
+                            llvm_type: variant_type,
+                        });
+                    }
+                    StructType {
+                        llvm_type,
+                        fields: Vec::new(),
+                        variants: enum_variants,
+                    }
+                }
+            };
+            self.structs
+                .borrow_mut()
+                .insert((*name).clone(), struct_type);
+        }
+
+        let structs = self.structs.borrow();
+        for (llvm_type, field_types) in bodies {
+            let mut fields: Vec<LLVMTypeRef> = field_types
+                .into_iter()
+                .map(|field_type| Types::from_type(field_type, &structs).to_llvm(self.context))
+                .collect();
+            unsafe { LLVMStructSetBody(llvm_type, fields.as_mut_ptr(), fields.len() as c_uint, 0) };
+        }
+    }
+
+    fn create_named_struct(&mut self, name: &str) -> LLVMTypeRef {
+        unsafe {
+            LLVMStructCreateNamed(self.context, self.module.borrow_mut().new_string_ptr(name))
+        }
+    }
+
+    fn declare_global(&mut self, var_decl: &VarDecl) {
+        let global_type = Types::from_type(
+            var_decl
+                .var_type
+                .clone()
+                .expect("The types of global variables are inferred by the type system"),
+            &self.structs.borrow(),
+        );
+        let global = unsafe {
+            let mut module = self.module.borrow_mut();
+            LLVMAddGlobal(
+                module.module,
+                global_type.to_llvm(self.context),
+                module.new_string_ptr(&var_decl.identifier),
+            )
+        };
+        self.globals
+            .borrow_mut()
+            .insert(var_decl.identifier.clone(), global);
+    }
+
+    /// Builds the values of the global variables. Constant values become the initializers
+    /// of the globals, while the rest are stored by the module initializer at runtime.
+    fn build_module_init(&mut self, program: &Program) {
+        let mut globals = Vec::new();
+        let mut program = program;
+        while let Program::Decl(decl, rest) = program {
+            match decl {
+                Decl::VarDecl(var_decl) => globals.push((var_decl, false)),
+                Decl::ConstDecl(var_decl) => globals.push((var_decl, true)),
+                _ => (),
+            }
+            program = rest;
+        }
+        if globals.is_empty() {
+            return;
+        }
+
+        let init_type = Types::Func(Box::new(Types::Void), Vec::new(), false);
+        let mut init = function::Function::new(self, init_type, MODULE_INIT);
+        let runtime = std::cell::Cell::new(false);
+        init.build(&|func| {
+            let entry = func.basic_block("entry");
+            func.position_at_block_ref(entry);
+            for (var_decl, constant) in globals.iter() {
+                let global = func
+                    .var(&var_decl.identifier)
+                    .expect("Global variables are declared before they are built");
+                let value = func.build_expression(&var_decl.expression);
+                unsafe {
+                    if LLVMIsConstant(value) != 0 {
+                        LLVMSetInitializer(global, value);
+                        LLVMSetGlobalConstant(global, *constant as LLVMBool);
+                    } else {
+                        assert!(
+                            !*constant,
+                            "The values of constants are known at compile time"
+                        );
+                        LLVMSetInitializer(global, LLVMConstNull(LLVMTypeOf(value)));
+                        func.build_store(value, global);
+                        runtime.set(true);
+                    }
+                }
+            }
+            func.build_ret_void();
+        });
+
+        if runtime.get() {
+            unsafe { LLVMSetLinkage(init.value(), llvm::LLVMLinkage::LLVMInternalLinkage) };
+            self.module_init = Some(init);
+        } else {
+            unsafe { LLVMDeleteFunction(init.value()) };
+        }
+    }
+
+    fn function_type(&self, args: &[ArgDecl], return_type: &Type, variadic: bool) -> Types {
+        let structs = self.structs.borrow();
+        Types::Func(
+            Box::new(Types::from_type(return_type.clone(), &structs)),
+            args.iter()
+                .map(|arg| Types::from_type(arg.arg_type.clone(), &structs))
+                .collect(),
+            variadic,
+        )
+    }
+
+    /// The type of the function implementing a method, taking a pointer to the object first
+    fn method_type(&self, class: &str, method: &FuncDecl) -> Types {
+        let structs = self.structs.borrow();
+        let object = Types::from_type(Type::UserDefined(class.to_string()), &structs);
+        Types::Func(
+            Box::new(Types::from_type(method.return_type.clone(), &structs)),
+            std::iter::once(Types::Pointer(Box::new(object)))
+                .chain(
+                    method
+                        .args
+                        .iter()
+                        .map(|arg| Types::from_type(arg.arg_type.clone(), &structs)),
+                )
+                .collect(),
+            false,
+        )
+    }
+
     fn declare_function(&mut self, function_type: Types, name: &str) {
         let func = function::Function::new(self, function_type, name);
         self.environment
@@ -121,10 +471,30 @@ impl Codegen {
     fn build_program_internal(&mut self, program: Program) {
         match program {
             Program::Decl(Decl::FuncDecl(func_decl), next) => {
-                self.build_function(func_decl);
+                self.build_function(func_decl, false);
+                self.build_program_internal(*next);
+            }
+            Program::Decl(Decl::ClassDecl(class_decl), next) => {
+                if let Some(mut constructor) = class_decl.constructor {
+                    constructor.name = ClassDecl::mangle(&class_decl.name, &constructor.name);
+                    self.build_function(constructor, false);
+                }
+                for method in class_decl.methods {
+                    let mut func_decl = method.func_decl;
+                    func_decl.name = ClassDecl::mangle(&class_decl.name, &func_decl.name);
+                    self.build_function(func_decl, true);
+                }
                 self.build_program_internal(*next);
             }
-            Program::Decl(_, _) => panic!("Global variables are not yet supported"),
+            // Declared and initialized before the functions are built
+            Program::Decl(Decl::ExternDecl(_), next)
+            | Program::Decl(Decl::StructDecl(_), next)
+            | Program::Decl(Decl::EnumDecl(_), next)
+            | Program::Decl(Decl::VarDecl(_), next)
+            | Program::Decl(Decl::ConstDecl(_), next) => self.build_program_internal(*next),
+            Program::Decl(Decl::Error(_), _) => {
+                panic!("Programs with syntax errors can not be compiled")
+            }
             Program::Empty => (),
         }
     }
@@ -155,16 +525,10 @@ impl Codegen {
         //    func.build_ret(ret);
         //});
 
-        self.declare_function(Types::printf(), "printf");
-
+        self.declare_program(&program);
+        self.build_module_init(&program);
         self.build_program_internal(program);
 
-        #[cfg(feature = "codegen-debug")]
-        {
-            unsafe {
-                LLVMDumpModule(self.module.borrow().module);
-            }
-        }
         self.environment
             .borrow()
             .get("main")
@@ -182,10 +546,43 @@ impl Drop for Codegen {
     }
 }
 
-fn to_cstring(input: &str) -> CString {
-    CString::new(input).expect("CString::new failed")
+/// The fields of a struct or the variants of an enum, used to size the payloads of enums
+enum Layout<'a> {
+    Struct(Vec<(&'a String, &'a Type)>),
+    Enum(Vec<(&'a String, Vec<&'a Type>)>),
+}
+
+/// The number of 8 byte words the payload of an enum needs for its largest variant.
+/// No value is bigger or more aligned than a word, so this holds for any data layout.
+fn payload_words(variants: &[(&String, Vec<&Type>)], layouts: &[(&String, Layout)]) -> usize {
+    variants
+        .iter()
+        .map(|(_, fields)| {
+            fields
+                .iter()
+                .map(|field| size_in_words(field, layouts))
+                .sum()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn size_in_words(value_type: &Type, layouts: &[(&String, Layout)]) -> usize {
+    match value_type {
+        Type::Array(element_type, length) => length * size_in_words(element_type, layouts),
+        Type::UserDefined(name) => match layouts.iter().find(|(other, _)| *other == name) {
+            Some((_, Layout::Struct(fields))) => fields
+                .iter()
+                .map(|(_, field)| size_in_words(field, layouts))
+                .sum(),
+            Some((_, Layout::Enum(variants))) => 1 + payload_words(variants, layouts),
+            None => panic!("Type '{}' has not been declared", name),
+        },
+        Type::Void | Type::Never => 0,
+        _ => 1,
+    }
 }
 
-fn to_ptr(input: &str) -> *const i8 {
-    to_cstring(input).as_ptr() as *const _
+fn to_cstring(input: &str) -> CString {
+    CString::new(input).expect("CString::new failed")
 }
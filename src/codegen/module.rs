@@ -1,13 +1,22 @@
+//! Wrapper of the llvm module, which keeps the strings passed to llvm alive.
+
 use super::*;
+use llvm::bit_writer::{LLVMWriteBitcodeToFile, LLVMWriteBitcodeToMemoryBuffer};
+use std::ffi::CStr;
+use std::path::Path;
+use std::ptr;
+use std::slice;
 
+/// An llvm module, along with the strings given to llvm while building it
 pub struct Module {
+    /// The llvm module
     pub module: LLVMModuleRef,
     strings: Vec<CString>,
     empty_string: CString,
 }
 
 impl Module {
-    pub fn new(context: LLVMContextRef, name: &str) -> Self {
+    pub(crate) fn new(context: LLVMContextRef, name: &str) -> Self {
         let c_name = to_cstring(name);
 
         let llvm_module;
@@ -22,6 +31,7 @@ impl Module {
         }
     }
 
+    /// Copies the string into a C string, which lives as long as the module
     pub fn new_string_ptr(&mut self, value: &str) -> *const i8 {
         if value.is_empty() {
             return self.empty_string();
@@ -33,9 +43,71 @@ impl Module {
         ptr
     }
 
+    /// An empty C string, used for unnamed values
     pub fn empty_string(&self) -> *const i8 {
         self.empty_string.as_ptr()
     }
+
+    /// Serialises the module as textual IR
+    pub fn print_to_string(&self) -> String {
+        unsafe {
+            let ptr = LLVMPrintModuleToString(self.module);
+            let ir = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+            LLVMDisposeMessage(ptr);
+            ir
+        }
+    }
+
+    /// Writes the module as textual IR (`.ll`) to the file
+    pub fn print_to_file(&self, path: &Path) -> Result<(), error::CodegenError> {
+        let c_path = path_to_cstring(path)?;
+        unsafe {
+            let mut message = ptr::null_mut();
+            if LLVMPrintModuleToFile(self.module, c_path.as_ptr(), &mut message) != 0 {
+                let reason = CStr::from_ptr(message).to_string_lossy().into_owned();
+                LLVMDisposeMessage(message);
+                return Err(error::CodegenError::error(format!(
+                    "Could not write IR to '{}': {}",
+                    path.display(),
+                    reason
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Serialises the module as bitcode
+    pub fn write_bitcode_to_memory(&self) -> Vec<u8> {
+        unsafe {
+            let buffer = LLVMWriteBitcodeToMemoryBuffer(self.module);
+            let bitcode = slice::from_raw_parts(
+                LLVMGetBufferStart(buffer) as *const u8,
+                LLVMGetBufferSize(buffer),
+            )
+            .to_vec();
+            LLVMDisposeMemoryBuffer(buffer);
+            bitcode
+        }
+    }
+
+    /// Writes the module as bitcode (`.bc`) to the file
+    pub fn write_bitcode_to_file(&self, path: &Path) -> Result<(), error::CodegenError> {
+        let c_path = path_to_cstring(path)?;
+        if unsafe { LLVMWriteBitcodeToFile(self.module, c_path.as_ptr()) } == 0 {
+            Ok(())
+        } else {
+            Err(error::CodegenError::error(format!(
+                "Could not write bitcode to '{}'",
+                path.display()
+            )))
+        }
+    }
+}
+
+fn path_to_cstring(path: &Path) -> Result<CString, error::CodegenError> {
+    CString::new(path.to_string_lossy().as_bytes()).map_err(|_| {
+        error::CodegenError::error(format!("Path '{}' contains a null byte", path.display()))
+    })
 }
 
 //impl Drop for Module {
@@ -0,0 +1,262 @@
+//! Optimization of the generated llvm IR, either using one of the standard
+//! optimization levels or by running a hand picked list of passes.
+
+use super::error::CodegenError;
+use super::module::Module;
+use llvm::core::*;
+use llvm::prelude::*;
+use llvm::target_machine::LLVMCodeGenOptLevel;
+use llvm::transforms::instcombine::LLVMAddInstructionCombiningPass;
+use llvm::transforms::ipo::*;
+use llvm::transforms::pass_manager_builder::*;
+use llvm::transforms::scalar::*;
+use llvm::transforms::util::*;
+use std::fmt;
+use std::os::raw::c_uint;
+
+/// The optimization levels, corresponding to the `-O` flags of clang
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OptimizationLevel {
+    /// No optimizations
+    #[default]
+    O0,
+    /// Few optimizations
+    O1,
+    /// Default optimizations
+    O2,
+    /// Aggressive optimizations
+    O3,
+    /// Like `O2`, but optimizing for size
+    Os,
+}
+
+impl OptimizationLevel {
+    /// Parses the level without the `-O` prefix, e.g. `"2"` or `"s"`
+    pub fn parse(level: &str) -> Option<Self> {
+        match level {
+            "0" => Some(OptimizationLevel::O0),
+            "1" => Some(OptimizationLevel::O1),
+            "2" => Some(OptimizationLevel::O2),
+            "3" => Some(OptimizationLevel::O3),
+            "s" => Some(OptimizationLevel::Os),
+            _ => None,
+        }
+    }
+
+    fn opt_level(self) -> c_uint {
+        match self {
+            OptimizationLevel::O0 => 0,
+            OptimizationLevel::O1 => 1,
+            OptimizationLevel::O2 | OptimizationLevel::Os => 2,
+            OptimizationLevel::O3 => 3,
+        }
+    }
+
+    fn size_level(self) -> c_uint {
+        match self {
+            OptimizationLevel::Os => 1,
+            _ => 0,
+        }
+    }
+
+    /// The inliner threshold used by clang for this level, if the inliner is used at all
+    fn inline_threshold(self) -> Option<c_uint> {
+        match self {
+            OptimizationLevel::O0 | OptimizationLevel::O1 => None,
+            OptimizationLevel::O2 => Some(225),
+            OptimizationLevel::O3 => Some(275),
+            OptimizationLevel::Os => Some(75),
+        }
+    }
+
+    /// The matching optimization level for the code generator of a target machine
+    pub fn codegen_level(self) -> LLVMCodeGenOptLevel {
+        match self {
+            OptimizationLevel::O0 => LLVMCodeGenOptLevel::LLVMCodeGenLevelNone,
+            OptimizationLevel::O1 => LLVMCodeGenOptLevel::LLVMCodeGenLevelLess,
+            OptimizationLevel::O2 | OptimizationLevel::Os => {
+                LLVMCodeGenOptLevel::LLVMCodeGenLevelDefault
+            }
+            OptimizationLevel::O3 => LLVMCodeGenOptLevel::LLVMCodeGenLevelAggressive,
+        }
+    }
+}
+
+impl fmt::Display for OptimizationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptimizationLevel::O0 => write!(f, "-O0"),
+            OptimizationLevel::O1 => write!(f, "-O1"),
+            OptimizationLevel::O2 => write!(f, "-O2"),
+            OptimizationLevel::O3 => write!(f, "-O3"),
+            OptimizationLevel::Os => write!(f, "-Os"),
+        }
+    }
+}
+
+type AddPass = unsafe extern "C" fn(LLVMPassManagerRef);
+
+/// All the passes that can be selected individually, using the same names as `opt`
+pub const PASSES: &[(&str, &str, AddPass)] = &[
+    (
+        "mem2reg",
+        "Promote stack variables to registers",
+        LLVMAddPromoteMemoryToRegisterPass,
+    ),
+    (
+        "sroa",
+        "Scalar replacement of aggregates",
+        LLVMAddScalarReplAggregatesPass,
+    ),
+    (
+        "instcombine",
+        "Combine redundant instructions",
+        LLVMAddInstructionCombiningPass,
+    ),
+    (
+        "reassociate",
+        "Reassociate expressions",
+        LLVMAddReassociatePass,
+    ),
+    ("gvn", "Global value numbering", LLVMAddGVNPass),
+    (
+        "simplifycfg",
+        "Simplify the control flow graph",
+        LLVMAddCFGSimplificationPass,
+    ),
+    ("dce", "Dead code elimination", LLVMAddDCEPass),
+    (
+        "adce",
+        "Aggressive dead code elimination",
+        LLVMAddAggressiveDCEPass,
+    ),
+    (
+        "dse",
+        "Dead store elimination",
+        LLVMAddDeadStoreEliminationPass,
+    ),
+    (
+        "early-cse",
+        "Early common subexpression elimination",
+        LLVMAddEarlyCSEPass,
+    ),
+    (
+        "sccp",
+        "Sparse conditional constant propagation",
+        LLVMAddSCCPPass,
+    ),
+    ("jump-threading", "Jump threading", LLVMAddJumpThreadingPass),
+    (
+        "correlated-propagation",
+        "Value propagation",
+        LLVMAddCorrelatedValuePropagationPass,
+    ),
+    ("memcpyopt", "Optimize memory copies", LLVMAddMemCpyOptPass),
+    (
+        "tailcallelim",
+        "Tail call elimination",
+        LLVMAddTailCallEliminationPass,
+    ),
+    ("licm", "Loop invariant code motion", LLVMAddLICMPass),
+    ("loop-rotate", "Rotate loops", LLVMAddLoopRotatePass),
+    (
+        "loop-deletion",
+        "Delete dead loops",
+        LLVMAddLoopDeletionPass,
+    ),
+    ("loop-unroll", "Unroll loops", LLVMAddLoopUnrollPass),
+    (
+        "indvars",
+        "Canonicalize induction variables",
+        LLVMAddIndVarSimplifyPass,
+    ),
+    ("inline", "Inline functions", LLVMAddFunctionInliningPass),
+    (
+        "always-inline",
+        "Inline functions marked always inline",
+        LLVMAddAlwaysInlinerPass,
+    ),
+    ("globaldce", "Dead global elimination", LLVMAddGlobalDCEPass),
+    ("globalopt", "Optimize globals", LLVMAddGlobalOptimizerPass),
+    (
+        "constmerge",
+        "Merge duplicate constants",
+        LLVMAddConstantMergePass,
+    ),
+    (
+        "ipsccp",
+        "Interprocedural sparse conditional constant propagation",
+        LLVMAddIPSCCPPass,
+    ),
+    (
+        "deadargelim",
+        "Dead argument elimination",
+        LLVMAddDeadArgEliminationPass,
+    ),
+    (
+        "function-attrs",
+        "Deduce function attributes",
+        LLVMAddFunctionAttrsPass,
+    ),
+    ("verify", "Verify the module", LLVMAddVerifierPass),
+];
+
+/// Wrapper around an llvm module pass manager
+pub struct PassManager {
+    pass_manager: LLVMPassManagerRef,
+}
+
+impl PassManager {
+    /// Creates a pass manager with the standard passes for the optimization level
+    pub fn new(level: OptimizationLevel) -> Self {
+        unsafe {
+            let pass_manager = LLVMCreatePassManager();
+            let builder = LLVMPassManagerBuilderCreate();
+            LLVMPassManagerBuilderSetOptLevel(builder, level.opt_level());
+            LLVMPassManagerBuilderSetSizeLevel(builder, level.size_level());
+            match level.inline_threshold() {
+                Some(threshold) => {
+                    LLVMPassManagerBuilderUseInlinerWithThreshold(builder, threshold)
+                }
+                None => LLVMAddAlwaysInlinerPass(pass_manager),
+            }
+            LLVMPassManagerBuilderPopulateModulePassManager(builder, pass_manager);
+            LLVMPassManagerBuilderDispose(builder);
+
+            Self { pass_manager }
+        }
+    }
+
+    /// Creates a pass manager running exactly the given passes in order.
+    /// See [`PASSES`] for the names of the available passes.
+    pub fn with_passes(passes: &[&str]) -> Result<Self, CodegenError> {
+        let pass_manager = Self {
+            pass_manager: unsafe { LLVMCreatePassManager() },
+        };
+        for name in passes {
+            match PASSES.iter().find(|(pass, _, _)| pass == name) {
+                Some((_, _, add_pass)) => unsafe { add_pass(pass_manager.pass_manager) },
+                None => {
+                    return Err(CodegenError::error(format!(
+                        "Unknown optimization pass '{}'",
+                        name
+                    )))
+                }
+            }
+        }
+        Ok(pass_manager)
+    }
+
+    /// Runs the passes on the module. Returns whether the module was modified.
+    pub fn run(&self, module: &Module) -> bool {
+        unsafe { LLVMRunPassManager(self.pass_manager, module.module) != 0 }
+    }
+}
+
+impl Drop for PassManager {
+    fn drop(&mut self) {
+        unsafe {
+            LLVMDisposePassManager(self.pass_manager);
+        }
+    }
+}
@@ -0,0 +1,501 @@
+use super::*;
+use crate::lexer::{wrapper::LexerWrapper, Token};
+use crate::parser::Parser;
+use crate::type_system::TypeSystem;
+use logos::Logos;
+use std::env;
+use std::fs;
+
+fn codegen(program: &'static str) -> Codegen {
+    let mut parser = Parser::new(LexerWrapper(Token::lexer(program)));
+    let mut program = parser.parse().unwrap();
+    TypeSystem::analyze(&mut program).unwrap();
+    let mut codegen = Codegen::new("test");
+    codegen.build_program(program);
+    codegen
+}
+
+#[test]
+fn print_to_string_constant_main() {
+    let codegen = codegen("fn main() -> int { 1 + 2 }");
+
+    let ir = codegen.print_to_string();
+
+    let expected = "\
+; ModuleID = 'test'
+source_filename = \"test\"
+
+define i64 @main() {
+entry:
+  br label %block
+
+block:                                            ; preds = %entry
+  ret i64 3
+}
+";
+    assert_eq!(ir, expected);
+}
+
+#[test]
+fn main_without_result_exits_with_zero() {
+    let codegen = codegen("fn main() { if true { return; } }");
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("define i64 @main()"), "{}", ir);
+    assert!(!ir.contains("ret void"), "{}", ir);
+    assert!(ir.contains("ret i64 0"), "{}", ir);
+}
+
+#[test]
+fn main_gets_the_arguments_as_c_passes_them() {
+    let codegen = codegen("fn main(argc: int, argv: [string; 2]) -> int { argc }");
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("define i32 @main(i32 %0, i8** %1)"), "{}", ir);
+    assert!(ir.contains("sext i32 %0 to i64"), "{}", ir);
+    assert!(ir.contains("store [2 x i8*] zeroinitializer"), "{}", ir);
+    assert!(ir.contains("call void @llvm.memcpy"), "{}", ir);
+    assert!(ir.contains("trunc i64"), "{}", ir);
+}
+
+#[test]
+fn print_to_file_matches_print_to_string() {
+    let codegen = codegen("fn main() -> int { 1 + 2 }");
+    let path = env::temp_dir().join(format!("poop-print-{}.ll", std::process::id()));
+
+    codegen.print_to_file(&path).unwrap();
+    let ir = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(ir, codegen.print_to_string());
+}
+
+#[test]
+fn write_bitcode_to_memory_has_magic() {
+    let codegen = codegen("fn main() -> int { 1 + 2 }");
+
+    let bitcode = codegen.write_bitcode_to_memory();
+
+    assert_eq!(&bitcode[..4], b"BC\xC0\xDE");
+}
+
+#[test]
+fn write_bitcode_to_file_matches_memory() {
+    let codegen = codegen("fn main() -> int { 1 + 2 }");
+    let path = env::temp_dir().join(format!("poop-bitcode-{}.bc", std::process::id()));
+
+    codegen.write_bitcode_to_file(&path).unwrap();
+    let bitcode = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(bitcode, codegen.write_bitcode_to_memory());
+}
+
+#[test]
+fn optimize_promotes_variables_to_registers() {
+    let codegen = codegen("fn main() -> int { let x = 5; x + 1 }");
+    assert!(codegen.print_to_string().contains("alloca"));
+
+    codegen.optimize(optimization::OptimizationLevel::O2);
+
+    let ir = codegen.print_to_string();
+    assert!(!ir.contains("alloca"));
+    assert!(ir.contains("ret i64 6"));
+}
+
+#[test]
+fn run_passes_mem2reg() {
+    let codegen = codegen("fn main() -> int { let x = 5; x + 1 }");
+
+    codegen.run_passes(&["mem2reg"]).unwrap();
+
+    assert!(!codegen.print_to_string().contains("alloca"));
+}
+
+#[test]
+fn run_passes_unknown_pass() {
+    let codegen = codegen("fn main() -> int { 1 }");
+
+    let result = codegen.run_passes(&["mem2reg", "not-a-pass"]);
+
+    assert_eq!(
+        result.unwrap_err().message(),
+        "Unknown optimization pass 'not-a-pass'"
+    );
+}
+
+#[test]
+fn float_arithmetic_and_comparison() {
+    let codegen = codegen(
+        "fn area(w: double, h: double) -> double { w * h - 0.5 }
+         fn small(x: float) -> bool { x < 1.5f }
+         fn main() -> int { 0 }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("fmul double"), "{}", ir);
+    assert!(ir.contains("fsub double %"), "{}", ir);
+    assert!(ir.contains("5.000000e-01"), "{}", ir);
+    assert!(ir.contains("fcmp olt float"), "{}", ir);
+}
+
+#[test]
+fn variadic_float_arguments_are_promoted() {
+    let codegen = codegen(
+        "extern fn printf(format: string, ...) -> int;
+         fn main() -> int { printf(\"%f\", 1.5f) }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(
+        ir.contains("@printf(i8* getelementptr inbounds ([3 x i8], [3 x i8]* @0, i32 0, i32 0), double 1.500000e+00)"),
+        "{}",
+        ir
+    );
+}
+
+#[test]
+fn annotated_variables_use_their_type() {
+    let codegen = codegen("fn main() -> int { let b: bool = true; let d: double = 2.0; 0 }");
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("%b = alloca i1"), "{}", ir);
+    assert!(ir.contains("%d = alloca double"), "{}", ir);
+}
+
+#[test]
+fn inferred_types_drive_slots_and_phis() {
+    let codegen = codegen(
+        "extern fn printf(format: string, ...) -> int;
+         fn main() {
+             let s = \"hi\";
+             let b = 1 < 2;
+             let d = if b { 1.5 } else { 2.5 };
+             if b { printf(s); };
+         }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("%s = alloca i8*"), "{}", ir);
+    assert!(ir.contains("%b = alloca i1"), "{}", ir);
+    assert!(ir.contains("%d = alloca double"), "{}", ir);
+    assert!(ir.contains("phi double"), "{}", ir);
+    assert_eq!(ir.matches("phi").count(), 1, "{}", ir);
+}
+
+#[test]
+fn loops_branch_to_their_blocks() {
+    let codegen = codegen(
+        "fn main() -> int {
+             while 1 < 2 { continue; };
+             loop { break 2; }
+         }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("br label %while"), "{}", ir);
+    assert!(ir.contains("label %body, label %after"), "{}", ir);
+    assert!(ir.contains("br label %loop"), "{}", ir);
+    assert!(ir.contains("phi i64 [ 2, %loop ]"), "{}", ir);
+}
+
+#[test]
+fn assignment_stores_into_the_variable() {
+    let codegen = codegen("fn main() -> int { let mut x = 1; x += 2; x }");
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("add i64"), "{}", ir);
+    assert_eq!(ir.matches("store i64").count(), 2, "{}", ir);
+}
+
+#[test]
+fn returns_terminate_their_blocks() {
+    let codegen = codegen(
+        "fn pick(b: bool) -> int { let x = if b { return 1; } else { 2 }; x }
+         fn early() -> int { return 7; }
+         fn main() -> int { 0 }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("ret i64 1"), "{}", ir);
+    assert!(ir.contains("phi i64 [ 2, %else ]"), "{}", ir);
+    assert!(ir.contains("ret i64 7"), "{}", ir);
+    assert!(ir.contains("unreachable"), "{}", ir);
+}
+
+#[test]
+fn else_if_branches_from_the_else_block() {
+    let codegen = codegen(
+        "fn f(n: int) -> int { if n < 0 { 1 } else if n > 10 { 2 } else { 3 } }
+         fn main() -> int { 0 }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("phi i64 [ 2, %if"), "{}", ir);
+    assert!(ir.contains("phi i64 [ 1, %if ], [ %"), "{}", ir);
+}
+
+#[test]
+fn extern_functions_are_declared() {
+    let codegen = codegen(
+        "extern fn puts(s: string) -> int;
+         extern fn printf(format: string, ...) -> int;
+         fn main() -> int { puts(\"hi\") }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("declare i64 @puts(i8*)"), "{}", ir);
+    assert!(ir.contains("declare i64 @printf(i8*, ...)"), "{}", ir);
+    assert!(ir.contains("call i64 @puts("), "{}", ir);
+}
+
+#[test]
+fn functions_can_call_later_functions() {
+    let codegen = codegen(
+        "fn main() { is_even(10); }
+         fn is_even(n: int) -> bool { if n == 0 { true } else { is_odd(n - 1) } }
+         fn is_odd(n: int) -> bool { if n == 0 { false } else { is_even(n - 1) } }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("call i1 @is_even("), "{}", ir);
+    assert!(ir.contains("call i1 @is_odd("), "{}", ir);
+    assert!(!ir.contains("@is_even.1"), "{}", ir);
+}
+
+#[test]
+fn global_variables_are_initialized() {
+    let codegen = codegen(
+        "const LIMIT = 10 * 2;
+         let mut counter = 0;
+         let start = twice(LIMIT);
+         fn twice(n: int) -> int { n * 2 }
+         fn main() -> int { counter = start; LIMIT }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("@LIMIT = constant i64 20"), "{}", ir);
+    assert!(ir.contains("@counter = global i64 0"), "{}", ir);
+    assert!(ir.contains("@start = global i64 0"), "{}", ir);
+    assert!(
+        ir.contains("define internal void @__poop_module_init()"),
+        "{}",
+        ir
+    );
+    assert!(ir.contains("call void @__poop_module_init()"), "{}", ir);
+    assert!(ir.contains("%0 = call i64 @twice(i64 20)"), "{}", ir);
+    assert!(ir.contains("store i64 %0, i64* @start"), "{}", ir);
+    assert!(ir.contains("ret i64 20"), "{}", ir);
+}
+
+#[test]
+fn constant_globals_need_no_initializer() {
+    let codegen = codegen("let x = 1.5; fn f() -> double { x } fn main() {}");
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("@x = global double 1.500000e+00"), "{}", ir);
+    assert!(!ir.contains("__poop_module_init"), "{}", ir);
+}
+
+#[test]
+fn constants_are_folded_from_other_constants() {
+    let codegen = codegen(
+        "const A = 1;
+         const B = A + 1;
+         const C = B * A > 1 && true;
+         fn main() -> int { B }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("@A = constant i64 1"), "{}", ir);
+    assert!(ir.contains("@B = constant i64 2"), "{}", ir);
+    assert!(ir.contains("@C = constant i1 true"), "{}", ir);
+    assert!(ir.contains("ret i64 2"), "{}", ir);
+    assert!(!ir.contains("__poop_module_init"), "{}", ir);
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    let codegen = codegen(
+        "fn check(n: int) -> bool { n > 0 }
+         fn f() -> bool { let x = 0; x != 0 && check(10 / x) || check(x) }
+         fn main() {}",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(!ir.contains(" and i1"), "{}", ir);
+    assert!(!ir.contains(" or i1"), "{}", ir);
+    assert!(ir.contains("phi i1 [ false, %block ]"), "{}", ir);
+    assert!(ir.contains("phi i1 [ true, %after ]"), "{}", ir);
+}
+
+#[test]
+fn negation_of_expressions() {
+    let codegen = codegen("fn main() -> int { let x = 2; let y = -(1.5 * 2.0); -x }");
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("sub i64 0, %"), "{}", ir);
+    assert!(ir.contains("double -3.000000e+00"), "{}", ir);
+}
+
+#[test]
+fn blocks_scope_their_variables() {
+    let codegen = codegen(
+        "fn f() -> double {
+             let x = 1;
+             let y = { let x = 2.5; x };
+             let z = x + 1;
+             y
+         }
+         fn main() {}",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("%x = alloca i64"), "{}", ir);
+    assert!(ir.contains("%x1 = alloca double"), "{}", ir);
+    assert!(ir.contains("load i64, i64* %x"), "{}", ir);
+}
+
+#[test]
+fn structs_are_named_llvm_structs() {
+    let codegen = codegen(
+        "struct Point { x: int, y: double }
+         fn make(x: int) -> Point { Point { y: 2.5, x: x } }
+         fn main() -> int { let mut p = make(1); p.x = 2; p.x + make(3).x }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("%Point = type { i64, double }"), "{}", ir);
+    assert!(ir.contains("define %Point @make(i64"), "{}", ir);
+    assert!(ir.contains("insertvalue %Point"), "{}", ir);
+    assert!(
+        ir.contains("getelementptr inbounds %Point, %Point* %p, i32 0, i32 0"),
+        "{}",
+        ir
+    );
+    assert!(ir.contains("extractvalue %Point"), "{}", ir);
+}
+
+#[test]
+fn array_indices_are_bounds_checked() {
+    let codegen = codegen(
+        "fn main() -> int {
+             let mut a = [1, 2, 3];
+             let i = 2;
+             a[i] = a[0] + len(a);
+             a[i]
+         }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("%a = alloca [3 x i64]"), "{}", ir);
+    assert!(ir.contains("icmp ult i64 %"), "{}", ir);
+    assert!(ir.contains("call i32 (i32, i8*, ...) @dprintf"), "{}", ir);
+    assert!(
+        ir.contains("index %lld is out of bounds for an array of length %lld"),
+        "{}",
+        ir
+    );
+    // The constant index needs no check
+    assert_eq!(ir.matches("call void @abort()").count(), 2, "{}", ir);
+}
+
+#[test]
+fn matches_switch_on_the_tag() {
+    let codegen = codegen(
+        "enum Shape { Circle(double), Rect(int, int), Empty }
+         fn width(shape: Shape) -> int {
+             match shape { Shape::Rect(w, 0) => w, Shape::Rect(w, _) => w + 1, _ => 0 }
+         }
+         fn main() -> int { let h = 3; width(Shape::Rect(2, h)) }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    // The payload fits the largest variant, instead of the fields of every variant
+    assert!(ir.contains("%Shape = type { i64, [2 x i64] }"), "{}", ir);
+    assert!(ir.contains("%Shape.Rect = type { i64, i64 }"), "{}", ir);
+    assert!(ir.contains("%tag = extractvalue %Shape %1, 0"), "{}", ir);
+    assert!(ir.contains("switch i64 %tag, label %default ["), "{}", ir);
+    assert!(ir.contains("i64 1, label %case"), "{}", ir);
+    assert!(ir.contains("%w = alloca i64"), "{}", ir);
+    assert!(
+        ir.contains("bitcast [2 x i64]* %payload to %Shape.Rect*"),
+        "{}",
+        ir
+    );
+    assert!(ir.contains("store i64 1, i64* %tag"), "{}", ir);
+    assert!(!ir.contains("insertvalue %Shape"), "{}", ir);
+}
+
+#[test]
+fn methods_take_a_pointer_to_the_object() {
+    let codegen = codegen(
+        "class Counter {
+             count: int,
+             fn new() { Counter { count: 0 } }
+             fn get(self) -> int { self.count }
+             fn add(mut self, n: int) { self.count += n; }
+         }
+         fn main() -> int { let mut c = Counter(); c.add(2); c.get() }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert!(ir.contains("%Counter = type { i64 }"), "{}", ir);
+    assert!(ir.contains("define %Counter @Counter.new()"), "{}", ir);
+    assert!(
+        ir.contains("define i64 @Counter.get(%Counter* %0)"),
+        "{}",
+        ir
+    );
+    assert!(
+        ir.contains("define void @Counter.add(%Counter* %0, i64 %1)"),
+        "{}",
+        ir
+    );
+    assert!(ir.contains("call %Counter @Counter.new()"), "{}", ir);
+    assert!(
+        ir.contains("call void @Counter.add(%Counter* %c, i64 2)"),
+        "{}",
+        ir
+    );
+}
+
+#[test]
+fn compound_assignments_evaluate_the_target_once() {
+    let codegen = codegen(
+        "let mut calls = 0;
+         fn index() -> int { calls += 1; calls - 1 }
+         fn main() -> int { let mut a = [1, 2]; a[index()] += 5; a[0] }",
+    );
+
+    let ir = codegen.print_to_string();
+
+    assert_eq!(ir.matches("call i64 @index()").count(), 1, "{}", ir);
+    assert_eq!(ir.matches("call void @abort()").count(), 1, "{}", ir);
+    // The element is loaded from and stored to the same pointer
+    assert!(ir.contains("%5 = load i64, i64* %4"), "{}", ir);
+    assert!(ir.contains("%6 = add i64 %5, 5"), "{}", ir);
+    assert!(ir.contains("store i64 %6, i64* %4"), "{}", ir);
+}
@@ -8,32 +8,14 @@ pub trait Builder {
     //    body: &dyn Fn(&mut Codegen, &LLVMValueRef) -> (),
     //) -> LLVMValueRef;
     //fn build_basic_block(&mut self, block_name: &str, function: LLVMValueRef) -> LLVMBasicBlockRef;
-    fn build_const_int(&mut self, value: u64) -> LLVMValueRef;
-    fn build_const_string(&mut self, value: &str) -> LLVMValueRef;
-    fn build_function_call(
-        &mut self,
-        function_name: &str,
-        args: &mut [LLVMValueRef],
-        name: &str,
-    ) -> LLVMValueRef;
     fn build_store(&mut self, value: LLVMValueRef, ptr: LLVMValueRef) -> LLVMValueRef;
     fn build_load(&mut self, ptr: LLVMValueRef) -> LLVMValueRef;
     fn build_bit_cast(&mut self, value: LLVMValueRef, dest_type: Types) -> LLVMValueRef;
     fn build_stack_ptr(&mut self, stack_type: Types, name: &str) -> LLVMValueRef;
-    fn build_stack_str(&mut self, value: &str) -> LLVMValueRef;
     fn build_ret_void(&mut self) -> LLVMValueRef;
     fn build_ret(&mut self, value: LLVMValueRef) -> LLVMValueRef;
-    fn position_at_block(&mut self, basic_block: LLVMBasicBlockRef);
     fn build_br(&mut self, basic_block: LLVMBasicBlockRef) -> LLVMValueRef;
-    fn build_const_pointer(&mut self, value: LLVMValueRef, to_type: Types) -> LLVMValueRef;
-    fn build_null(&mut self, null_type: Types) -> LLVMValueRef;
     fn build_global_string(&mut self, value: &str) -> LLVMValueRef;
-    fn build_phi(
-        &mut self,
-        value_type: Types,
-        incoming: Vec<(LLVMBasicBlockRef, LLVMValueRef)>,
-    ) -> LLVMValueRef;
-    fn set_value_name(&mut self, value: LLVMValueRef, name: &str);
 }
 
 impl Builder for Codegen {
@@ -73,38 +55,6 @@ impl Builder for Codegen {
     //    }
     //}
 
-    fn build_const_int(&mut self, value: u64) -> LLVMValueRef {
-        unsafe { LLVMConstInt(Types::Int.to_llvm(self.context), value, 1) }
-    }
-
-    fn build_const_string(&mut self, value: &str) -> LLVMValueRef {
-        let value_ptr = self.module.borrow_mut().new_string_ptr(value);
-        unsafe { LLVMConstStringInContext(self.context, value_ptr, value.len() as u32, 0) }
-    }
-
-    fn build_function_call(
-        &mut self,
-        function_name: &str,
-        args: &mut [LLVMValueRef],
-        name: &str,
-    ) -> LLVMValueRef {
-        unsafe {
-            let function = LLVMGetNamedFunction(
-                self.module.borrow().module,
-                self.module.borrow_mut().new_string_ptr(function_name),
-            );
-            //println!("{}: {:?}", function_name, function);
-
-            LLVMBuildCall(
-                self.builder,
-                function,
-                args.as_mut_ptr(),
-                args.len() as c_uint,
-                to_ptr(name),
-            )
-        }
-    }
-
     fn build_store(&mut self, value: LLVMValueRef, ptr: LLVMValueRef) -> LLVMValueRef {
         unsafe { LLVMBuildStore(self.builder, value, ptr) }
     }
@@ -134,24 +84,6 @@ impl Builder for Codegen {
         }
     }
 
-    fn build_const_pointer(&mut self, value: LLVMValueRef, to_type: Types) -> LLVMValueRef {
-        unsafe { LLVMConstPointerCast(value, to_type.to_llvm(self.context)) }
-    }
-
-    fn build_null(&mut self, null_type: Types) -> LLVMValueRef {
-        unsafe { LLVMConstNull(null_type.to_llvm(self.context)) }
-    }
-
-    fn build_stack_str(&mut self, value: &str) -> LLVMValueRef {
-        let heap = self.build_stack_ptr(
-            Types::Array(Box::new(Types::Char), (value.len() + 1) as c_uint),
-            "",
-        );
-        let string = self.build_const_string(value);
-        self.build_store(string, heap);
-        self.build_bit_cast(heap, Types::String)
-    }
-
     fn build_global_string(&mut self, value: &str) -> LLVMValueRef {
         let string;
         {
@@ -175,72 +107,12 @@ impl Builder for Codegen {
         unsafe { LLVMBuildRet(self.builder, value) }
     }
 
-    fn position_at_block(&mut self, basic_block: LLVMBasicBlockRef) {
-        unsafe {
-            LLVMPositionBuilderAtEnd(self.builder, basic_block);
-        }
-    }
-
     fn build_br(&mut self, basic_block: LLVMBasicBlockRef) -> LLVMValueRef {
         unsafe { LLVMBuildBr(self.builder, basic_block) }
     }
-
-    fn build_phi(
-        &mut self,
-        value_type: Types,
-        incoming: Vec<(LLVMBasicBlockRef, LLVMValueRef)>,
-    ) -> LLVMValueRef {
-        unsafe {
-            let value = LLVMBuildPhi(
-                self.builder,
-                value_type.to_llvm(self.context),
-                self.module.borrow().empty_string(),
-            );
-            let count = incoming.len() as u32;
-            let (mut blocks, mut values): (Vec<_>, Vec<_>) = incoming.iter().cloned().unzip();
-            LLVMAddIncoming(value, values.as_mut_ptr(), blocks.as_mut_ptr(), count);
-            value
-        }
-    }
-
-    fn set_value_name(&mut self, value: LLVMValueRef, name: &str) {
-        unsafe { LLVMSetValueName2(value, self.module.borrow_mut().new_string_ptr(name), 0) }
-    }
 }
 
 impl Builder for function::Function {
-    fn build_const_int(&mut self, value: u64) -> LLVMValueRef {
-        unsafe { LLVMConstInt(Types::Int.to_llvm(self.context), value, 1) }
-    }
-
-    fn build_const_string(&mut self, value: &str) -> LLVMValueRef {
-        let value_ptr = self.module.borrow_mut().new_string_ptr(value);
-        unsafe { LLVMConstStringInContext(self.context, value_ptr, value.len() as u32, 0) }
-    }
-
-    fn build_function_call(
-        &mut self,
-        function_name: &str,
-        args: &mut [LLVMValueRef],
-        name: &str,
-    ) -> LLVMValueRef {
-        unsafe {
-            let function = LLVMGetNamedFunction(
-                self.module.borrow().module,
-                self.module.borrow_mut().new_string_ptr(function_name),
-            );
-            //println!("{}: {:?}", function_name, function);
-
-            LLVMBuildCall(
-                self.builder,
-                function,
-                args.as_mut_ptr(),
-                args.len() as c_uint,
-                to_ptr(name),
-            )
-        }
-    }
-
     fn build_store(&mut self, value: LLVMValueRef, ptr: LLVMValueRef) -> LLVMValueRef {
         unsafe { LLVMBuildStore(self.builder, value, ptr) }
     }
@@ -261,31 +133,8 @@ impl Builder for function::Function {
     }
 
     fn build_stack_ptr(&mut self, stack_type: Types, name: &str) -> LLVMValueRef {
-        unsafe {
-            LLVMBuildAlloca(
-                self.builder,
-                stack_type.to_llvm(self.context),
-                self.module.borrow_mut().new_string_ptr(name),
-            )
-        }
-    }
-
-    fn build_const_pointer(&mut self, value: LLVMValueRef, to_type: Types) -> LLVMValueRef {
-        unsafe { LLVMConstPointerCast(value, to_type.to_llvm(self.context)) }
-    }
-
-    fn build_null(&mut self, null_type: Types) -> LLVMValueRef {
-        unsafe { LLVMConstNull(null_type.to_llvm(self.context)) }
-    }
-
-    fn build_stack_str(&mut self, value: &str) -> LLVMValueRef {
-        let heap = self.build_stack_ptr(
-            Types::Array(Box::new(Types::Char), (value.len() + 1) as c_uint),
-            "",
-        );
-        let string = self.build_const_string(value);
-        self.build_store(string, heap);
-        self.build_bit_cast(heap, Types::String)
+        let stack_type = stack_type.to_llvm(self.context);
+        self.build_stack_slot(stack_type, name)
     }
 
     fn build_global_string(&mut self, value: &str) -> LLVMValueRef {
@@ -303,43 +152,37 @@ impl Builder for function::Function {
         self.build_bit_cast(string, Types::String)
     }
 
+    /// `main` declared without a result still returns the exit code, which is 0
     fn build_ret_void(&mut self) -> LLVMValueRef {
-        unsafe { LLVMBuildRetVoid(self.builder) }
+        let return_type = self.return_type();
+        unsafe {
+            if function::is_void(return_type) {
+                LLVMBuildRetVoid(self.builder)
+            } else {
+                LLVMBuildRet(self.builder, LLVMConstInt(return_type, 0, 0))
+            }
+        }
     }
 
+    /// `main` taking the arguments of the program returns the exit code as C's `int`
     fn build_ret(&mut self, value: LLVMValueRef) -> LLVMValueRef {
-        unsafe { LLVMBuildRet(self.builder, value) }
-    }
-
-    fn position_at_block(&mut self, basic_block: LLVMBasicBlockRef) {
+        let return_type = self.return_type();
         unsafe {
-            LLVMPositionBuilderAtEnd(self.builder, basic_block);
+            let value = if LLVMTypeOf(value) == return_type {
+                value
+            } else {
+                LLVMBuildTrunc(
+                    self.builder,
+                    value,
+                    return_type,
+                    self.module.borrow().empty_string(),
+                )
+            };
+            LLVMBuildRet(self.builder, value)
         }
     }
 
     fn build_br(&mut self, basic_block: LLVMBasicBlockRef) -> LLVMValueRef {
         unsafe { LLVMBuildBr(self.builder, basic_block) }
     }
-
-    fn build_phi(
-        &mut self,
-        value_type: Types,
-        incoming: Vec<(LLVMBasicBlockRef, LLVMValueRef)>,
-    ) -> LLVMValueRef {
-        unsafe {
-            let value = LLVMBuildPhi(
-                self.builder,
-                value_type.to_llvm(self.context),
-                self.module.borrow().empty_string(),
-            );
-            let count = incoming.len() as u32;
-            let (mut blocks, mut values): (Vec<_>, Vec<_>) = incoming.iter().cloned().unzip();
-            LLVMAddIncoming(value, values.as_mut_ptr(), blocks.as_mut_ptr(), count);
-            value
-        }
-    }
-
-    fn set_value_name(&mut self, value: LLVMValueRef, name: &str) {
-        unsafe { LLVMSetValueName2(value, self.module.borrow_mut().new_string_ptr(name), 0) }
-    }
 }
@@ -1,27 +1,92 @@
+//! Conversion of the types of the language into llvm types.
+
 use crate::parser::ast::Type;
 use llvm::core::*;
 use llvm::prelude::{LLVMContextRef, LLVMTypeRef};
+use std::collections::HashMap;
 use std::os::raw::c_uint;
 
+/// A named llvm struct type declared for a struct or an enum of the program.
+///
+/// Enums are tagged unions: the index of the variant as an `i64` tag, followed by
+/// a payload big enough for the values of the largest variant. The payload is read
+/// and written through a pointer to the llvm struct of the variant.
+pub struct StructType {
+    /// The named llvm struct
+    pub llvm_type: LLVMTypeRef,
+    /// The names of the fields, in the order of the llvm struct body, empty for enums
+    pub fields: Vec<String>,
+    /// The variants of an enum, empty for structs
+    pub variants: Vec<Variant>,
+}
+
+/// A variant of an enum
+pub struct Variant {
+    /// Name of the variant
+    pub name: String,
+    /// The llvm struct of the values of the variant, stored in the payload of the enum
+    pub llvm_type: LLVMTypeRef,
+}
+
+impl StructType {
+    /// The index of the field `name` in the llvm struct
+    pub fn field_index(&self, name: &str) -> c_uint {
+        self.fields
+            .iter()
+            .position(|field| field == name)
+            .unwrap_or_else(|| panic!("Unknown field '{}'", name)) as c_uint
+    }
+
+    /// The index of the variant `name`, which is its tag, and the variant itself
+    pub fn variant(&self, name: &str) -> (usize, &Variant) {
+        self.variants
+            .iter()
+            .enumerate()
+            .find(|(_, variant)| variant.name == name)
+            .unwrap_or_else(|| panic!("Unknown variant '{}'", name))
+    }
+}
+
+/// The types of llvm values, which the types of the language are converted into
 pub enum Types {
+    /// `i64`
     Int,
+    /// `i32`, the `int` of C
+    CInt,
+    /// `float`
+    Float,
+    /// `double`
+    Double,
+    /// `i1`
     Bool,
+    /// `i8`
     Char,
+    /// Pointer to `i8` characters
     String,
+    /// `void`
     Void,
+    /// Array of the element type with the given length
     Array(Box<Types>, c_uint),
+    /// Function with the return type, the argument types and whether it is variadic
     Func(Box<Types>, Vec<Types>, bool),
+    /// A named struct declared for a struct, class or enum
+    Struct(LLVMTypeRef),
+    /// Pointer to a value of the type
+    Pointer(Box<Types>),
 }
 
 impl Types {
-    pub fn to_llvm(&self, context: LLVMContextRef) -> LLVMTypeRef {
+    pub(crate) fn to_llvm(&self, context: LLVMContextRef) -> LLVMTypeRef {
         unsafe {
             match self {
                 Types::Int => LLVMInt64TypeInContext(context),
+                Types::CInt => LLVMInt32TypeInContext(context),
+                Types::Float => LLVMFloatTypeInContext(context),
+                Types::Double => LLVMDoubleTypeInContext(context),
                 Types::Bool => LLVMInt1TypeInContext(context),
                 Types::Char => LLVMInt8TypeInContext(context),
                 Types::String => LLVMPointerType(Types::Char.to_llvm(context), 0),
-                Types::Void => LLVMVoidType(),
+                Types::Void => LLVMVoidTypeInContext(context),
                 Types::Array(element_type, count) => {
                     LLVMArrayType(element_type.to_llvm(context), *count)
                 }
@@ -34,26 +99,55 @@ impl Types {
                     args.len() as u32,
                     if *variadic { 1 } else { 0 },
                 ),
+                Types::Struct(struct_type) => *struct_type,
+                Types::Pointer(pointee) => LLVMPointerType(pointee.to_llvm(context), 0),
             }
         }
     }
 
-    pub fn printf() -> Self {
-        Types::Func(Box::new(Types::Int), vec![Types::String], true)
-    }
-
+    /// The type of `main`, returning the exit code
     pub fn main() -> Self {
         Types::Func(Box::new(Types::Int), Vec::new(), false)
     }
+
+    /// The type of `main` taking the arguments of the program, the way C passes them
+    pub fn main_with_args() -> Self {
+        Types::Func(
+            Box::new(Types::CInt),
+            vec![Types::CInt, Types::Pointer(Box::new(Types::String))],
+            false,
+        )
+    }
+
+    /// Like `Types::from`, but also converts the user defined types declared in `structs`
+    pub fn from_type(value: Type, structs: &HashMap<String, StructType>) -> Self {
+        match value {
+            Type::UserDefined(name) => match structs.get(&name) {
+                Some(struct_type) => Types::Struct(struct_type.llvm_type),
+                None => panic!("Type '{}' has not been declared", name),
+            },
+            Type::Array(element_type, length) => Types::Array(
+                Box::new(Self::from_type(*element_type, structs)),
+                length as c_uint,
+            ),
+            value => value.into(),
+        }
+    }
 }
 
 impl From<Type> for Types {
     fn from(value: Type) -> Self {
         match value {
             Type::Int => Self::Int,
+            Type::Float => Self::Float,
+            Type::Double => Self::Double,
             Type::Boolean => Self::Bool,
             Type::String => Self::String,
-            Type::Void => Self::Void,
+            Type::Array(element_type, length) => {
+                Self::Array(Box::new((*element_type).into()), length as c_uint)
+            }
+            // Values that never exist need no storage
+            Type::Void | Type::Never => Self::Void,
             t => panic!("Type not yet implemented for: {:?}", t),
         }
     }
@@ -0,0 +1,319 @@
+//! Shared reporting of errors from all the stages of the compiler.
+//!
+//! Errors from the parser, the type system and the codegen are all converted into a
+//! [`Diagnostic`], which the [`Emitter`] renders along with the offending source code:
+//!
+//! ``` text
+//! error[E0002]: Expected ;, found: <identifier>
+//!  --> test.poop:2:15
+//!   |
+//! 2 |     let x = 5 y
+//!   |               ^ unexpected identifier
+//!   |
+//!   = note: statements are separated by `;`
+//! ```
+
+use crate::span::{SourceFile, SourceMap, Span};
+use std::fmt::Write;
+
+#[cfg(test)]
+mod test;
+
+/// How severe a diagnostic is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    /// Compilation can not continue
+    Error,
+    /// Something is suspicious, but compilation can continue
+    Warning,
+    /// Additional information
+    Note,
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+
+    fn style(self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Note => GREEN,
+        }
+    }
+}
+
+/// A message attached to a span of the source code
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    /// The code the label points at
+    pub span: Span,
+    /// Message shown next to the underline. Can be empty
+    pub message: String,
+    /// Primary labels points at the cause of the diagnostic,
+    /// while secondary labels gives context
+    pub primary: bool,
+}
+
+impl Label {
+    /// Creates a primary label, underlined with `^`
+    pub fn primary<T: Into<String>>(span: Span, message: T) -> Self {
+        Self {
+            span,
+            message: message.into(),
+            primary: true,
+        }
+    }
+
+    /// Creates a secondary label, underlined with `-`
+    pub fn secondary<T: Into<String>>(span: Span, message: T) -> Self {
+        Self {
+            span,
+            message: message.into(),
+            primary: false,
+        }
+    }
+}
+
+/// An error, warning or note to be reported to the user
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Severity of the diagnostic
+    pub severity: Severity,
+    /// Error code, e.g. `E0002`
+    pub code: Option<String>,
+    /// The main message
+    pub message: String,
+    /// Labels pointing at the relevant code
+    pub labels: Vec<Label>,
+    /// Notes shown after the code
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Creates a new diagnostic without any labels
+    pub fn new<T: Into<String>>(severity: Severity, message: T) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Creates a new error
+    pub fn error<T: Into<String>>(message: T) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    /// Creates a new warning
+    pub fn warning<T: Into<String>>(message: T) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    /// Sets the error code
+    pub fn with_code<T: Into<String>>(mut self, code: T) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Adds a label
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    /// Adds several labels
+    pub fn with_labels<I: IntoIterator<Item = Label>>(mut self, labels: I) -> Self {
+        self.labels.extend(labels);
+        self
+    }
+
+    /// Adds a note
+    pub fn with_note<T: Into<String>>(mut self, note: T) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Adds several notes
+    pub fn with_notes<I: IntoIterator<Item = String>>(mut self, notes: I) -> Self {
+        self.notes.extend(notes);
+        self
+    }
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders diagnostics, optionally using ANSI colours
+pub struct Emitter {
+    colour: bool,
+}
+
+impl Emitter {
+    /// Creates a new emitter
+    pub fn new(colour: bool) -> Self {
+        Self { colour }
+    }
+
+    /// Renders the diagnostic and prints it to stderr
+    pub fn emit(&self, diagnostic: &Diagnostic, source_map: &SourceMap) {
+        eprint!("{}", self.render(diagnostic, source_map));
+    }
+
+    /// Renders the diagnostic into a string
+    pub fn render(&self, diagnostic: &Diagnostic, source_map: &SourceMap) -> String {
+        let mut out = String::new();
+        let severity = diagnostic.severity;
+        match &diagnostic.code {
+            Some(code) => write!(
+                out,
+                "{}",
+                self.paint(severity.style(), &format!("{}[{}]", severity.name(), code))
+            ),
+            None => write!(out, "{}", self.paint(severity.style(), severity.name())),
+        }
+        .unwrap();
+        writeln!(
+            out,
+            "{}",
+            self.paint(BOLD, &format!(": {}", diagnostic.message))
+        )
+        .unwrap();
+
+        // Labels are shown grouped by file, starting with the file of the primary label
+        let mut labels: Vec<(&Label, &SourceFile)> = diagnostic
+            .labels
+            .iter()
+            .filter_map(|label| source_map.get(label.span.file).map(|file| (label, file)))
+            .collect();
+        labels.sort_by_key(|(label, _)| !label.primary);
+        let mut files: Vec<&SourceFile> = Vec::new();
+        for (_, file) in labels.iter() {
+            if !files.iter().any(|other| std::ptr::eq(*other, *file)) {
+                files.push(file);
+            }
+        }
+
+        let gutter_width = labels
+            .iter()
+            .map(|(label, file)| file.line_and_column(label.span.start).0)
+            .max()
+            .unwrap_or(0)
+            .to_string()
+            .len();
+        let padding = " ".repeat(gutter_width);
+
+        for (i, file) in files.iter().enumerate() {
+            let mut file_labels: Vec<&Label> = labels
+                .iter()
+                .filter(|(_, other)| std::ptr::eq(*other, *file))
+                .map(|(label, _)| *label)
+                .collect();
+            let (line, column) = file.line_and_column(file_labels[0].span.start);
+            writeln!(
+                out,
+                "{}{} {}:{}:{}",
+                padding,
+                self.paint(BLUE, if i == 0 { "-->" } else { ":::" }),
+                file.name(),
+                line,
+                column
+            )
+            .unwrap();
+            writeln!(out, "{} {}", padding, self.paint(BLUE, "|")).unwrap();
+
+            file_labels.sort_by_key(|label| label.span.start);
+            let mut previous_line = None;
+            for label in file_labels.iter() {
+                let (line, column) = file.line_and_column(label.span.start);
+                let text = file.line(line);
+                if previous_line != Some(line) {
+                    if matches!(previous_line, Some(previous) if line > previous + 1) {
+                        writeln!(out, "{}", self.paint(BLUE, "...")).unwrap();
+                    }
+                    writeln!(
+                        out,
+                        "{} {} {}",
+                        self.paint(BLUE, &format!("{:>width$}", line, width = gutter_width)),
+                        self.paint(BLUE, "|"),
+                        text
+                    )
+                    .unwrap();
+                    previous_line = Some(line);
+                }
+
+                let start = (column - 1).min(text.len());
+                let end = if file.line_and_column(label.span.end).0 == line {
+                    (file.line_and_column(label.span.end).1 - 1).min(text.len())
+                } else {
+                    text.len()
+                };
+                let indent: String = text
+                    .get(..start)
+                    .unwrap_or("")
+                    .chars()
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                let width = text.get(start..end).map_or(0, |s| s.chars().count()).max(1);
+                let (marker, style) = if label.primary {
+                    ("^", severity.style())
+                } else {
+                    ("-", BLUE)
+                };
+                let underline = if label.message.is_empty() {
+                    marker.repeat(width)
+                } else {
+                    format!("{} {}", marker.repeat(width), label.message)
+                };
+                writeln!(
+                    out,
+                    "{} {} {}{}",
+                    padding,
+                    self.paint(BLUE, "|"),
+                    indent,
+                    self.paint(style, &underline)
+                )
+                .unwrap();
+            }
+        }
+
+        if !diagnostic.notes.is_empty() && !files.is_empty() {
+            writeln!(out, "{} {}", padding, self.paint(BLUE, "|")).unwrap();
+        }
+        for note in diagnostic.notes.iter() {
+            writeln!(
+                out,
+                "{} {} {}",
+                padding,
+                self.paint(BLUE, "="),
+                note_line(self, note)
+            )
+            .unwrap();
+        }
+
+        out
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.colour {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            String::from(text)
+        }
+    }
+}
+
+fn note_line(emitter: &Emitter, note: &str) -> String {
+    format!("{} {}", emitter.paint(BOLD, "note:"), note)
+}
@@ -0,0 +1,88 @@
+use super::*;
+use crate::span::{SourceMap, Span};
+
+#[test]
+fn render_primary_label() {
+    // Given
+    let mut source_map = SourceMap::new();
+    let file = source_map.add("test.poop", "fn main() {\n    let x = 5 y\n}");
+    let diagnostic = Diagnostic::error("Expected ;, found: <identifier>")
+        .with_code("E0002")
+        .with_label(Label::primary(
+            Span::new(file, 26..27),
+            "unexpected identifier",
+        ));
+
+    // When
+    let rendered = Emitter::new(false).render(&diagnostic, &source_map);
+
+    // Then
+    assert_eq!(
+        rendered,
+        "error[E0002]: Expected ;, found: <identifier>\n \
+         --> test.poop:2:15\n  \
+         |\n\
+         2 |     let x = 5 y\n  \
+         |               ^ unexpected identifier\n"
+    );
+}
+
+#[test]
+fn render_secondary_labels_and_notes() {
+    // Given
+    let mut source_map = SourceMap::new();
+    let file = source_map.add("test.poop", "let x = true;\nlet y = x + 1;");
+    let diagnostic = Diagnostic::error("Type mismatch")
+        .with_label(Label::primary(Span::new(file, 22..27), "expected number"))
+        .with_label(Label::secondary(
+            Span::new(file, 4..5),
+            "declared as boolean",
+        ))
+        .with_note("arithmetic is only defined for numbers");
+
+    // When
+    let rendered = Emitter::new(false).render(&diagnostic, &source_map);
+
+    // Then
+    assert_eq!(
+        rendered,
+        "error: Type mismatch\n \
+         --> test.poop:2:9\n  \
+         |\n\
+         1 | let x = true;\n  \
+         |     - declared as boolean\n\
+         2 | let y = x + 1;\n  \
+         |         ^^^^^ expected number\n  \
+         |\n  \
+         = note: arithmetic is only defined for numbers\n"
+    );
+}
+
+#[test]
+fn render_without_span() {
+    // Given
+    let source_map = SourceMap::new();
+    let diagnostic = Diagnostic::error("There must be a main function");
+
+    // When
+    let rendered = Emitter::new(false).render(&diagnostic, &source_map);
+
+    // Then
+    assert_eq!(rendered, "error: There must be a main function\n");
+}
+
+#[test]
+fn render_with_colour() {
+    // Given
+    let source_map = SourceMap::new();
+    let diagnostic = Diagnostic::warning("Unused variable");
+
+    // When
+    let rendered = Emitter::new(true).render(&diagnostic, &source_map);
+
+    // Then
+    assert_eq!(
+        rendered,
+        "\x1b[1;33mwarning\x1b[0m\x1b[1m: Unused variable\x1b[0m\n"
+    );
+}
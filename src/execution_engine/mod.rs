@@ -1,13 +1,20 @@
+//! Runs the generated llvm module in-process, using the MCJIT execution engine.
+
+use llvm::core::*;
 use llvm::execution_engine::*;
 use llvm::prelude::*;
+use llvm::support::LLVMLoadLibraryPermanently;
 use llvm::target::*;
+use llvm::LLVMTypeKind;
 use std::cell::RefCell;
 use std::mem;
 use std::os::raw::{c_char, c_int, c_uint};
+use std::ptr;
 use std::rc::Rc;
 
-use super::codegen::{module::Module, Codegen};
+use super::codegen::{error::CodegenError, module::Module, Codegen};
 
+/// An execution engine compiling the module of a codegen just in time
 pub struct ExecutionEngine {
     _codegen: Codegen,
     module: Rc<RefCell<Module>>,
@@ -15,15 +22,17 @@ pub struct ExecutionEngine {
 }
 
 impl ExecutionEngine {
+    /// Creates an execution engine for the module of the codegen
     pub fn new(codegen: Codegen) -> Self {
-        let mut ee;
+        let mut ee = ptr::null_mut();
         let module = codegen.module();
         unsafe {
-            ee = mem::MaybeUninit::uninit().assume_init();
-            let mut out = mem::zeroed();
+            let mut out = ptr::null_mut();
             LLVMLinkInMCJIT();
             LLVM_InitializeNativeTarget();
             LLVM_InitializeNativeAsmPrinter();
+            // Makes the symbols of the process, e.g. libc, available to extern functions
+            LLVMLoadLibraryPermanently(ptr::null());
 
             //println!("Out before: {:?}", out);
             //println!("EE before: {:?}", ee);
@@ -39,6 +48,7 @@ impl ExecutionEngine {
         }
     }
 
+    /// The address of the compiled function with the given name
     pub fn get_function(&mut self, function_name: &str) -> extern "C" fn() -> () {
         let function_name_ptr = self.module.borrow_mut().new_string_ptr(function_name);
         unsafe {
@@ -49,20 +59,59 @@ impl ExecutionEngine {
         }
     }
 
-    pub fn run_as_main(&mut self, main_fn: LLVMValueRef, argv: &[&str]) -> c_int {
-        unsafe {
-            LLVMRunFunctionAsMain(
-                self.ee,
-                main_fn,
-                argv.len() as c_uint,
-                argv.iter()
-                    .map(|arg| self.module.borrow_mut().new_string_ptr(arg))
-                    .collect::<Vec<*const c_char>>()
-                    .as_mut_ptr(),
-                mem::zeroed(),
+    /// Checks that the engine can run `main_fn` as `main`. It has to return an integer
+    /// and take no arguments, or return C's `int` and take C's `argc` and `argv`.
+    ///
+    /// # Safety
+    ///
+    /// `main_fn` must be a function of the module of the engine.
+    pub unsafe fn check_main(&self, main_fn: LLVMValueRef) -> Result<(), CodegenError> {
+        let function_type = LLVMGetElementType(LLVMTypeOf(main_fn));
+        let return_type = LLVMGetReturnType(function_type);
+        let mut params = vec![ptr::null_mut(); LLVMCountParamTypes(function_type) as usize];
+        LLVMGetParamTypes(function_type, params.as_mut_ptr());
+        let is_int = |int_type: LLVMTypeRef, width: Option<c_uint>| {
+            LLVMGetTypeKind(int_type) == LLVMTypeKind::LLVMIntegerTypeKind
+                && width.map_or(true, |width| LLVMGetIntTypeWidth(int_type) == width)
+        };
+        let runnable = match params.as_slice() {
+            [] => is_int(return_type, None),
+            [argc, argv] => {
+                is_int(return_type, Some(32))
+                    && is_int(*argc, Some(32))
+                    && LLVMGetTypeKind(*argv) == LLVMTypeKind::LLVMPointerTypeKind
+            }
+            _ => false,
+        };
+        if runnable {
+            Ok(())
+        } else {
+            Err(
+                CodegenError::error("The main function can not be run").with_note(
+                    "main has to return an integer and take no arguments or `argc` and `argv`",
+                ),
             )
         }
     }
+
+    /// Runs `main_fn` with `argv` as the command line arguments, returning its exit code.
+    ///
+    /// # Safety
+    ///
+    /// `main_fn` must be a function of the module of the engine, e.g. the one returned by
+    /// [`Codegen::build_program`], which [`ExecutionEngine::check_main`] accepts.
+    pub unsafe fn run_as_main(&mut self, main_fn: LLVMValueRef, argv: &[&str]) -> c_int {
+        LLVMRunFunctionAsMain(
+            self.ee,
+            main_fn,
+            argv.len() as c_uint,
+            argv.iter()
+                .map(|arg| self.module.borrow_mut().new_string_ptr(arg))
+                .collect::<Vec<*const c_char>>()
+                .as_mut_ptr(),
+            ptr::null(),
+        )
+    }
 }
 
 impl Drop for ExecutionEngine {
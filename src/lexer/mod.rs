@@ -1,3 +1,5 @@
+//! The tokens of the language, produced from the source code by [`logos`].
+
 pub use logos::Logos;
 use std::fmt;
 use std::ops::Range;
@@ -8,146 +10,216 @@ pub mod wrapper;
 #[derive(Logos, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Token {
     // Logos specific
-    /// EOF
-    #[end]
+    /// EOF, used by the parser once the lexer runs out of tokens
     End,
-    /// Error occured in the lexer
+    /// Error occured in the lexer. Whitespace is skipped
     #[error]
+    #[regex(r"[ \t\n\r\f]+", logos::skip)]
     Error,
 
     // Comments
     /// Line comment starting with `//` and ends at a new line
-    #[regex = "//[^\n]*\n"]
+    #[regex("//[^\n]*\n")]
     LineComment,
 
     // Special characters
     /// Token `(`
-    #[token = "("]
+    #[token("(")]
     LParen,
     /// Token `)`
-    #[token = ")"]
+    #[token(")")]
     RParen,
     /// Token `{`
-    #[token = "{"]
+    #[token("{")]
     LBrace,
     /// Token `}`
-    #[token = "}"]
+    #[token("}")]
     RBrace,
     /// Token `[`
-    #[token = "["]
+    #[token("[")]
     LBracket,
     /// Token `]`
-    #[token = "]"]
+    #[token("]")]
     RBracket,
     /// Token `.`
-    #[token = "."]
+    #[token(".")]
     Period,
+    /// Token `...`
+    #[token("...")]
+    Ellipsis,
     /// Token `,`
-    #[token = ","]
+    #[token(",")]
     Comma,
     /// Token `;`
-    #[token = ";"]
+    #[token(";")]
     Semicolon,
     /// Token `:`
-    #[token = ":"]
+    #[token(":")]
     Colon,
+    /// Token `::`
+    #[token("::")]
+    DoubleColon,
     /// Token `->`
-    #[token = "->"]
+    #[token("->")]
     Arrow,
+    /// Token `=>`
+    #[token("=>")]
+    FatArrow,
     /// Token `+`
-    #[token = "+"]
+    #[token("+")]
     Plus,
     /// Token `-`
-    #[token = "-"]
+    #[token("-")]
     Minus,
     /// Token `*`
-    #[token = "*"]
+    #[token("*")]
     Star,
     /// Token `/`
-    #[token = "/"]
+    #[token("/")]
     Slash,
     /// Token `%`
-    #[token = "%"]
+    #[token("%")]
     Percent,
     /// Token `=`
-    #[token = "="]
+    #[token("=")]
     Equal,
+    /// Token `+=`
+    #[token("+=")]
+    PlusEqual,
+    /// Token `-=`
+    #[token("-=")]
+    MinusEqual,
+    /// Token `*=`
+    #[token("*=")]
+    StarEqual,
+    /// Token `/=`
+    #[token("/=")]
+    SlashEqual,
+    /// Token `%=`
+    #[token("%=")]
+    PercentEqual,
     /// Token `==`
-    #[token = "=="]
+    #[token("==")]
     Equality,
     /// Token `!=`
-    #[token = "!="]
+    #[token("!=")]
     NotEq,
     /// Token `<`
-    #[token = "<"]
+    #[token("<")]
     LessThan,
     /// Token `>`
-    #[token = ">"]
+    #[token(">")]
     GreaterThan,
     /// Token `<=`
-    #[token = "<="]
+    #[token("<=")]
     LessEq,
     /// Token `>=`
-    #[token = ">="]
+    #[token(">=")]
     GreaterEq,
     /// Token `!`
-    #[token = "!"]
+    #[token("!")]
     Not,
     /// Token `&&`
-    #[token = "&&"]
+    #[token("&&")]
     And,
     /// Token `||`
-    #[token = "||"]
+    #[token("||")]
     Or,
 
     // Identifier
     /// Identifier, denoted by the regex `[a-zA-Z_][a-zA-Z0-9_]*`
-    #[regex = "[a-zA-Z_][a-zA-Z0-9_]*"]
+    #[regex("[a-zA-Z_][a-zA-Z0-9_]*")]
     Ident,
 
     // Keywords
     /// Keyword `fn`
-    #[token = "fn"]
+    #[token("fn")]
     Fn,
+    /// Keyword `const`
+    #[token("const")]
+    Const,
+    /// Keyword `extern`
+    #[token("extern")]
+    Extern,
     /// Keyword `let`
-    #[token = "let"]
+    #[token("let")]
     Let,
+    /// Keyword `mut`
+    #[token("mut")]
+    Mut,
+    /// Keyword `struct`
+    #[token("struct")]
+    Struct,
+    /// Keyword `enum`
+    #[token("enum")]
+    Enum,
+    /// Keyword `class`
+    #[token("class")]
+    Class,
+    /// Keyword `self`, the object of a method
+    #[token("self")]
+    SelfValue,
     /// Keyword `if`
-    #[token = "if"]
+    #[token("if")]
     If,
     /// Keyword `else`
-    #[token = "else"]
+    #[token("else")]
     Else,
+    /// Keyword `while`
+    #[token("while")]
+    While,
+    /// Keyword `loop`
+    #[token("loop")]
+    Loop,
+    /// Keyword `break`
+    #[token("break")]
+    Break,
+    /// Keyword `continue`
+    #[token("continue")]
+    Continue,
+    /// Keyword `return`
+    #[token("return")]
+    Return,
+    /// Keyword `match`
+    #[token("match")]
+    Match,
 
     // Literals
     /// Literal for integers
-    #[regex = "[0-9][0-9_]*"]
+    #[regex("[0-9][0-9_]*")]
     Int,
+    /// Literal for floating point numbers, e.g. `1.5`, `2.0e-3` or `1.5f`.
+    /// Literals ending with `f` are floats, all others are doubles
+    #[regex("[0-9][0-9_]*(\\.[0-9][0-9_]*([eE][+-]?[0-9]+)?|[eE][+-]?[0-9]+)[fF]?")]
+    Float,
     /// Literal for boolean true
-    #[token = "true"]
+    #[token("true")]
     True,
     /// Literal for boolean false
-    #[token = "false"]
+    #[token("false")]
     False,
     /// Literal for strings
-    #[regex = "\"[^\"]*\""]
+    #[regex("\"[^\"]*\"")]
     String,
 
     // Type names
     /// Type `int`
-    #[token = "int"]
+    #[token("int")]
     IntType,
     /// Type `float`
-    #[token = "float"]
+    #[token("float")]
     FloatType,
     /// Type `double`
-    #[token = "double"]
+    #[token("double")]
     DoubleType,
     /// Type `bool`
-    #[token = "bool"]
+    #[token("bool")]
     BooleanType,
+    /// Type `string`
+    #[token("string")]
+    StringType,
     /// Type `void`
-    #[token = "void"]
+    #[token("void")]
     VoidType,
 }
 
@@ -157,15 +229,24 @@ impl fmt::Display for Token {
             Token::And => write!(f, "&&"),
             Token::Arrow => write!(f, "->"),
             Token::BooleanType => write!(f, "bool"),
+            Token::Break => write!(f, "break"),
+            Token::Class => write!(f, "class"),
             Token::Colon => write!(f, ":"),
             Token::Comma => write!(f, ","),
+            Token::Const => write!(f, "const"),
+            Token::Continue => write!(f, "continue"),
+            Token::DoubleColon => write!(f, "::"),
             Token::DoubleType => write!(f, "double"),
+            Token::Ellipsis => write!(f, "..."),
             Token::Else => write!(f, "else"),
             Token::End => write!(f, "EOF"),
+            Token::Enum => write!(f, "enum"),
             Token::Equal => write!(f, "="),
             Token::Equality => write!(f, "=="),
             Token::Error => write!(f, "ERROR"),
+            Token::Extern => write!(f, "extern"),
             Token::False => write!(f, "false"),
+            Token::FatArrow => write!(f, "=>"),
             Token::FloatType => write!(f, "float"),
             Token::Fn => write!(f, "fn"),
             Token::GreaterEq => write!(f, ">="),
@@ -173,30 +254,44 @@ impl fmt::Display for Token {
             Token::Ident => write!(f, "<identifier>"),
             Token::If => write!(f, "if"),
             Token::Int => write!(f, "<int>"),
+            Token::Float => write!(f, "<float>"),
             Token::IntType => write!(f, "int"),
             Token::LessEq => write!(f, "<="),
             Token::LessThan => write!(f, "<"),
             Token::Let => write!(f, "let"),
             Token::LineComment => write!(f, "// <comment>"),
+            Token::Loop => write!(f, "loop"),
             Token::LBrace => write!(f, "{{"),
             Token::LBracket => write!(f, "["),
             Token::LParen => write!(f, "("),
+            Token::Match => write!(f, "match"),
             Token::Minus => write!(f, "-"),
+            Token::MinusEqual => write!(f, "-="),
+            Token::Mut => write!(f, "mut"),
             Token::Not => write!(f, "!"),
             Token::NotEq => write!(f, "!="),
             Token::Or => write!(f, "||"),
             Token::Percent => write!(f, "%"),
+            Token::PercentEqual => write!(f, "%="),
             Token::Period => write!(f, "."),
             Token::Plus => write!(f, "+"),
+            Token::PlusEqual => write!(f, "+="),
             Token::RBrace => write!(f, "}}"),
             Token::RBracket => write!(f, "]"),
+            Token::Return => write!(f, "return"),
             Token::RParen => write!(f, ")"),
             Token::Semicolon => write!(f, ";"),
+            Token::SelfValue => write!(f, "self"),
             Token::Slash => write!(f, "/"),
+            Token::SlashEqual => write!(f, "/="),
             Token::Star => write!(f, "*"),
+            Token::StarEqual => write!(f, "*="),
             Token::String => write!(f, "<string>"),
+            Token::Struct => write!(f, "struct"),
+            Token::StringType => write!(f, "string"),
             Token::True => write!(f, "true"),
             Token::VoidType => write!(f, "void"),
+            Token::While => write!(f, "while"),
         }
     }
 }
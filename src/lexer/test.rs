@@ -26,3 +26,28 @@ fn second_line() {
     println!("{}", &string[4..5]);
     assert_eq!(line_and_pos, (2, 1));
 }
+
+#[test]
+fn float_literals() {
+    // Given
+    let string = "1.5 2.0e-3 1.5f 3e8 1_000.25 7";
+
+    // When
+    let tokens: Vec<(Token, &str)> = Token::lexer(string)
+        .spanned()
+        .map(|(token, range)| (token, &string[range]))
+        .collect();
+
+    // Then
+    assert_eq!(
+        tokens,
+        vec![
+            (Token::Float, "1.5"),
+            (Token::Float, "2.0e-3"),
+            (Token::Float, "1.5f"),
+            (Token::Float, "3e8"),
+            (Token::Float, "1_000.25"),
+            (Token::Int, "7"),
+        ]
+    );
+}
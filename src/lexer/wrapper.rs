@@ -3,32 +3,23 @@
 //! and allowing us to skip comments already here, as to not increase the complexity of the parser
 
 use super::Token;
-pub use logos::source::WithSource;
-pub use logos::{Lexer, Logos, Slice, Source};
+pub use logos::{Lexer, Logos};
 use std::fmt;
 pub use std::ops::Range;
 use std::ops::{Deref, DerefMut};
 
 /// Wrapper used for allowing the parser to better get token locations and ignore comments
-pub struct LexerWrapper<Source>(pub Lexer<Token, Source>);
+pub struct LexerWrapper<'source>(pub Lexer<'source, Token>);
 
-impl<'source, Source> Deref for LexerWrapper<Source>
-where
-    Token: self::Logos + WithSource<Source> + Copy + PartialEq,
-    Source: self::Source<'source> + Copy,
-{
-    type Target = Lexer<Token, Source>;
+impl<'source> Deref for LexerWrapper<'source> {
+    type Target = Lexer<'source, Token>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl<'source, Source> DerefMut for LexerWrapper<Source>
-where
-    Token: self::Logos + WithSource<Source> + Copy + PartialEq,
-    Source: self::Source<'source> + Copy,
-{
+impl<'source> DerefMut for LexerWrapper<'source> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
@@ -37,72 +28,46 @@ where
 /// Adding the ability to use with iterators.
 /// This also allow for iterator features like `Peek`
 #[derive(Clone)]
-pub struct TokenItem<Token: Logos, Source> {
+pub struct TokenItem<'source> {
     /// Current token in the iteration.
     pub token: Token,
-    source: Source,
-
-    token_start: usize,
-    token_end: usize,
+    slice: &'source str,
+    range: Range<usize>,
 }
 
-impl<'source, Token, Source> fmt::Debug for TokenItem<Token, Source>
-where
-    Token: self::Logos + WithSource<Source> + fmt::Debug,
-    Source: self::Source<'source>,
-{
+impl<'source> fmt::Debug for TokenItem<'source> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}({:?})", self.token, self.slice())
     }
 }
 
-impl<'source, Token, Source> TokenItem<Token, Source>
-where
-    Token: self::Logos + WithSource<Source>,
-    Source: self::Source<'source>,
-{
-    /// Get the range for the current token in `Source`.
+impl<'source> TokenItem<'source> {
+    /// Get the range for the current token in the source.
     #[inline]
     pub fn range(&self) -> Range<usize> {
-        self.token_start..self.token_end
+        self.range.clone()
     }
 
     /// Get a string slice of the current token.
     #[inline]
     pub fn slice(&self) -> &'source str {
-        unsafe {
-            std::str::from_utf8_unchecked(
-                self.source
-                    .slice_unchecked(self.token_start..self.token_end)
-                    .as_bytes(),
-            )
-        }
+        self.slice
     }
 }
 
-impl<'source, Source> Iterator for LexerWrapper<Source>
-where
-    Source: self::Source<'source> + Copy,
-{
-    type Item = TokenItem<Token, Source>;
+impl<'source> Iterator for LexerWrapper<'source> {
+    type Item = TokenItem<'source>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while Token::LineComment == self.token {
-            self.advance();
-        }
-
-        if Token::END == self.token {
-            None
-        } else {
-            let range = self.range();
-            let res = Some(TokenItem {
-                token: self.token,
-                source: self.source,
-                token_start: range.start,
-                token_end: range.end,
-            });
-            self.advance();
-            res
+        loop {
+            let token = self.0.next()?;
+            if token != Token::LineComment {
+                return Some(TokenItem {
+                    token,
+                    slice: self.slice(),
+                    range: self.span(),
+                });
+            }
         }
     }
 }
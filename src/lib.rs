@@ -1,15 +1,19 @@
 //! # POOP
 //! This crate contains the libraries for parsing the language POOP.
-//! Currently it only supports interpreting the language, but there are
-//! plans to expand the capabilities to compile the language.
+//! Programs can either be run directly using the JIT in [`execution_engine`],
+//! or compiled ahead of time into native object files and executables
+//! using [`target_machine`].
 
 #![warn(missing_docs)]
 
 extern crate llvm_sys as llvm;
 
 pub mod codegen;
+pub mod diagnostic;
 pub mod execution_engine;
 pub mod lexer;
 pub mod mir;
 pub mod parser;
+pub mod span;
+pub mod target_machine;
 pub mod type_system;
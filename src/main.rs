@@ -1,26 +1,367 @@
+//! Command line driver for the POOP compiler.
+//!
+//! ```text
+//! poop <COMMAND> [OPTIONS] <INPUT>... [-- <ARGS>...]
+//! ```
+
 use logos::Logos;
-use poop::codegen::Codegen;
+use poop::codegen::optimization::{OptimizationLevel, PASSES};
+use poop::codegen::{error::CodegenError, Codegen};
+use poop::diagnostic::{Diagnostic, Emitter};
 use poop::execution_engine::ExecutionEngine;
-use poop::lexer::{wrapper::LexerWrapper, RangeConverter, Token};
-use poop::parser::Parser;
+use poop::lexer::{wrapper::LexerWrapper, Token};
+use poop::parser::{ast, Parser};
+use poop::span::SourceMap;
+use poop::target_machine::{self, FileType, TargetMachine};
+use poop::type_system::TypeSystem;
+use std::env;
 use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+
+const USAGE: &str = "\
+Usage: poop <COMMAND> [OPTIONS] <INPUT>... [-- <ARGS>...]
+
+Commands:
+    run      Compile the program and run it using the JIT
+    check    Lex, parse and type check the program without compiling it
+    build    Compile the program into an artifact
+    emit     Print the generated LLVM IR
+    passes   List the optimization passes that can be used with --passes
+
+Options:
+    -o <FILE>            Write the output to <FILE>
+    -O<LEVEL>            Optimization level: 0 (default), 1, 2, 3 or s
+    --passes <PASSES>    Comma separated list of optimization passes to run
+                         instead of the ones given by the optimization level
+    --emit <ARTIFACT>    Artifact produced by `build`, one of:
+                         exe (default), obj, asm, llvm-ir, llvm-bc
+    --color <WHEN>       Colour the error messages: auto (default), always or never
+    -h, --help           Print this message
+
+Arguments after `--` are passed on to the program when using `run`.";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Run,
+    Check,
+    Build,
+    Emit,
+    Passes,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Artifact {
+    Executable,
+    Object,
+    Assembly,
+    Ir,
+    Bitcode,
+}
+
+impl Artifact {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "exe" => Ok(Artifact::Executable),
+            "obj" => Ok(Artifact::Object),
+            "asm" => Ok(Artifact::Assembly),
+            "llvm-ir" => Ok(Artifact::Ir),
+            "llvm-bc" => Ok(Artifact::Bitcode),
+            name => Err(format!("Unknown artifact '{}'", name)),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Artifact::Executable => "",
+            Artifact::Object => "o",
+            Artifact::Assembly => "s",
+            Artifact::Ir => "ll",
+            Artifact::Bitcode => "bc",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Colour {
+    Auto,
+    Always,
+    Never,
+}
+
+impl Colour {
+    fn parse(when: &str) -> Result<Self, String> {
+        match when {
+            "auto" => Ok(Colour::Auto),
+            "always" => Ok(Colour::Always),
+            "never" => Ok(Colour::Never),
+            when => Err(format!("Unknown colour choice '{}'", when)),
+        }
+    }
+
+    /// Whether to use colours. `auto` only uses colours when stderr is a terminal,
+    /// unless `NO_COLOR` is set or the terminal is dumb.
+    fn enabled(self) -> bool {
+        match self {
+            Colour::Auto => {
+                io::stderr().is_terminal()
+                    && env::var_os("NO_COLOR").is_none()
+                    && env::var("TERM").is_ok_and(|term| term != "dumb")
+            }
+            Colour::Always => true,
+            Colour::Never => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    command: Command,
+    inputs: Vec<String>,
+    output: Option<String>,
+    emit: Artifact,
+    optimization: OptimizationLevel,
+    passes: Option<Vec<String>>,
+    colour: Colour,
+    args: Vec<String>,
+}
+
+impl Options {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let command = match args.next().as_deref() {
+            Some("run") => Command::Run,
+            Some("check") => Command::Check,
+            Some("build") => Command::Build,
+            Some("emit") => Command::Emit,
+            Some("passes") => Command::Passes,
+            Some("-h") | Some("--help") => return Err(String::new()),
+            Some(command) => return Err(format!("Unknown command '{}'", command)),
+            None => return Err(String::from("No command given")),
+        };
+
+        let mut options = Options {
+            command,
+            inputs: Vec::new(),
+            output: None,
+            emit: Artifact::Executable,
+            optimization: OptimizationLevel::default(),
+            passes: None,
+            colour: Colour::Auto,
+            args: Vec::new(),
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--" => {
+                    options.args.extend(args);
+                    break;
+                }
+                "-h" | "--help" => return Err(String::new()),
+                "-o" => match args.next() {
+                    Some(output) => options.output = Some(output),
+                    None => return Err(String::from("Option '-o' requires a file name")),
+                },
+                "--emit" => match args.next() {
+                    Some(artifact) => options.emit = Artifact::parse(&artifact)?,
+                    None => return Err(String::from("Option '--emit' requires an artifact")),
+                },
+                "--passes" => match args.next() {
+                    Some(passes) => {
+                        options.passes = Some(passes.split(',').map(String::from).collect())
+                    }
+                    None => {
+                        return Err(String::from("Option '--passes' requires a list of passes"))
+                    }
+                },
+                "--color" => match args.next() {
+                    Some(when) => options.colour = Colour::parse(&when)?,
+                    None => return Err(String::from("Option '--color' requires a value")),
+                },
+                flag if flag.starts_with("-O") => {
+                    options.optimization = OptimizationLevel::parse(&flag[2..])
+                        .ok_or_else(|| format!("Unknown optimization level '{}'", flag))?
+                }
+                flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+                input => options.inputs.push(String::from(input)),
+            }
+        }
+
+        if options.inputs.is_empty() && options.command != Command::Passes {
+            Err(String::from("No input files given"))
+        } else {
+            Ok(options)
+        }
+    }
+
+    /// The name used for the module and default output files,
+    /// taken from the first input file.
+    fn module_name(&self) -> String {
+        Path::new(&self.inputs[0])
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("main"))
+    }
+
+    /// The output path, defaulting to the module name with the extension of the artifact.
+    fn output_path(&self) -> PathBuf {
+        match &self.output {
+            Some(output) => PathBuf::from(output),
+            None => Path::new(&self.module_name()).with_extension(self.emit.extension()),
+        }
+    }
+}
 
 fn main() {
-    let program = fs::read_to_string("test.poop").unwrap();
-    println!("Running the following program: \"\"\"\n{}\n\"\"\"", program);
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            if message.is_empty() {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    process::exit(drive(&options).unwrap_or(1));
+}
+
+/// Runs the compiler pipeline for the given options,
+/// returning the exit code of the process.
+fn drive(options: &Options) -> Result<i32, ()> {
+    if let Command::Passes = options.command {
+        for (name, description, _) in PASSES {
+            println!("{:<24}{}", name, description);
+        }
+        return Ok(0);
+    }
 
-    let range_converter = RangeConverter::new(&program);
-    let lexer = LexerWrapper(Token::lexer(&program as &str));
-    let mut parser = Parser::new(lexer, range_converter);
-    let program = parser.parse().expect("This should be able to parse");
+    let emitter = Emitter::new(options.colour.enabled());
+    let mut source_map = SourceMap::new();
+    let report = |err: CodegenError, source_map: &SourceMap| {
+        emitter.emit(&Diagnostic::from(&err), source_map)
+    };
 
-    let mut codegen = Codegen::new("test");
+    let mut program = parse_inputs(&options.inputs, &mut source_map, &emitter)?;
+    let source_map = Rc::new(source_map);
+    if let Err(errors) = TypeSystem::analyze(&mut program) {
+        for err in errors.iter() {
+            emitter.emit(&Diagnostic::from(err), &source_map);
+        }
+        return Err(());
+    }
+    if let Command::Check = options.command {
+        return Ok(0);
+    }
+
+    let mut codegen = Codegen::new(&options.module_name()).with_source_map(source_map.clone());
     let main_fn = codegen.build_program(program);
+    match &options.passes {
+        Some(passes) => {
+            let passes: Vec<&str> = passes.iter().map(String::as_str).collect();
+            codegen
+                .run_passes(&passes)
+                .map_err(|err| report(err, &source_map))?;
+        }
+        None => codegen.optimize(options.optimization),
+    }
+
+    match options.command {
+        Command::Run => {
+            let mut argv = vec![options.inputs[0].as_str()];
+            argv.extend(options.args.iter().map(String::as_str));
+            let mut ee = ExecutionEngine::new(codegen);
+            // The main function is built into the module run by the engine
+            unsafe { ee.check_main(main_fn) }.map_err(|err| report(err, &source_map))?;
+            Ok(unsafe { ee.run_as_main(main_fn, &argv) })
+        }
+        Command::Build => build(options, &codegen)
+            .map(|_| 0)
+            .map_err(|err| report(err, &source_map)),
+        Command::Emit => match &options.output {
+            Some(output) => codegen
+                .print_to_file(Path::new(output))
+                .map(|_| 0)
+                .map_err(|err| report(err, &source_map)),
+            None => {
+                print!("{}", codegen.print_to_string());
+                Ok(0)
+            }
+        },
+        Command::Check | Command::Passes => unreachable!(),
+    }
+}
 
-    let mut ee = ExecutionEngine::new(codegen);
-    println!("Return code: {}", ee.run_as_main(main_fn, &[]));
+/// Compiles the generated module into the artifact requested by the options.
+fn build(options: &Options, codegen: &Codegen) -> Result<(), CodegenError> {
+    let output = options.output_path();
+    let module = codegen.module();
+    let module = module.borrow();
+    match options.emit {
+        Artifact::Ir => module.print_to_file(&output),
+        Artifact::Bitcode => module.write_bitcode_to_file(&output),
+        Artifact::Object => TargetMachine::host(options.optimization)?.emit_to_file(
+            &module,
+            &output,
+            FileType::Object,
+        ),
+        Artifact::Assembly => TargetMachine::host(options.optimization)?.emit_to_file(
+            &module,
+            &output,
+            FileType::Assembly,
+        ),
+        Artifact::Executable => {
+            let object =
+                env::temp_dir().join(format!("{}-{}.o", options.module_name(), process::id()));
+            TargetMachine::host(options.optimization)?.emit_to_file(
+                &module,
+                &object,
+                FileType::Object,
+            )?;
+            let result = target_machine::link(&[&object], &output);
+            let _ = fs::remove_file(&object);
+            result
+        }
+    }
+}
 
-    //let ee_main = ee.get_function("expr");
+/// Reads and parses every input file, combining them into a single program.
+/// The files are added to `source_map`, so later errors can refer to them.
+/// All syntax errors in all the files are reported before failing.
+fn parse_inputs(
+    inputs: &[String],
+    source_map: &mut SourceMap,
+    emitter: &Emitter,
+) -> Result<ast::Program, ()> {
+    let mut program = ast::Program::Empty;
+    let mut failed = false;
+    for input in inputs {
+        let source = fs::read_to_string(input).map_err(|err| {
+            emitter.emit(
+                &Diagnostic::error(format!("Could not read '{}': {}", input, err)),
+                source_map,
+            )
+        })?;
 
-    //ee_main();
+        let file = source_map.add(input.as_str(), source);
+        let source = source_map.get(file).unwrap().source();
+        let lexer = LexerWrapper(Token::lexer(source));
+        let mut parser = Parser::new_with_file(lexer, file);
+        match parser.parse() {
+            Ok(parsed) => program = program.append(parsed),
+            Err(errors) => {
+                for err in errors.iter() {
+                    emitter.emit(&Diagnostic::from(err), source_map);
+                }
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        Err(())
+    } else {
+        Ok(program)
+    }
 }
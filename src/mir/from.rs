@@ -21,7 +21,13 @@ impl From<ast::Decl> for Decl {
     fn from(decl: ast::Decl) -> Self {
         match decl {
             ast::Decl::FuncDecl(func_decl) => Decl::FuncDecl(func_decl.into()),
+            ast::Decl::ExternDecl(extern_decl) => Decl::ExternDecl(extern_decl.into()),
+            ast::Decl::StructDecl(struct_decl) => Decl::StructDecl(struct_decl.into()),
+            ast::Decl::EnumDecl(enum_decl) => Decl::EnumDecl(enum_decl.into()),
+            ast::Decl::ClassDecl(class_decl) => Decl::ClassDecl(class_decl.into()),
             ast::Decl::VarDecl(var_decl) => Decl::VarDecl((&var_decl).into()),
+            ast::Decl::ConstDecl(var_decl) => Decl::ConstDecl((&var_decl).into()),
+            ast::Decl::Error(_) => unreachable!("Programs with syntax errors have no MIR"),
         }
     }
 }
@@ -37,6 +43,61 @@ impl From<ast::FuncDecl> for FuncDecl {
     }
 }
 
+impl From<ast::ExternDecl> for ExternDecl {
+    fn from(extern_decl: ast::ExternDecl) -> Self {
+        ExternDecl::new(
+            &extern_decl.name,
+            extern_decl.args.iter().map(|arg| arg.into()).collect(),
+            extern_decl.return_type.into(),
+            extern_decl.variadic,
+        )
+    }
+}
+
+impl From<ast::StructDecl> for StructDecl {
+    fn from(struct_decl: ast::StructDecl) -> Self {
+        StructDecl::new(
+            &struct_decl.name,
+            struct_decl
+                .fields
+                .iter()
+                .map(|field| field.into())
+                .collect(),
+        )
+    }
+}
+
+impl From<ast::EnumDecl> for EnumDecl {
+    fn from(enum_decl: ast::EnumDecl) -> Self {
+        EnumDecl::new(
+            &enum_decl.name,
+            enum_decl
+                .variants
+                .into_iter()
+                .map(|variant| {
+                    let fields = variant.fields.into_iter().map(Type::from).collect();
+                    (variant.name, fields)
+                })
+                .collect(),
+        )
+    }
+}
+
+impl From<ast::ClassDecl> for ClassDecl {
+    fn from(class_decl: ast::ClassDecl) -> Self {
+        ClassDecl::new(
+            &class_decl.name,
+            class_decl.fields.iter().map(|field| field.into()).collect(),
+            class_decl.constructor.map(FuncDecl::from),
+            class_decl
+                .methods
+                .into_iter()
+                .map(|method| MethodDecl::new(method.func_decl.into(), method.mutable))
+                .collect(),
+        )
+    }
+}
+
 impl From<&ast::ArgDecl> for ArgDecl {
     fn from(arg_decl: &ast::ArgDecl) -> Self {
         ArgDecl::new(&arg_decl.name, arg_decl.arg_type.clone().into())
@@ -52,7 +113,11 @@ impl From<ast::Type> for Type {
             ast::Type::Int => Self::Int,
             ast::Type::String => Self::String,
             ast::Type::Void => Self::Void,
+            ast::Type::Never => Self::Never,
             ast::Type::UserDefined(name) => Self::UserDefined(name),
+            ast::Type::Array(element_type, length) => {
+                Self::Array(Box::new((*element_type).into()), length)
+            }
         }
     }
 }
@@ -63,6 +128,7 @@ impl From<&ast::Statement> for Statement {
             ast::Statement::VarDecl(var_decl) => Statement::VarDecl(var_decl.into()),
             ast::Statement::Expression(expr) => Statement::Expression(expr.into()),
             ast::Statement::Empty => Statement::Empty,
+            ast::Statement::Error(_) => unreachable!("Programs with syntax errors have no MIR"),
         }
     }
 }
@@ -83,7 +149,11 @@ impl From<&ast::VarDecl> for VarDecl {
     fn from(var_decl: &ast::VarDecl) -> Self {
         Self::new(
             var_decl.identifier.clone(),
-            Type::NotYetInferred(Vec::new()),
+            var_decl.mutable,
+            var_decl
+                .var_type
+                .clone()
+                .map_or_else(|| Type::NotYetInferred(Vec::new()), Type::from),
             var_decl.expression.clone().into(),
         )
     }
@@ -91,103 +161,155 @@ impl From<&ast::VarDecl> for VarDecl {
 
 impl From<&ast::Expression> for Expression {
     fn from(expr: &ast::Expression) -> Self {
-        match expr {
-            ast::Expression::Addition(lhs, rhs) => {
+        match &expr.kind {
+            ast::ExpressionKind::Addition(lhs, rhs) => {
                 Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
                     (**lhs).clone().into(),
                     (**rhs).clone().into(),
                     Operator::Plus,
                 )))
             }
-            ast::Expression::Subtraction(lhs, rhs) => {
+            ast::ExpressionKind::Subtraction(lhs, rhs) => {
                 Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
                     (**lhs).clone().into(),
                     (**rhs).clone().into(),
                     Operator::Minus,
                 )))
             }
-            ast::Expression::Multiplication(lhs, rhs) => {
+            ast::ExpressionKind::Multiplication(lhs, rhs) => {
                 Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
                     (**lhs).clone().into(),
                     (**rhs).clone().into(),
                     Operator::Star,
                 )))
             }
-            ast::Expression::Division(lhs, rhs) => {
+            ast::ExpressionKind::Division(lhs, rhs) => {
                 Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
                     (**lhs).clone().into(),
                     (**rhs).clone().into(),
                     Operator::Slash,
                 )))
             }
-            ast::Expression::Modulus(lhs, rhs) => {
+            ast::ExpressionKind::Modulus(lhs, rhs) => {
                 Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
                     (**lhs).clone().into(),
                     (**rhs).clone().into(),
                     Operator::Percent,
                 )))
             }
-            ast::Expression::Equality(lhs, rhs) => {
+            ast::ExpressionKind::Equality(lhs, rhs) => {
                 Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
                     (**lhs).clone().into(),
                     (**rhs).clone().into(),
                     Operator::Equality,
                 )))
             }
-            ast::Expression::NotEq(lhs, rhs) => {
+            ast::ExpressionKind::NotEq(lhs, rhs) => {
                 Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
                     (**lhs).clone().into(),
                     (**rhs).clone().into(),
                     Operator::NotEq,
                 )))
             }
-            ast::Expression::LessThan(lhs, rhs) => {
+            ast::ExpressionKind::LessThan(lhs, rhs) => {
                 Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
                     (**lhs).clone().into(),
                     (**rhs).clone().into(),
                     Operator::LessThan,
                 )))
             }
-            ast::Expression::GreaterThan(lhs, rhs) => {
+            ast::ExpressionKind::GreaterThan(lhs, rhs) => {
                 Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
                     (**lhs).clone().into(),
                     (**rhs).clone().into(),
                     Operator::GreaterThan,
                 )))
             }
-            ast::Expression::LessEq(lhs, rhs) => {
+            ast::ExpressionKind::LessEq(lhs, rhs) => {
                 Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
                     (**lhs).clone().into(),
                     (**rhs).clone().into(),
                     Operator::LessEq,
                 )))
             }
-            ast::Expression::GreaterEq(lhs, rhs) => {
+            ast::ExpressionKind::GreaterEq(lhs, rhs) => {
                 Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
                     (**lhs).clone().into(),
                     (**rhs).clone().into(),
                     Operator::GreaterEq,
                 )))
             }
-            ast::Expression::And(lhs, rhs) => {
+            ast::ExpressionKind::And(lhs, rhs) => {
                 Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
                     (**lhs).clone().into(),
                     (**rhs).clone().into(),
                     Operator::And,
                 )))
             }
-            ast::Expression::Or(lhs, rhs) => Self::BinaryOp(BinaryOpContainer::new(BinaryOp::new(
-                (**lhs).clone().into(),
-                (**rhs).clone().into(),
-                Operator::Or,
+            ast::ExpressionKind::Or(lhs, rhs) => Self::BinaryOp(BinaryOpContainer::new(
+                BinaryOp::new((**lhs).clone().into(), (**rhs).clone().into(), Operator::Or),
+            )),
+            ast::ExpressionKind::Not(expr) => Self::UnaryOp(UnaryOpContainer::new(UnaryOp::new(
+                (**expr).clone().into(),
+                Operator::Not,
             ))),
-            ast::Expression::Not(expr) => Self::UnaryOp(UnaryOpContainer::new(UnaryOp::new(
+            ast::ExpressionKind::Neg(expr) => Self::UnaryOp(UnaryOpContainer::new(UnaryOp::new(
                 (**expr).clone().into(),
-                Operator::Or,
+                Operator::Neg,
             ))),
-            ast::Expression::If(expr) => Self::If(expr.into()),
-            ast::Expression::Block(block) => Self::Block(block.into()),
-            ast::Expression::Value(value) => Self::Value((*value).clone().into()),
+            ast::ExpressionKind::Field(value, name) => {
+                Self::Field(Box::new((&**value).into()), name.clone())
+            }
+            ast::ExpressionKind::MethodCall(object, call) => {
+                Self::MethodCall(Box::new((&**object).into()), call.clone().into())
+            }
+            ast::ExpressionKind::Index(value, index) => {
+                Self::Index(Box::new((&**value).into()), Box::new((&**index).into()))
+            }
+            ast::ExpressionKind::CompoundAssignment(op, target, value) => {
+                let op = match op {
+                    ast::AssignmentOp::Add => Operator::Plus,
+                    ast::AssignmentOp::Sub => Operator::Minus,
+                    ast::AssignmentOp::Mul => Operator::Star,
+                    ast::AssignmentOp::Div => Operator::Slash,
+                    ast::AssignmentOp::Mod => Operator::Percent,
+                };
+                Self::CompoundAssignment(
+                    op,
+                    AssignmentContainer::new(Assignment::new(
+                        (&**target).into(),
+                        (&**value).into(),
+                    )),
+                )
+            }
+            ast::ExpressionKind::Assignment(target, value) => Self::Assignment(
+                AssignmentContainer::new(Assignment::new((&**target).into(), (&**value).into())),
+            ),
+            ast::ExpressionKind::If(expr) => Self::If(expr.into()),
+            ast::ExpressionKind::Match(expr) => Self::Match(expr.into()),
+            ast::ExpressionKind::While(condition, body) => {
+                Self::While(WhileExpressionContainer::new(WhileExpression::new(
+                    (**condition).clone().into(),
+                    body.into(),
+                )))
+            }
+            ast::ExpressionKind::Loop(loop_expr) => Self::Loop(LoopExpression::new(
+                (&loop_expr.body).into(),
+                loop_expr
+                    .result_type
+                    .clone()
+                    .map_or_else(|| Type::NotYetInferred(Vec::new()), Type::from),
+            )),
+            ast::ExpressionKind::Break(value) => {
+                Self::Break(value.as_ref().map(|value| Box::new((&**value).into())))
+            }
+            ast::ExpressionKind::Continue => Self::Continue,
+            ast::ExpressionKind::Return(value) => {
+                Self::Return(value.as_ref().map(|value| Box::new((&**value).into())))
+            }
+            ast::ExpressionKind::Block(block) => Self::Block(block.into()),
+            ast::ExpressionKind::Value(value) => Self::Value((*value).clone().into()),
+            ast::ExpressionKind::Error => unreachable!("Programs with syntax errors have no MIR"),
         }
     }
 }
@@ -205,10 +327,45 @@ impl From<&ast::IfExpressionContainer> for IfExpressionContainer {
             if_expr.condition.into(),
             if_expr.body.into(),
             if_expr.else_expression.into(),
+            if_expr
+                .result_type
+                .map_or_else(|| Type::NotYetInferred(Vec::new()), Type::from),
         ))
     }
 }
 
+impl From<&ast::MatchExpressionContainer> for MatchExpressionContainer {
+    fn from(match_expr: &ast::MatchExpressionContainer) -> Self {
+        MatchExpressionContainer::new(MatchExpression::new(
+            (&match_expr.value).into(),
+            match_expr
+                .arms
+                .iter()
+                .map(|arm| ((&arm.pattern).into(), (&arm.body).into()))
+                .collect(),
+            match_expr
+                .result_type
+                .clone()
+                .map_or_else(|| Type::NotYetInferred(Vec::new()), Type::from),
+        ))
+    }
+}
+
+impl From<&ast::Pattern> for Pattern {
+    fn from(pattern: &ast::Pattern) -> Self {
+        match &pattern.kind {
+            ast::PatternKind::Wildcard => Self::Wildcard,
+            ast::PatternKind::Binding(name) => Self::Binding(name.clone()),
+            ast::PatternKind::Literal(lit) => Self::Literal(lit.clone().into()),
+            ast::PatternKind::Variant(enum_name, variant, patterns) => Self::Variant(
+                enum_name.clone(),
+                variant.clone(),
+                patterns.iter().map(|pattern| pattern.into()).collect(),
+            ),
+        }
+    }
+}
+
 impl From<ast::ElseExpression> for ElseExpression {
     fn from(else_expression: ast::ElseExpression) -> Self {
         match else_expression {
@@ -227,10 +384,42 @@ impl From<ast::Value> for Value {
             ast::Value::Literal(lit) => Self::Literal(lit.into()),
             ast::Value::Variable(name) => Self::Variable(name),
             ast::Value::FunctionCall(func) => Self::FunctionCall(func.into()),
+            ast::Value::Struct(literal) => Self::Struct(literal.into()),
+            ast::Value::Variant(literal) => Self::Variant(literal.into()),
+            ast::Value::Array(elements) => {
+                Self::Array(elements.iter().map(|element| element.into()).collect())
+            }
         }
     }
 }
 
+impl From<ast::StructLiteral> for StructLiteral {
+    fn from(literal: ast::StructLiteral) -> Self {
+        Self::new(
+            &literal.name,
+            literal
+                .fields
+                .iter()
+                .map(|field| (field.name.clone(), (&field.value).into()))
+                .collect(),
+        )
+    }
+}
+
+impl From<ast::VariantLiteral> for VariantLiteral {
+    fn from(literal: ast::VariantLiteral) -> Self {
+        Self::new(
+            &literal.enum_name,
+            &literal.variant,
+            literal
+                .arguments
+                .iter()
+                .map(|argument| argument.into())
+                .collect(),
+        )
+    }
+}
+
 impl From<ast::Literal> for Literal {
     fn from(lit: ast::Literal) -> Self {
         match lit {
@@ -22,8 +22,18 @@ pub struct Program {
 pub enum Decl {
     /// Variable declaration
     VarDecl(VarDecl),
+    /// Constant declaration
+    ConstDecl(VarDecl),
     /// Function declaration
     FuncDecl(FuncDecl),
+    /// External function declaration
+    ExternDecl(ExternDecl),
+    /// Struct declaration
+    StructDecl(StructDecl),
+    /// Enum declaration
+    EnumDecl(EnumDecl),
+    /// Class declaration
+    ClassDecl(ClassDecl),
 }
 
 /// Function declaration
@@ -56,7 +66,121 @@ impl HasType for FuncDecl {
     }
 }
 
-/// Argument declaration
+/// Declaration of a function defined outside of the program
+#[derive(Debug, PartialEq)]
+pub struct ExternDecl {
+    /// Function name
+    pub name: String,
+    /// Function arguments
+    pub args: Vec<ArgDecl>,
+    return_type: Type,
+    /// Whether the function takes extra arguments after `args`
+    pub variadic: bool,
+}
+
+impl ExternDecl {
+    /// Creates a new external function declaration
+    pub fn new(name: &str, args: Vec<ArgDecl>, return_type: Type, variadic: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            args,
+            return_type,
+            variadic,
+        }
+    }
+}
+
+impl HasType for ExternDecl {
+    fn return_type(&self) -> Option<Type> {
+        Some(self.return_type.clone())
+    }
+}
+
+/// Struct declaration
+#[derive(Debug, PartialEq)]
+pub struct StructDecl {
+    /// Struct name
+    pub name: String,
+    /// Struct fields
+    pub fields: Vec<ArgDecl>,
+}
+
+impl StructDecl {
+    /// Creates a new struct declaration
+    pub fn new(name: &str, fields: Vec<ArgDecl>) -> Self {
+        Self {
+            name: name.to_string(),
+            fields,
+        }
+    }
+}
+
+/// Enum declaration
+#[derive(Debug, PartialEq)]
+pub struct EnumDecl {
+    /// Enum name
+    pub name: String,
+    /// Variant names, with the types of the values they hold
+    pub variants: Vec<(String, Vec<Type>)>,
+}
+
+impl EnumDecl {
+    /// Creates a new enum declaration
+    pub fn new(name: &str, variants: Vec<(String, Vec<Type>)>) -> Self {
+        Self {
+            name: name.to_string(),
+            variants,
+        }
+    }
+}
+
+/// Class declaration
+#[derive(Debug, PartialEq)]
+pub struct ClassDecl {
+    /// Class name
+    pub name: String,
+    /// Class fields
+    pub fields: Vec<ArgDecl>,
+    /// The constructor, `new`
+    pub constructor: Option<FuncDecl>,
+    /// Class methods
+    pub methods: Vec<MethodDecl>,
+}
+
+impl ClassDecl {
+    /// Creates a new class declaration
+    pub fn new(
+        name: &str,
+        fields: Vec<ArgDecl>,
+        constructor: Option<FuncDecl>,
+        methods: Vec<MethodDecl>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            fields,
+            constructor,
+            methods,
+        }
+    }
+}
+
+/// Method declaration
+#[derive(Debug, PartialEq)]
+pub struct MethodDecl {
+    /// Whether the method takes `mut self`
+    pub mutable: bool,
+    /// The method, without `self` in its arguments
+    pub func_decl: FuncDecl,
+}
+
+impl MethodDecl {
+    /// Creates a new method declaration
+    pub fn new(func_decl: FuncDecl, mutable: bool) -> Self {
+        Self { mutable, func_decl }
+    }
+}
+
+/// Argument or field declaration
 #[derive(Debug, PartialEq)]
 pub struct ArgDecl {
     /// Argument name
@@ -96,8 +220,12 @@ pub enum Type {
     String,
     /// Void
     Void,
+    /// The type of expressions which never produce a value
+    Never,
     /// User defined type. Currently not supporting inheritance
     UserDefined(String),
+    /// Array of a fixed number of elements
+    Array(Box<Type>, usize),
     /// Used internally for when no type has been inferred yet
     NotYetInferred(Vec<Type>),
 }
@@ -118,7 +246,9 @@ impl fmt::Display for Type {
             Type::Boolean => write!(f, "boolean"),
             Type::String => write!(f, "string"),
             Type::Void => write!(f, "void"),
+            Type::Never => write!(f, "never"),
             Type::UserDefined(name) => write!(f, "{}", name),
+            Type::Array(element_type, length) => write!(f, "[{}; {}]", element_type, length),
             Type::NotYetInferred(possibilities) => {
                 write!(f, "Type is not inferred yet. Possible types:")?;
                 for possiblility in possibilities.iter() {
@@ -181,6 +311,8 @@ impl Deref for Block {
 pub struct VarDecl {
     /// Name of the variable
     pub identifier: String,
+    /// Whether the variable can be assigned to
+    pub mutable: bool,
     var_type: Type,
     /// Expression assigned to the variable
     pub expression: Expression,
@@ -188,9 +320,10 @@ pub struct VarDecl {
 
 impl VarDecl {
     /// Creates a new variable declaration
-    pub fn new(identifier: String, var_type: Type, expression: Expression) -> Self {
+    pub fn new(identifier: String, mutable: bool, var_type: Type, expression: Expression) -> Self {
         Self {
             identifier,
+            mutable,
             var_type,
             expression,
         }
@@ -210,8 +343,30 @@ pub enum Expression {
     BinaryOp(BinaryOpContainer),
     /// Unary operation expression
     UnaryOp(UnaryOpContainer),
+    /// Field access expression
+    Field(Box<Expression>, String),
+    /// Method call expression
+    MethodCall(Box<Expression>, FunctionCall),
+    /// Array indexing expression
+    Index(Box<Expression>, Box<Expression>),
+    /// Assignment expression
+    Assignment(AssignmentContainer),
+    /// Compound assignment expression, like `x += 1`
+    CompoundAssignment(Operator, AssignmentContainer),
     /// If expression
     If(IfExpressionContainer),
+    /// Match expression
+    Match(MatchExpressionContainer),
+    /// While loop expression
+    While(WhileExpressionContainer),
+    /// Loop expression
+    Loop(LoopExpression),
+    /// Break expression, with an optional value for the loop
+    Break(Option<Box<Expression>>),
+    /// Continue expression
+    Continue,
+    /// Return expression, with an optional value
+    Return(Option<Box<Expression>>),
     /// Block expression
     Block(Block),
     /// Value expression
@@ -271,6 +426,25 @@ impl HasType for UnaryOp {
     }
 }
 
+/// Container used to go around the circular nature
+pub type AssignmentContainer = Box<Assignment>;
+
+/// Assignment of a value to a variable
+#[derive(Debug, PartialEq)]
+pub struct Assignment {
+    /// The variable being assigned to
+    pub target: Expression,
+    /// The new value
+    pub value: Expression,
+}
+
+impl Assignment {
+    /// Creates a new assignment
+    pub fn new(target: Expression, value: Expression) -> Self {
+        Self { target, value }
+    }
+}
+
 /// Types of basic operators
 #[derive(Debug, PartialEq)]
 pub enum Operator {
@@ -302,6 +476,8 @@ pub enum Operator {
     Or,
     /// Not `!`
     Not,
+    /// Negation `-`
+    Neg,
 }
 
 /// Container used to go around the circular nature
@@ -316,19 +492,115 @@ pub struct IfExpression {
     pub body: Block,
     /// Else expression
     pub else_expression: ElseExpression,
+    result_type: Type,
 }
 
 impl IfExpression {
     /// Creates a new if expression
-    pub fn new(condition: Expression, body: Block, else_expression: ElseExpression) -> Self {
+    pub fn new(
+        condition: Expression,
+        body: Block,
+        else_expression: ElseExpression,
+        result_type: Type,
+    ) -> Self {
         Self {
             condition,
             body,
             else_expression,
+            result_type,
+        }
+    }
+}
+
+impl HasType for IfExpression {
+    fn return_type(&self) -> Option<Type> {
+        Some(self.result_type.clone())
+    }
+}
+
+/// Container used to go around the circular nature
+pub type MatchExpressionContainer = Box<MatchExpression>;
+
+/// Match expression
+#[derive(Debug, PartialEq)]
+pub struct MatchExpression {
+    /// The value being matched
+    pub value: Expression,
+    /// Patterns and bodies of the arms, tried in order
+    pub arms: Vec<(Pattern, Expression)>,
+    result_type: Type,
+}
+
+impl MatchExpression {
+    /// Creates a new match expression
+    pub fn new(value: Expression, arms: Vec<(Pattern, Expression)>, result_type: Type) -> Self {
+        Self {
+            value,
+            arms,
+            result_type,
         }
     }
 }
 
+impl HasType for MatchExpression {
+    fn return_type(&self) -> Option<Type> {
+        Some(self.result_type.clone())
+    }
+}
+
+/// Pattern of a match arm
+#[derive(Debug, PartialEq)]
+pub enum Pattern {
+    /// Matches any value
+    Wildcard,
+    /// Matches any value, binding it to the name
+    Binding(String),
+    /// Matches only the same value
+    Literal(Literal),
+    /// Matches the variant of the enum, when the patterns match its values
+    Variant(String, String, Vec<Pattern>),
+}
+
+/// Container used to go around the circular nature
+pub type WhileExpressionContainer = Box<WhileExpression>;
+
+/// While loop expression
+#[derive(Debug, PartialEq)]
+pub struct WhileExpression {
+    /// Condition checked before each iteration
+    pub condition: Expression,
+    /// Body block
+    pub body: Block,
+}
+
+impl WhileExpression {
+    /// Creates a new while loop
+    pub fn new(condition: Expression, body: Block) -> Self {
+        Self { condition, body }
+    }
+}
+
+/// Loop expression, only ending with `break`
+#[derive(Debug, PartialEq)]
+pub struct LoopExpression {
+    /// Body block
+    pub body: Block,
+    result_type: Type,
+}
+
+impl LoopExpression {
+    /// Creates a new loop
+    pub fn new(body: Block, result_type: Type) -> Self {
+        Self { body, result_type }
+    }
+}
+
+impl HasType for LoopExpression {
+    fn return_type(&self) -> Option<Type> {
+        Some(self.result_type.clone())
+    }
+}
+
 /// Else expression can either be an else block, another if expression or nothing
 #[derive(Debug, PartialEq)]
 pub enum ElseExpression {
@@ -349,6 +621,65 @@ pub enum Value {
     Variable(String),
     /// Function call value
     FunctionCall(FunctionCall),
+    /// Struct literal value
+    Struct(StructLiteral),
+    /// Array literal value
+    Array(Vec<Expression>),
+    /// Enum value
+    Variant(VariantLiteral),
+}
+
+/// Struct literal
+#[derive(Debug, PartialEq)]
+pub struct StructLiteral {
+    /// Name of the struct
+    pub name: String,
+    /// Field names and values, in the order they are written
+    pub fields: Vec<(String, Expression)>,
+}
+
+impl StructLiteral {
+    /// Creates a new struct literal
+    pub fn new(name: &str, fields: Vec<(String, Expression)>) -> Self {
+        Self {
+            name: String::from(name),
+            fields,
+        }
+    }
+}
+
+impl HasType for StructLiteral {
+    fn return_type(&self) -> Option<Type> {
+        Some(Type::UserDefined(self.name.clone()))
+    }
+}
+
+/// Enum value, e.g. `Shape::Rect(2, 3)`
+#[derive(Debug, PartialEq)]
+pub struct VariantLiteral {
+    /// Name of the enum
+    pub enum_name: String,
+    /// Name of the variant
+    pub variant: String,
+    /// Values held by the variant
+    pub arguments: Vec<Expression>,
+}
+
+impl VariantLiteral {
+    /// Creates a new enum value
+    pub fn new(enum_name: &str, variant: &str, arguments: Vec<Expression>) -> Self {
+        Self {
+            enum_name: String::from(enum_name),
+            variant: String::from(variant),
+            arguments,
+        }
+    }
+}
+
+impl HasType for VariantLiteral {
+    fn return_type(&self) -> Option<Type> {
+        Some(Type::UserDefined(self.enum_name.clone()))
+    }
 }
 
 /// Literal types
@@ -4,16 +4,18 @@
 use super::error::ParserError;
 use super::Output;
 use super::Parser;
-use super::Source;
-use super::{Token, Tokens};
+use super::Token;
+use crate::span::Span;
+use std::convert::TryFrom;
 use std::fmt;
 use std::ops::Deref;
-use std::str;
 
 /// Wrapper type to help get around recursive types
 pub type ProgramContainer = Box<Program>;
 
 /// The top level node in the AST.
+// `Empty` only ends the list once, so boxing the declarations would save no memory
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, PartialEq)]
 pub enum Program {
     /// Top level declaration, and compounded with the rest of the program
@@ -22,17 +24,57 @@ pub enum Program {
     Empty,
 }
 
+impl Program {
+    /// Appends `other` to the end of this program.
+    /// This is used for combining programs parsed from several input files.
+    pub fn append(self, other: Program) -> Program {
+        match self {
+            Program::Decl(decl, rest) => {
+                Program::Decl(decl, ProgramContainer::new(rest.append(other)))
+            }
+            Program::Empty => other,
+        }
+    }
+}
+
 /// Enum of all the types of top level declarations
 #[derive(Debug, PartialEq)]
 pub enum Decl {
     /// Variable Declaration
     VarDecl(VarDecl),
+    /// Constant declaration, with a value known at compile time
+    ConstDecl(VarDecl),
     /// Function Declaration
     FuncDecl(FuncDecl),
+    /// External function declaration
+    ExternDecl(ExternDecl),
+    /// Struct declaration
+    StructDecl(StructDecl),
+    /// Enum declaration
+    EnumDecl(EnumDecl),
+    /// Class declaration
+    ClassDecl(ClassDecl),
+    /// Placeholder for a declaration with syntax errors
+    Error(Span),
+}
+
+impl Decl {
+    /// The span of the declaration
+    pub fn span(&self) -> Span {
+        match self {
+            Decl::VarDecl(var_decl) | Decl::ConstDecl(var_decl) => var_decl.span,
+            Decl::FuncDecl(func_decl) => func_decl.span,
+            Decl::ExternDecl(extern_decl) => extern_decl.span,
+            Decl::StructDecl(struct_decl) => struct_decl.span,
+            Decl::EnumDecl(enum_decl) => enum_decl.span,
+            Decl::ClassDecl(class_decl) => class_decl.span,
+            Decl::Error(span) => *span,
+        }
+    }
 }
 
 /// Function declaration
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub struct FuncDecl {
     /// Name of the function
     pub name: String,
@@ -42,6 +84,8 @@ pub struct FuncDecl {
     pub return_type: Type,
     /// Body of the function
     pub body: Block,
+    /// Span from `fn` to the end of the body
+    pub span: Span,
 }
 
 impl FuncDecl {
@@ -52,17 +96,257 @@ impl FuncDecl {
             args,
             return_type,
             body,
+            span: Span::default(),
         }
     }
+
+    /// Sets the span of the declaration
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for FuncDecl {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.args == other.args
+            && self.return_type == other.return_type
+            && self.body == other.body
+    }
+}
+
+/// Declaration of a function defined outside of the program, e.g. in libc
+#[derive(Debug)]
+pub struct ExternDecl {
+    /// Name of the function
+    pub name: String,
+    /// Arguments of the function
+    pub args: Vec<ArgDecl>,
+    /// Return type of the function
+    pub return_type: Type,
+    /// Whether the function takes extra arguments after `args`
+    pub variadic: bool,
+    /// Span from `extern` to the semicolon
+    pub span: Span,
+}
+
+impl ExternDecl {
+    /// Creates a new external function declaration
+    pub fn new(name: &str, args: Vec<ArgDecl>, return_type: Type) -> Self {
+        Self {
+            name: name.to_string(),
+            args,
+            return_type,
+            variadic: false,
+            span: Span::default(),
+        }
+    }
+
+    /// Marks the function as variadic
+    pub fn variadic(mut self) -> Self {
+        self.variadic = true;
+        self
+    }
+
+    /// Sets the span of the declaration
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
 }
 
-/// Argument declaration
+impl PartialEq for ExternDecl {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.args == other.args
+            && self.return_type == other.return_type
+            && self.variadic == other.variadic
+    }
+}
+
+/// Struct declaration, e.g. `struct Point { x: int, y: int }`
+#[derive(Debug)]
+pub struct StructDecl {
+    /// Name of the struct
+    pub name: String,
+    /// Fields of the struct, in order
+    pub fields: Vec<ArgDecl>,
+    /// Span from `struct` to `}`
+    pub span: Span,
+}
+
+impl StructDecl {
+    /// Creates a new struct declaration
+    pub fn new(name: &str, fields: Vec<ArgDecl>) -> Self {
+        Self {
+            name: name.to_string(),
+            fields,
+            span: Span::default(),
+        }
+    }
+
+    /// Sets the span of the declaration
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for StructDecl {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.fields == other.fields
+    }
+}
+
+/// Enum declaration, e.g. `enum Shape { Circle(double), Rect(int, int), Empty }`
+#[derive(Debug)]
+pub struct EnumDecl {
+    /// Name of the enum
+    pub name: String,
+    /// Variants of the enum, in order
+    pub variants: Vec<VariantDecl>,
+    /// Span from `enum` to `}`
+    pub span: Span,
+}
+
+impl EnumDecl {
+    /// Creates a new enum declaration
+    pub fn new(name: &str, variants: Vec<VariantDecl>) -> Self {
+        Self {
+            name: name.to_string(),
+            variants,
+            span: Span::default(),
+        }
+    }
+
+    /// Sets the span of the declaration
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for EnumDecl {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.variants == other.variants
+    }
+}
+
+/// Variant of an enum, with the types of the values it holds
+#[derive(Debug)]
+pub struct VariantDecl {
+    /// Name of the variant
+    pub name: String,
+    /// Types of the values, in order
+    pub fields: Vec<Type>,
+    /// Span from the name to the end of the variant
+    pub span: Span,
+}
+
+impl VariantDecl {
+    /// Creates a new variant declaration
+    pub fn new(name: &str, fields: Vec<Type>) -> Self {
+        Self {
+            name: name.to_string(),
+            fields,
+            span: Span::default(),
+        }
+    }
+
+    /// Sets the span of the variant
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for VariantDecl {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.fields == other.fields
+    }
+}
+
+/// Class declaration, a struct with methods and a constructor, e.g.
+/// `class Counter { count: int, fn new() { Counter { count: 0 } } fn get(self) -> int { self.count } }`
+#[derive(Debug)]
+pub struct ClassDecl {
+    /// Name of the class
+    pub name: String,
+    /// Fields of the class, in order
+    pub fields: Vec<ArgDecl>,
+    /// `fn new(args) { body }`, called as `Class(args)` to create an object
+    pub constructor: Option<FuncDecl>,
+    /// Methods of the class, in order
+    pub methods: Vec<MethodDecl>,
+    /// Span from `class` to `}`
+    pub span: Span,
+}
+
+impl ClassDecl {
+    /// Creates a new class declaration
+    pub fn new(
+        name: &str,
+        fields: Vec<ArgDecl>,
+        constructor: Option<FuncDecl>,
+        methods: Vec<MethodDecl>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            fields,
+            constructor,
+            methods,
+            span: Span::default(),
+        }
+    }
+
+    /// Sets the span of the declaration
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    /// The name of the function implementing the method `method` of `class`.
+    /// The constructor is the method `new`.
+    pub fn mangle(class: &str, method: &str) -> String {
+        format!("{}.{}", class, method)
+    }
+}
+
+impl PartialEq for ClassDecl {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.fields == other.fields
+            && self.constructor == other.constructor
+            && self.methods == other.methods
+    }
+}
+
+/// Method of a class, e.g. `fn get(self) -> int { self.count }`
 #[derive(Debug, PartialEq)]
+pub struct MethodDecl {
+    /// Whether the method takes `mut self`, so it can change the fields of the object
+    pub mutable: bool,
+    /// The method, with the arguments following `self`
+    pub func_decl: FuncDecl,
+}
+
+impl MethodDecl {
+    /// Creates a new method declaration
+    pub fn new(func_decl: FuncDecl, mutable: bool) -> Self {
+        Self { mutable, func_decl }
+    }
+}
+
+/// Argument declaration, also used for the fields of structs
+#[derive(Debug)]
 pub struct ArgDecl {
     /// Name of the argument
     pub name: String,
     /// Type of the argument
     pub arg_type: Type,
+    /// Span of the name and the type
+    pub span: Span,
 }
 
 impl ArgDecl {
@@ -71,8 +355,21 @@ impl ArgDecl {
         Self {
             name: name.to_string(),
             arg_type,
+            span: Span::default(),
         }
     }
+
+    /// Sets the span of the declaration
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for ArgDecl {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.arg_type == other.arg_type
+    }
 }
 
 /// Enum of all the built in types.
@@ -91,8 +388,12 @@ pub enum Type {
     String,
     /// Void, representing nothing
     Void,
+    /// The type of expressions which never produce a value, like `return`
+    Never,
     /// User defined type. Uses a string to identify the name
     UserDefined(String),
+    /// Array of a fixed number of elements, e.g. `[int; 4]`
+    Array(Box<Type>, usize),
 }
 
 impl fmt::Display for Type {
@@ -104,36 +405,18 @@ impl fmt::Display for Type {
             Type::Boolean => write!(f, "boolean"),
             Type::String => write!(f, "string"),
             Type::Void => write!(f, "void"),
+            Type::Never => write!(f, "never"),
             Type::UserDefined(name) => write!(f, "{}", name),
+            Type::Array(element_type, length) => write!(f, "[{}; {}]", element_type, length),
         }
     }
 }
 
-impl<'source, Source> From<&mut Parser<'source, Source>> for Type
-where
-    Source: self::Source<'source> + Copy,
-{
-    fn from(parser: &mut Parser<'source, Source>) -> Type {
-        match parser.next_token() {
-            Token::IntType => Type::Int,
-            Token::FloatType => Type::Float,
-            Token::DoubleType => Type::Double,
-            Token::BooleanType => Type::Boolean,
-            Token::VoidType => Type::Void,
-            Token::Ident => Type::UserDefined(String::from(parser.slice)),
-            token => panic!(
-                "Expected {}, found {}",
-                Tokens::from(vec![
-                    Token::IntType,
-                    Token::FloatType,
-                    Token::DoubleType,
-                    Token::BooleanType,
-                    Token::VoidType,
-                    Token::Ident
-                ]),
-                token
-            ),
-        }
+impl<'source> TryFrom<&mut Parser<'source>> for Type {
+    type Error = ParserError;
+
+    fn try_from(parser: &mut Parser<'source>) -> Output<Type> {
+        parser.type_decl()
     }
 }
 
@@ -148,14 +431,55 @@ pub enum Statement {
     /// The reason for this to exist is to enable having an empty last expression
     /// like you would find in Rust
     Empty,
+    /// Placeholder for a statement with syntax errors
+    Error(Span),
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct Block(Vec<Statement>);
+impl Statement {
+    /// The span of the statement. Empty statements have no meaningful span
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::VarDecl(var_decl) => var_decl.span,
+            Statement::Expression(expr) => expr.span,
+            Statement::Empty => Span::default(),
+            Statement::Error(span) => *span,
+        }
+    }
+
+    /// Whether this is a placeholder for a statement with syntax errors
+    pub fn is_error(&self) -> bool {
+        matches!(self, Statement::Error(_))
+    }
+}
+
+/// A sequence of statements, `{ statements }`
+#[derive(Debug, Clone)]
+pub struct Block {
+    /// The statements, in order
+    pub statements: Vec<Statement>,
+    /// Span from `{` to `}`
+    pub span: Span,
+}
 
 impl Block {
+    /// Creates a new block
     pub fn new(statements: Vec<Statement>) -> Self {
-        Self(statements)
+        Self {
+            statements,
+            span: Span::default(),
+        }
+    }
+
+    /// Sets the span of the block
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for Block {
+    fn eq(&self, other: &Self) -> bool {
+        self.statements == other.statements
     }
 }
 
@@ -163,134 +487,380 @@ impl Deref for Block {
     type Target = Vec<Statement>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.statements
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// Declaration of a variable, `let name = expression`, or of a constant
+#[derive(Debug, Clone)]
 pub struct VarDecl {
+    /// Name of the variable
     pub identifier: String,
+    /// Whether the variable was declared with `let mut`
+    pub mutable: bool,
+    /// The type annotation if any, filled in by the type system otherwise
+    pub var_type: Option<Type>,
+    /// The initial value of the variable
     pub expression: Expression,
+    /// Span from `let` to the end of the expression
+    pub span: Span,
 }
 
 impl VarDecl {
+    /// Creates a new declaration of an immutable variable
     pub fn new(identifier: String, expression: Expression) -> Self {
         Self {
             identifier,
+            mutable: false,
+            var_type: None,
             expression,
+            span: Span::default(),
         }
     }
+
+    /// Makes the variable mutable
+    pub fn mutable(mut self) -> Self {
+        self.mutable = true;
+        self
+    }
+
+    /// Sets the type annotation of the declaration
+    pub fn with_type(mut self, var_type: Type) -> Self {
+        self.var_type = Some(var_type);
+        self
+    }
+
+    /// Sets the span of the declaration
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for VarDecl {
+    fn eq(&self, other: &Self) -> bool {
+        self.identifier == other.identifier
+            && self.mutable == other.mutable
+            && self.var_type == other.var_type
+            && self.expression == other.expression
+    }
 }
 
+/// Wrapper type to help get around recursive types
 pub type ExpressionContainer = Box<Expression>;
 
+/// An expression along with its location in the source.
+/// Spans are ignored when comparing expressions.
+#[derive(Debug, Clone)]
+pub struct Expression {
+    /// The kind of expression
+    pub kind: ExpressionKind,
+    /// Span of the whole expression
+    pub span: Span,
+}
+
+impl Expression {
+    /// Creates a new expression
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl From<ExpressionKind> for Expression {
+    fn from(kind: ExpressionKind) -> Self {
+        Self::new(kind, Span::default())
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+/// The different kinds of expressions
 #[derive(Debug, PartialEq, Clone)]
-pub enum Expression {
+pub enum ExpressionKind {
+    /// `left + right`
     Addition(ExpressionContainer, ExpressionContainer),
+    /// `left - right`
     Subtraction(ExpressionContainer, ExpressionContainer),
+    /// `left * right`
     Multiplication(ExpressionContainer, ExpressionContainer),
+    /// `left / right`
     Division(ExpressionContainer, ExpressionContainer),
+    /// `left % right`
     Modulus(ExpressionContainer, ExpressionContainer),
+    /// `left == right`
     Equality(ExpressionContainer, ExpressionContainer),
+    /// `left != right`
     NotEq(ExpressionContainer, ExpressionContainer),
+    /// `left < right`
     LessThan(ExpressionContainer, ExpressionContainer),
+    /// `left > right`
     GreaterThan(ExpressionContainer, ExpressionContainer),
+    /// `left <= right`
     LessEq(ExpressionContainer, ExpressionContainer),
+    /// `left >= right`
     GreaterEq(ExpressionContainer, ExpressionContainer),
+    /// `left && right`, where `right` is only evaluated when `left` is true
     And(ExpressionContainer, ExpressionContainer),
+    /// `left || right`, where `right` is only evaluated when `left` is false
     Or(ExpressionContainer, ExpressionContainer),
+    /// Logical negation, `!value`
     Not(ExpressionContainer),
+    /// Negation of a number, `-value`
+    Neg(ExpressionContainer),
+    /// Access of a field of a struct, `value.field`
+    Field(ExpressionContainer, String),
+    /// Call of a method of a class, `object.method(arguments)`
+    MethodCall(ExpressionContainer, FunctionCall),
+    /// Access of an element of an array, `value[index]`
+    Index(ExpressionContainer, ExpressionContainer),
+    /// `target = value`
+    Assignment(ExpressionContainer, ExpressionContainer),
+    /// `target op= value`, like `x += 1`. The target is only evaluated once
+    CompoundAssignment(AssignmentOp, ExpressionContainer, ExpressionContainer),
+    /// `if condition { body } else { ... }`
     If(IfExpressionContainer),
+    /// `match value { pattern => body, ... }`
+    Match(MatchExpressionContainer),
+    /// `while condition { body }`
+    While(ExpressionContainer, Block),
+    /// `loop { body }`, which can result in a value given to `break`
+    Loop(LoopExpression),
+    /// `break`, with an optional value for `loop`
+    Break(Option<ExpressionContainer>),
+    /// `continue`, skipping to the next iteration of the loop
+    Continue,
+    /// `return`, with an optional value
+    Return(Option<ExpressionContainer>),
+    /// A block, resulting in the value of its last statement
     Block(Block),
+    /// A value, like a literal or a variable
     Value(Value),
+    /// Placeholder for an expression with syntax errors
+    Error,
 }
 
-impl Expression {
+impl ExpressionKind {
     /// NUD stands for `Null-Denotation` which means the operators with no left
     /// context.
-    pub fn nud<'source, Source>(parser: &mut Parser<'source, Source>) -> Output<Expression>
-    where
-        Source: self::Source<'source> + Copy,
-    {
+    pub fn nud(parser: &mut Parser) -> Output<Expression> {
+        let start = parser.peek_span();
         if let Some(token_item) = parser.lexer.peek() {
             match token_item.token {
                 Token::Ident
-                | Token::Minus
+                | Token::SelfValue
                 | Token::Int
+                | Token::Float
                 | Token::String
                 | Token::True
-                | Token::False => Ok(Expression::Value(parser.value()?)),
+                | Token::False
+                | Token::LBracket => {
+                    let value = parser.value()?;
+                    Ok(Expression::new(
+                        ExpressionKind::Value(value),
+                        start.to(parser.span),
+                    ))
+                }
                 Token::Not => {
                     parser.next_token();
-                    Ok(Expression::Not(ExpressionContainer::new(
-                        parser.expression(0)?,
-                    )))
+                    let expr = parser.expression(0)?;
+                    let span = start.to(expr.span);
+                    Ok(Expression::new(
+                        ExpressionKind::Not(ExpressionContainer::new(expr)),
+                        span,
+                    ))
+                }
+                Token::Minus => {
+                    parser.next_token();
+                    // Negative number literals are kept as literals, so the smallest integer fits
+                    if let Token::Int | Token::Float = parser.peek_token() {
+                        let literal = parser.literal(Token::Minus)?;
+                        return Ok(Expression::new(
+                            ExpressionKind::Value(Value::Literal(literal)),
+                            start.to(parser.span),
+                        ));
+                    }
+                    let expr = parser.expression(Self::UNARY_BP)?;
+                    let span = start.to(expr.span);
+                    Ok(Expression::new(
+                        ExpressionKind::Neg(ExpressionContainer::new(expr)),
+                        span,
+                    ))
                 }
                 Token::LParen => {
                     parser.expect_token(Token::LParen)?;
-                    let expr = parser.expression(0);
+                    let expr = parser.allowing_struct_literals(|parser| parser.expression(0))?;
                     parser.expect_token(Token::RParen)?;
-                    expr
+                    Ok(Expression::new(expr.kind, start.to(parser.span)))
+                }
+                Token::LBrace => {
+                    let block = parser.allowing_struct_literals(|parser| parser.block())?;
+                    let span = block.span;
+                    Ok(Expression::new(ExpressionKind::Block(block), span))
+                }
+                Token::Match => {
+                    let match_expression = parser.match_expression()?;
+                    let span = match_expression.span;
+                    Ok(Expression::new(
+                        ExpressionKind::Match(MatchExpressionContainer::new(match_expression)),
+                        span,
+                    ))
+                }
+                Token::If => {
+                    let if_expression = parser.if_expression()?;
+                    let span = if_expression.span;
+                    Ok(Expression::new(
+                        ExpressionKind::If(IfExpressionContainer::new(if_expression)),
+                        span,
+                    ))
+                }
+                Token::While => {
+                    parser.next_token();
+                    let condition = parser.condition()?;
+                    let body = parser.block()?;
+                    Ok(Expression::new(
+                        ExpressionKind::While(ExpressionContainer::new(condition), body),
+                        start.to(parser.span),
+                    ))
+                }
+                Token::Loop => {
+                    parser.next_token();
+                    let body = parser.block()?;
+                    Ok(Expression::new(
+                        ExpressionKind::Loop(LoopExpression::new(body)),
+                        start.to(parser.span),
+                    ))
+                }
+                Token::Break => {
+                    parser.next_token();
+                    let value = match parser.peek_token() {
+                        Token::Semicolon | Token::RBrace | Token::End => None,
+                        _ => Some(ExpressionContainer::new(parser.expression(0)?)),
+                    };
+                    Ok(Expression::new(
+                        ExpressionKind::Break(value),
+                        start.to(parser.span),
+                    ))
+                }
+                Token::Continue => {
+                    parser.next_token();
+                    Ok(Expression::new(ExpressionKind::Continue, start))
+                }
+                Token::Return => {
+                    parser.next_token();
+                    let value = match parser.peek_token() {
+                        Token::Semicolon | Token::RBrace | Token::End => None,
+                        _ => Some(ExpressionContainer::new(parser.expression(0)?)),
+                    };
+                    Ok(Expression::new(
+                        ExpressionKind::Return(value),
+                        start.to(parser.span),
+                    ))
                 }
-                Token::LBrace => Ok(Expression::Block(parser.block()?)),
-                Token::If => Ok(Expression::If(IfExpressionContainer::new(
-                    parser.if_expression()?,
-                ))),
                 _ => Err(ParserError::error(
                     format!(
                         "Expected: number or boolean, found: {:?}",
                         token_item.slice()
                     ),
-                    parser.range_converter.to_line_and_pos(token_item.range()),
+                    Span::new(parser.file, token_item.range()),
                 )),
             }
         } else {
-            Err(ParserError::error("Expected: number or boolean", (0, 0)))
+            Err(ParserError::error(
+                "Expected: number or boolean",
+                Span::new(parser.file, parser.span.end..parser.span.end),
+            ))
         }
     }
 
     /// LED stands for `Left-Denotation` which means operators that has a left
     /// context.
     pub fn led(left: Expression, token: Token, right: Expression) -> Output<Expression> {
+        let span = left.span.to(right.span);
+        let (left, right) = (Box::new(left), Box::new(right));
+        let kind = match token {
+            Token::Plus => ExpressionKind::Addition(left, right),
+            Token::Minus => ExpressionKind::Subtraction(left, right),
+            Token::Star => ExpressionKind::Multiplication(left, right),
+            Token::Slash => ExpressionKind::Division(left, right),
+            Token::Percent => ExpressionKind::Modulus(left, right),
+            Token::Equality => ExpressionKind::Equality(left, right),
+            Token::NotEq => ExpressionKind::NotEq(left, right),
+            Token::LessThan => ExpressionKind::LessThan(left, right),
+            Token::GreaterThan => ExpressionKind::GreaterThan(left, right),
+            Token::LessEq => ExpressionKind::LessEq(left, right),
+            Token::GreaterEq => ExpressionKind::GreaterEq(left, right),
+            Token::And => ExpressionKind::And(left, right),
+            Token::Or => ExpressionKind::Or(left, right),
+            Token::Equal => ExpressionKind::Assignment(left, right),
+            Token::PlusEqual => ExpressionKind::CompoundAssignment(AssignmentOp::Add, left, right),
+            Token::MinusEqual => ExpressionKind::CompoundAssignment(AssignmentOp::Sub, left, right),
+            Token::StarEqual => ExpressionKind::CompoundAssignment(AssignmentOp::Mul, left, right),
+            Token::SlashEqual => ExpressionKind::CompoundAssignment(AssignmentOp::Div, left, right),
+            Token::PercentEqual => {
+                ExpressionKind::CompoundAssignment(AssignmentOp::Mod, left, right)
+            }
+            token => {
+                return Err(ParserError::expected(
+                    vec![
+                        Token::Plus,
+                        Token::Minus,
+                        Token::Star,
+                        Token::Slash,
+                        Token::Percent,
+                        Token::Equality,
+                        Token::NotEq,
+                        Token::LessThan,
+                        Token::GreaterThan,
+                        Token::LessEq,
+                        Token::GreaterEq,
+                        Token::And,
+                        Token::Or,
+                        Token::Equal,
+                        Token::PlusEqual,
+                        Token::MinusEqual,
+                        Token::StarEqual,
+                        Token::SlashEqual,
+                        Token::PercentEqual,
+                    ],
+                    token,
+                    span,
+                ))
+            }
+        };
+        Ok(Expression::new(kind, span))
+    }
+
+    /// Binding power of the prefix operator `-`, tighter than any binary operator
+    /// except field access and indexing
+    pub const UNARY_BP: usize = 70;
+
+    /// Binding power of the right operand of an operator. Assignments are right
+    /// associative, so `a = b = c` is `a = (b = c)`
+    pub fn right_bp(token: Token) -> usize {
         match token {
-            Token::Plus => Ok(Expression::Addition(Box::new(left), Box::new(right))),
-            Token::Minus => Ok(Expression::Subtraction(Box::new(left), Box::new(right))),
-            Token::Star => Ok(Expression::Multiplication(Box::new(left), Box::new(right))),
-            Token::Slash => Ok(Expression::Division(Box::new(left), Box::new(right))),
-            Token::Percent => Ok(Expression::Modulus(Box::new(left), Box::new(right))),
-            Token::Equality => Ok(Expression::Equality(Box::new(left), Box::new(right))),
-            Token::NotEq => Ok(Expression::NotEq(Box::new(left), Box::new(right))),
-            Token::LessThan => Ok(Expression::LessThan(Box::new(left), Box::new(right))),
-            Token::GreaterThan => Ok(Expression::GreaterThan(Box::new(left), Box::new(right))),
-            Token::LessEq => Ok(Expression::LessEq(Box::new(left), Box::new(right))),
-            Token::GreaterEq => Ok(Expression::GreaterEq(Box::new(left), Box::new(right))),
-            Token::And => Ok(Expression::And(Box::new(left), Box::new(right))),
-            Token::Or => Ok(Expression::Or(Box::new(left), Box::new(right))),
-            token => Err(ParserError::expected(
-                vec![
-                    Token::Plus,
-                    Token::Minus,
-                    Token::Star,
-                    Token::Slash,
-                    Token::Percent,
-                    Token::Equality,
-                    Token::NotEq,
-                    Token::LessThan,
-                    Token::GreaterThan,
-                    Token::LessEq,
-                    Token::GreaterEq,
-                    Token::And,
-                    Token::Or,
-                ],
-                token,
-                (0, 0),
-            )),
+            Token::Equal
+            | Token::PlusEqual
+            | Token::MinusEqual
+            | Token::StarEqual
+            | Token::SlashEqual
+            | Token::PercentEqual => Self::bp(token) - 1,
+            _ => Self::bp(token),
         }
     }
 
     /// Function to determine binding power of an operator
     pub fn bp(token: Token) -> usize {
         match token {
+            Token::Equal
+            | Token::PlusEqual
+            | Token::MinusEqual
+            | Token::StarEqual
+            | Token::SlashEqual
+            | Token::PercentEqual => 5,
             Token::Or => 10,
             Token::And => 20,
             Token::Equality
@@ -302,101 +872,420 @@ impl Expression {
             Token::Percent => 40,
             Token::Plus | Token::Minus => 50,
             Token::Star | Token::Slash => 60,
-            _ => usize::min_value(),
+            Token::Period | Token::LBracket => 80,
+            _ => usize::MIN,
         }
     }
 }
 
+/// The arithmetic operator of a compound assignment
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AssignmentOp {
+    /// `+=`
+    Add,
+    /// `-=`
+    Sub,
+    /// `*=`
+    Mul,
+    /// `/=`
+    Div,
+    /// `%=`
+    Mod,
+}
+
+/// Wrapper type to help get around recursive types
 pub type IfExpressionContainer = Box<IfExpression>;
 
-#[derive(Debug, PartialEq, Clone)]
+/// `if condition { body }` with an optional else expression
+#[derive(Debug, Clone)]
 pub struct IfExpression {
+    /// The condition deciding which branch is taken
     pub condition: Expression,
+    /// Evaluated when the condition is true
     pub body: Block,
+    /// Evaluated when the condition is false
     pub else_expression: ElseExpression,
+    /// The type of the value, filled in by the type system
+    pub result_type: Option<Type>,
+    /// Span from `if` to the end of the last else block
+    pub span: Span,
 }
 
 impl IfExpression {
+    /// Creates a new if expression
     pub fn new(condition: Expression, body: Block, else_expression: ElseExpression) -> Self {
         Self {
             condition,
             body,
             else_expression,
+            result_type: None,
+            span: Span::default(),
         }
     }
+
+    /// Sets the span of the expression
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
 }
 
+impl PartialEq for IfExpression {
+    fn eq(&self, other: &Self) -> bool {
+        self.condition == other.condition
+            && self.body == other.body
+            && self.else_expression == other.else_expression
+    }
+}
+
+/// `loop { body }`, repeating the body until it breaks
+#[derive(Debug, Clone)]
+pub struct LoopExpression {
+    /// The body of the loop
+    pub body: Block,
+    /// The type of the values given to `break`, filled in by the type system
+    pub result_type: Option<Type>,
+}
+
+impl LoopExpression {
+    /// Creates a new loop
+    pub fn new(body: Block) -> Self {
+        Self {
+            body,
+            result_type: None,
+        }
+    }
+}
+
+impl PartialEq for LoopExpression {
+    fn eq(&self, other: &Self) -> bool {
+        self.body == other.body
+    }
+}
+
+/// Wrapper type to help get around recursive types
+pub type MatchExpressionContainer = Box<MatchExpression>;
+
+/// `match value { pattern => body, ... }`
+#[derive(Debug, Clone)]
+pub struct MatchExpression {
+    /// The value being matched
+    pub value: Expression,
+    /// The arms, tried in order
+    pub arms: Vec<MatchArm>,
+    /// The type of the value, filled in by the type system
+    pub result_type: Option<Type>,
+    /// Span from `match` to `}`
+    pub span: Span,
+}
+
+impl MatchExpression {
+    /// Creates a new match expression
+    pub fn new(value: Expression, arms: Vec<MatchArm>) -> Self {
+        Self {
+            value,
+            arms,
+            result_type: None,
+            span: Span::default(),
+        }
+    }
+
+    /// Sets the span of the expression
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for MatchExpression {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.arms == other.arms
+    }
+}
+
+/// An arm of a match expression, `pattern => body`
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    /// The pattern the value is matched against
+    pub pattern: Pattern,
+    /// Evaluated when the pattern matches
+    pub body: Expression,
+    /// Span from the pattern to the end of the body
+    pub span: Span,
+}
+
+impl MatchArm {
+    /// Creates a new match arm
+    pub fn new(pattern: Pattern, body: Expression) -> Self {
+        Self {
+            pattern,
+            body,
+            span: Span::default(),
+        }
+    }
+
+    /// Sets the span of the arm
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for MatchArm {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern && self.body == other.body
+    }
+}
+
+/// A pattern of a match arm
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    /// The kind of pattern
+    pub kind: PatternKind,
+    /// Span of the whole pattern
+    pub span: Span,
+}
+
+impl Pattern {
+    /// Creates a new pattern
+    pub fn new(kind: PatternKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl From<PatternKind> for Pattern {
+    fn from(kind: PatternKind) -> Self {
+        Self::new(kind, Span::default())
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+/// The different kinds of patterns
+#[derive(Debug, PartialEq, Clone)]
+pub enum PatternKind {
+    /// `_`, matching any value
+    Wildcard,
+    /// A name, matching any value and binding it to the name in the arm
+    Binding(String),
+    /// A literal, matching only the same value
+    Literal(Literal),
+    /// `Enum::Variant(patterns)`, matching the variant when the patterns match its values
+    Variant(String, String, Vec<Pattern>),
+}
+
+/// What follows the body of an if expression
 #[derive(Debug, PartialEq, Clone)]
 pub enum ElseExpression {
+    /// `else { body }`
     Block(Block),
+    /// `else if ...`
     IfExpression(IfExpressionContainer),
+    /// No else expression
     None,
 }
 
+/// The values an expression can consist of
 #[derive(Debug, PartialEq, Clone)]
 pub enum Value {
+    /// A literal, e.g. `5` or `"text"`
     Literal(Literal),
+    /// A use of a variable or constant
     Variable(String),
+    /// A call of a function
     FunctionCall(FunctionCall),
+    /// A value of a struct
+    Struct(StructLiteral),
+    /// Array literal, e.g. `[1, 2, 3]`
+    Array(Vec<Expression>),
+    /// Enum value, e.g. `Shape::Rect(2, 3)`
+    Variant(VariantLiteral),
 }
 
+/// A literal value written in the source
 #[derive(Debug, PartialEq, Clone)]
 pub enum Literal {
+    /// Number literal, e.g. `5` or `1.5`
     Number(Number),
+    /// `true` or `false`
     Boolean(bool),
+    /// String literal, e.g. `"text"`
     String(String),
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// A call of a function, `name(arguments)`
+#[derive(Debug, Clone)]
 pub struct FunctionCall {
+    /// Name of the function
     pub name: String,
+    /// The arguments, in order
     pub arguments: Vec<Expression>,
+    /// Span from the name to `)`
+    pub span: Span,
 }
 
 impl FunctionCall {
+    /// Creates a new function call
     pub fn new(name: &str, arguments: Vec<Expression>) -> Self {
         Self {
             name: String::from(name),
             arguments,
+            span: Span::default(),
+        }
+    }
+
+    /// Sets the span of the call
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for FunctionCall {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.arguments == other.arguments
+    }
+}
+
+/// A value of a struct, e.g. `Point { x: 1, y: 2 }`
+#[derive(Debug, Clone)]
+pub struct StructLiteral {
+    /// Name of the struct
+    pub name: String,
+    /// The values of the fields, in the order they are written
+    pub fields: Vec<FieldValue>,
+    /// Span from the name to `}`
+    pub span: Span,
+}
+
+impl StructLiteral {
+    /// Creates a new struct literal
+    pub fn new(name: &str, fields: Vec<FieldValue>) -> Self {
+        Self {
+            name: String::from(name),
+            fields,
+            span: Span::default(),
+        }
+    }
+
+    /// Sets the span of the literal
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for StructLiteral {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.fields == other.fields
+    }
+}
+
+/// The value of a field in a struct literal, `field: value`
+#[derive(Debug, Clone)]
+pub struct FieldValue {
+    /// Name of the field
+    pub name: String,
+    /// The value of the field
+    pub value: Expression,
+    /// Span from the name to the end of the value
+    pub span: Span,
+}
+
+impl FieldValue {
+    /// Creates a new field value
+    pub fn new(name: &str, value: Expression) -> Self {
+        Self {
+            name: String::from(name),
+            value,
+            span: Span::default(),
+        }
+    }
+
+    /// Sets the span of the field
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for FieldValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.value == other.value
+    }
+}
+
+/// A value of an enum, e.g. `Shape::Rect(2, 3)`
+#[derive(Debug, Clone)]
+pub struct VariantLiteral {
+    /// Name of the enum
+    pub enum_name: String,
+    /// Name of the variant
+    pub variant: String,
+    /// The values held by the variant, in order
+    pub arguments: Vec<Expression>,
+    /// Span from the enum name to the end of the variant
+    pub span: Span,
+}
+
+impl VariantLiteral {
+    /// Creates a new variant literal
+    pub fn new(enum_name: &str, variant: &str, arguments: Vec<Expression>) -> Self {
+        Self {
+            enum_name: String::from(enum_name),
+            variant: String::from(variant),
+            arguments,
+            span: Span::default(),
         }
     }
+
+    /// Sets the span of the literal
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
 }
 
+impl PartialEq for VariantLiteral {
+    fn eq(&self, other: &Self) -> bool {
+        self.enum_name == other.enum_name
+            && self.variant == other.variant
+            && self.arguments == other.arguments
+    }
+}
+
+/// A number literal
 #[derive(Debug, PartialEq, Clone)]
 pub enum Number {
+    /// `int`
     Int(isize),
+    /// `float`, written with the `f` suffix
     Float(f32),
+    /// `double`
     Double(f64),
 }
 
-impl<'source, Source> From<&mut Parser<'source, Source>> for Number
-where
-    Source: self::Source<'source> + Copy,
-{
-    fn from(parser: &mut Parser<'source, Source>) -> Self {
+impl<'source> TryFrom<&mut Parser<'source>> for Number {
+    type Error = ParserError;
+
+    fn try_from(parser: &mut Parser<'source>) -> Output<Self> {
         let sign = if Token::Minus == parser.peek_token() {
             parser.next_token();
             -1
         } else {
             1
         };
-        if let Some(token_item) = parser.lexer.peek() {
-            match token_item.token {
-                Token::Int => Number::Int(
-                    sign * str::from_utf8(token_item.slice().as_bytes())
-                        .expect("Could not parse byte array")
-                        .parse::<isize>()
-                        .expect("Parsing of integer failed"),
-                ),
-                _ => panic!("Expected: number or boolean in parsing of Literal"),
-            }
-        } else {
-            panic!("Expected: number or boolean in parsing of Literal, found EOF");
-        }
+        let token = parser.next_token();
+        parser.number(token, sign)
     }
 }
 
 impl Number {
+    /// The number as an integer, truncating floating point numbers
     pub fn to_int(&self) -> isize {
         match self {
             Self::Int(i) => *i,
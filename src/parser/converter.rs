@@ -1,7 +1,8 @@
+//! Conversion of the AST into the MIR.
+
 use super::super::mir;
 use super::ast;
-
-type Output<Out = ()> = Result<Out, ()>;
+use super::Output;
 
 trait InlineAppend {
     fn inline_append(self, other: &mut Self) -> Self;
@@ -14,6 +15,7 @@ impl<T> InlineAppend for Vec<T> {
     }
 }
 
+/// Converts the program into the MIR
 pub fn convert_ast(program: ast::Program) -> Output<mir::Program> {
     Ok(mir::Program {
         declarations: convert_program(program)?,
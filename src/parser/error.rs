@@ -1,60 +1,121 @@
+//! Syntax errors found while parsing.
+
 #[cfg(feature = "parser-debug")]
 use backtrace::Backtrace;
 
 use super::{Token, Tokens};
+use crate::diagnostic::{Diagnostic, Label};
+use crate::span::Span;
 use std::fmt;
-//use std::ops::Range;
 
+/// A syntax error, reported as a diagnostic
 pub struct ParserError {
     message: String,
-    position: (usize, usize),
+    span: Span,
     code: ParserErrorCode,
+    labels: Vec<Label>,
+    notes: Vec<String>,
+    // Boxed to keep the results of every function returning the error small
     #[cfg(feature = "parser-debug")]
-    backtrace: Backtrace,
+    backtrace: Box<Backtrace>,
 }
 
 impl ParserError {
-    pub fn new<T: Into<String>>(
-        message: T,
-        code: ParserErrorCode,
-        position: (usize, usize),
-    ) -> ParserError {
+    /// Creates an error with the given message, code and span
+    pub fn new<T: Into<String>>(message: T, code: ParserErrorCode, span: Span) -> ParserError {
         ParserError {
             message: message.into(),
             code,
-            position,
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
             #[cfg(feature = "parser-debug")]
-            backtrace: Backtrace::new_unresolved(),
+            backtrace: Box::new(Backtrace::new_unresolved()),
         }
     }
 
-    pub fn error<T: Into<String>>(message: T, position: (usize, usize)) -> ParserError {
-        ParserError::new(message, ParserErrorCode::E9999, position)
+    /// An error without a code of its own
+    pub fn error<T: Into<String>>(message: T, span: Span) -> ParserError {
+        ParserError::new(message, ParserErrorCode::E9999, span)
     }
 
-    pub fn expected(expected: Vec<Token>, found: Token, position: (usize, usize)) -> ParserError {
+    /// Another token was expected
+    pub fn expected(expected: Vec<Token>, found: Token, span: Span) -> ParserError {
         ParserError::new(
             format!("Expected {}, found: {}", Tokens::from(expected), found),
             ParserErrorCode::E0002,
-            position,
+            span,
+        )
+    }
+
+    /// An integer literal does not fit in an `int`
+    pub fn integer_out_of_range(literal: &str, span: Span) -> ParserError {
+        ParserError::new(
+            format!("Integer literal out of range: {}", literal),
+            ParserErrorCode::E0003,
+            span,
+        )
+        .with_note(format!(
+            "integers must be between {} and {}",
+            isize::MIN,
+            isize::MAX
+        ))
+    }
+
+    /// An array length does not fit in the `u32` LLVM array types are limited to
+    pub fn array_length_out_of_range(literal: &str, span: Span) -> ParserError {
+        ParserError::new(
+            format!("Array length out of range: {}", literal),
+            ParserErrorCode::E0004,
+            span,
         )
+        .with_note(format!("array lengths can be at most {}", u32::MAX))
     }
 
+    /// Adds a secondary label pointing at related code
+    pub fn with_label<T: Into<String>>(mut self, span: Span, message: T) -> ParserError {
+        self.labels.push(Label::secondary(span, message));
+        self
+    }
+
+    /// Adds a note explaining the error
+    pub fn with_note<T: Into<String>>(mut self, note: T) -> ParserError {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// The message describing the error
     pub fn message(&self) -> &str {
         &self.message
     }
 
+    /// The code identifying the kind of error
     pub fn code(&self) -> &ParserErrorCode {
         &self.code
     }
+
+    /// The span of the code causing the error
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Self {
+        Diagnostic::error(error.message.as_str())
+            .with_code(format!("{:?}", error.code))
+            .with_label(Label::primary(error.span, ""))
+            .with_labels(error.labels.iter().cloned())
+            .with_notes(error.notes.iter().cloned())
+    }
 }
 
 impl fmt::Debug for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Parser error {:?}: {} {:?}",
-            self.code, self.message, self.position
+            "Parser error {:?}: {} ({})",
+            self.code, self.message, self.span
         )?;
         #[cfg(feature = "parser-debug")]
         {
@@ -66,12 +127,17 @@ impl fmt::Debug for ParserError {
     }
 }
 
+/// Codes identifying the kinds of syntax errors, so they can be looked up
 #[derive(Debug)]
 pub enum ParserErrorCode {
     /// Unused
     E0001,
     /// Expected another symbol
     E0002,
+    /// Integer literal out of range
+    E0003,
+    /// Array length out of range
+    E0004,
     /// Unspecified error (i.e. lazy developer)
     E9999,
 }
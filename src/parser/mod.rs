@@ -12,15 +12,38 @@
 //! IMPORT        := Use IDENTIFIER Semicolon ;
 //!
 //! DECL          := FUNC_DECL
+//!               |  EXTERN_DECL
+//!               |  STRUCT_DECL
+//!               |  ENUM_DECL
 //!               |  CLASS_DECL
-//!               |  VAR_DECL
+//!               |  VAR_DECL Semicolon
+//!               |  CONST_DECL Semicolon
 //!               ;
 //!
 //! FUNC_DECL     := Fn Ident LParen ARG_DECL RParen RETURN_DECL BLOCK ;
 //!
-//! CLASS_DECL    := MODIFIER Class CLASS_BODY ;
+//! EXTERN_DECL   := Extern Fn Ident LParen ARG_DECL [ Ellipsis ] RParen RETURN_DECL Semicolon ;
+//!
+//! STRUCT_DECL   := Struct Ident LBrace ARG_DECL RBrace ;
+//!
+//! ENUM_DECL     := Enum Ident LBrace VARIANT_LIST RBrace ;
+//!
+//! VARIANT_LIST  := Ident [ LParen TYPE_LIST RParen ] [ Comma [ VARIANT_LIST ] ]
+//!               |  λ
+//!               ;
+//!
+//! TYPE_LIST     := IDENTIFIER [ Comma [ TYPE_LIST ] ]
+//!               |  λ
+//!               ;
+//!
+//! CLASS_DECL    := Class Ident LBrace ARG_DECL { METHOD_DECL } RBrace ;
+//!
+//! (* Only the constructor, which must be named `new`, has no `self` *)
+//! METHOD_DECL   := Fn Ident LParen [ [ Mut ] SelfValue [ Comma ] ] ARG_DECL RParen RETURN_DECL BLOCK ;
+//!
+//! VAR_DECL      := Let [ Mut ] Ident [ TYPE_DECL ] Equal EXPRESSION ;
 //!
-//! VAR_DECL      := Let Ident [ TYPE_DECL ] Equal EXPRESSION ;
+//! CONST_DECL    := Const Ident [ TYPE_DECL ] Equal EXPRESSION ;
 //!
 //! ARG_DECL      := ARG [ Comma ARG_DECL ]
 //!               |  λ
@@ -34,6 +57,10 @@
 //!
 //! TYPE_DECL     := Colon IDENTIFIER ;
 //!
+//! IDENTIFIER    := IntType | FloatType | DoubleType | BooleanType | StringType | VoidType | Ident
+//!               |  LBracket IDENTIFIER Semicolon Int RBracket
+//!               ;
+//!
 //! BLOCK         := LBrace BLOCK_CONTENT RBrace ;
 //!
 //! BLOCK_CONTENT := STATEMENT [ Semicolon BLOCK_CONTENT ]
@@ -59,22 +86,64 @@
 //!               |  EXPRESSION GreaterEq EXPRESSION
 //!               |  EXPRESSION And EXPRESSION
 //!               |  EXPRESSION Or EXPRESSION
+//!               |  EXPRESSION ASSIGN_OP EXPRESSION
+//!               |  EXPRESSION Period Ident
+//!               |  EXPRESSION Period Ident LParen ARG_LIST RParen
+//!               |  EXPRESSION LBracket EXPRESSION RBracket
 //!               |  Not EXPRESSION
+//!               |  Minus EXPRESSION
 //!               |  LParen EXPRESSION RParen
 //!               |  IF_EXPRESSION
+//!               |  MATCH_EXPRESSION
+//!               |  While CONDITION BLOCK
+//!               |  Loop BLOCK
+//!               |  Break [ EXPRESSION ]
+//!               |  Continue
+//!               |  Return [ EXPRESSION ]
 //!               |  BLOCK
 //!               |  VALUE
 //!               ;
 //!
-//! IF_EXPRESSION := If EXPRESSION BLOCK [ Else ( BLOCK | IF_EXPRESSION ) ]
+//! ASSIGN_OP     := Equal | PlusEqual | MinusEqual | StarEqual | SlashEqual | PercentEqual ;
+//!
+//! IF_EXPRESSION := If CONDITION BLOCK [ Else ( BLOCK | IF_EXPRESSION ) ]
 //!               ;
 //!
+//! MATCH_EXPRESSION := Match CONDITION LBrace ARM_LIST RBrace ;
+//!
+//! (* The comma can be left out after an arm with a block *)
+//! ARM_LIST      := PATTERN FatArrow EXPRESSION [ Comma [ ARM_LIST ] ]
+//!               |  λ
+//!               ;
+//!
+//! PATTERN       := Ident
+//!               |  Ident DoubleColon Ident [ LParen PATTERN_LIST RParen ]
+//!               |  LITERAL
+//!               ;
+//!
+//! PATTERN_LIST  := PATTERN [ Comma [ PATTERN_LIST ] ]
+//!               |  λ
+//!               ;
+//!
+//! (* An expression without struct literals outside of parentheses and blocks,
+//!    as `x {` starts the body *)
+//! CONDITION     := EXPRESSION ;
+//!
 //! VALUE         := LITERAL
 //!               |  Ident LParen ARG_LIST RParen
+//!               |  Ident LBrace FIELD_LIST RBrace
+//!               |  Ident DoubleColon Ident [ LParen ARG_LIST RParen ]
 //!               |  Ident
+//!               |  SelfValue
+//!               |  LBracket ARG_LIST RBracket
+//!               ;
+//!
+//! FIELD_LIST    := Ident Colon EXPRESSION [ Comma [ FIELD_LIST ] ]
+//!               |  λ
 //!               ;
 //!
 //! LITERAL       := Number
+//!               |  Float
 //!               |  True
 //!               |  False
 //!               ;
@@ -84,11 +153,10 @@
 //!               ;
 //! ```
 
-use super::lexer::{wrapper::LexerWrapper, RangeConverter, Token, Tokens};
-use logos::source::Source;
+use super::lexer::{wrapper::LexerWrapper, Token, Tokens};
+use crate::span::{FileId, Span};
 use std::iter::Peekable;
 //use std::ops::Range;
-use std::str;
 
 pub mod ast;
 pub mod converter;
@@ -97,121 +165,322 @@ pub mod error;
 #[cfg(test)]
 mod test;
 
+/// The tokens starting a declaration
+const DECL_START: &[Token] = &[
+    Token::Fn,
+    Token::Extern,
+    Token::Struct,
+    Token::Enum,
+    Token::Class,
+    Token::Let,
+    Token::Const,
+];
+
 /// Short hand type of result that returns a `ParserError`
 pub type Output<Out = ()> = Result<Out, error::ParserError>;
 
 /// Parser that contains the current state of the parsing
-pub struct Parser<'source, Source>
-where
-    Source: logos::source::Source<'source> + Copy,
-{
-    lexer: Peekable<LexerWrapper<Source>>,
-    range: (usize, usize),
+pub struct Parser<'source> {
+    lexer: Peekable<LexerWrapper<'source>>,
+    file: FileId,
+    span: Span,
     slice: &'source str,
-    range_converter: RangeConverter,
+    errors: Vec<error::ParserError>,
+    /// False while parsing conditions, where `{` starts the body rather than a struct literal
+    struct_literals: bool,
 }
 
-impl<'source, Source> Parser<'source, Source>
-where
-    Source: self::Source<'source> + Copy,
-{
+impl<'source> Parser<'source> {
     /// Creates a new Parser
     /// `lexer` is the wrapped lexer
-    /// `range_converter` is the converter over the same input as the lexer
-    pub fn new(
-        lexer: LexerWrapper<Source>,
-        range_converter: RangeConverter,
-    ) -> Parser<'source, Source> {
+    pub fn new(lexer: LexerWrapper<'source>) -> Parser<'source> {
+        Self::new_with_file(lexer, FileId::default())
+    }
+
+    /// Creates a new Parser, where all spans refer to `file`
+    /// `lexer` is the wrapped lexer over the content of `file`
+    pub fn new_with_file(lexer: LexerWrapper<'source>, file: FileId) -> Parser<'source> {
         Parser {
             lexer: lexer.peekable(),
-            range: (0, 0),
+            file,
+            span: Span::new(file, 0..0),
             slice: "",
-            range_converter,
-        }
-    }
-
-    /// Parses the contained lexed file into a [`ast::Program`] or an error [`error::ParserError`]
-    pub fn parse(&mut self) -> Output<ast::Program> {
-        self.program()
-        //if let Some(token_item) = self.lexer.peek() {
-        //    match token_item.token {
-        //        Token::Let
-        //        | Token::LBrace
-        //        | Token::LParen
-        //        | Token::Minus
-        //        | Token::Int
-        //        | Token::True
-        //        | Token::False => self.statement(),
-        //        _ => Err(error::ParserError::error(
-        //            "Unsupported token.",
-        //            self.range.clone(),
-        //        )),
-        //    }
-        //} else {
-        //    Err(error::ParserError::error(
-        //        "Noop. Should be fixed",
-        //        self.range.clone(),
-        //    ))
-        //}
-    }
-
-    fn program(&mut self) -> Output<ast::Program> {
-        match self.peek_token() {
-            Token::Fn | Token::Let => Ok(ast::Program::Decl(
-                self.decl()?,
-                ast::ProgramContainer::new(self.program()?),
-            )),
-            Token::End => Ok(ast::Program::Empty),
-            token => Err(error::ParserError::expected(
-                vec![Token::Fn, Token::Let, Token::End],
-                token,
-                self.range,
-            )),
+            errors: Vec::new(),
+            struct_literals: true,
+        }
+    }
+
+    /// Parses the contained lexed file into a [`ast::Program`].
+    /// If the file contains syntax errors, all of them are returned instead.
+    pub fn parse(&mut self) -> Result<ast::Program, Vec<error::ParserError>> {
+        let (program, errors) = self.parse_recovering();
+        if errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(errors)
         }
     }
 
+    /// Parses the contained lexed file, recovering from syntax errors at statement
+    /// and declaration boundaries. The returned program is a best effort, where the
+    /// erroneous parts are replaced by error nodes, along with all the errors found.
+    pub fn parse_recovering(&mut self) -> (ast::Program, Vec<error::ParserError>) {
+        let program = self.program();
+        (program, std::mem::take(&mut self.errors))
+    }
+
+    fn program(&mut self) -> ast::Program {
+        let mut decls = Vec::new();
+        loop {
+            match self.peek_token() {
+                Token::End => break,
+                token if DECL_START.contains(&token) => match self.decl() {
+                    Ok(decl) => decls.push(decl),
+                    Err(error) => {
+                        let start = error.span();
+                        self.errors.push(error);
+                        self.synchronize(DECL_START);
+                        decls.push(ast::Decl::Error(start.to(self.span)));
+                    }
+                },
+                token => {
+                    let start = self.peek_span();
+                    let mut expected = DECL_START.to_vec();
+                    expected.push(Token::End);
+                    self.errors
+                        .push(error::ParserError::expected(expected, token, start));
+                    self.next_token();
+                    self.synchronize(DECL_START);
+                    decls.push(ast::Decl::Error(start.to(self.span)));
+                }
+            }
+        }
+
+        decls
+            .into_iter()
+            .rev()
+            .fold(ast::Program::Empty, |program, decl| {
+                ast::Program::Decl(decl, ast::ProgramContainer::new(program))
+            })
+    }
+
     fn decl(&mut self) -> Output<ast::Decl> {
         match self.peek_token() {
             Token::Fn => Ok(ast::Decl::FuncDecl(self.func_decl()?)),
+            Token::Extern => Ok(ast::Decl::ExternDecl(self.extern_decl()?)),
+            Token::Struct => Ok(ast::Decl::StructDecl(self.struct_decl()?)),
+            Token::Enum => Ok(ast::Decl::EnumDecl(self.enum_decl()?)),
+            Token::Class => Ok(ast::Decl::ClassDecl(self.class_decl()?)),
             Token::Let => {
                 let res = ast::Decl::VarDecl(self.var_decl()?);
                 self.expect_token(Token::Semicolon)?;
                 Ok(res)
             }
+            Token::Const => {
+                let res = ast::Decl::ConstDecl(self.const_decl()?);
+                self.expect_token(Token::Semicolon)?;
+                Ok(res)
+            }
             token => Err(error::ParserError::expected(
-                vec![Token::Fn, Token::Let],
+                DECL_START.to_vec(),
                 token,
-                self.range,
+                self.peek_span(),
             )),
         }
     }
 
     fn func_decl(&mut self) -> Output<ast::FuncDecl> {
+        let start = self.peek_span();
         self.expect_token(Token::Fn)?;
         self.expect_token(Token::Ident)?;
         let name = self.slice;
         self.expect_token(Token::LParen)?;
         let args = self.arg_decls()?;
         self.expect_token(Token::RParen)?;
-        let return_type = if let Token::Arrow = self.peek_token() {
+        let return_type = self.return_decl()?;
+        let body = self.block()?;
+
+        Ok(ast::FuncDecl::new(name, args, return_type, body).with_span(start.to(self.span)))
+    }
+
+    fn extern_decl(&mut self) -> Output<ast::ExternDecl> {
+        let start = self.peek_span();
+        self.expect_token(Token::Extern)?;
+        self.expect_token(Token::Fn)?;
+        self.expect_token(Token::Ident)?;
+        let name = self.slice;
+        self.expect_token(Token::LParen)?;
+        let args = self.arg_decls()?;
+        let variadic = if let Token::Ellipsis = self.peek_token() {
             self.next_token();
-            self.type_decl()?
+            true
         } else {
-            ast::Type::Void
+            false
         };
+        self.expect_token(Token::RParen)?;
+        let return_type = self.return_decl()?;
+        self.expect_token(Token::Semicolon)?;
+
+        let extern_decl = ast::ExternDecl::new(name, args, return_type);
+        let extern_decl = if variadic {
+            extern_decl.variadic()
+        } else {
+            extern_decl
+        };
+        Ok(extern_decl.with_span(start.to(self.span)))
+    }
+
+    fn struct_decl(&mut self) -> Output<ast::StructDecl> {
+        let start = self.peek_span();
+        self.expect_token(Token::Struct)?;
+        self.expect_token(Token::Ident)?;
+        let name = self.slice;
+        self.expect_token(Token::LBrace)?;
+        let fields = self.arg_decls()?;
+        self.expect_token(Token::RBrace)?;
+
+        Ok(ast::StructDecl::new(name, fields).with_span(start.to(self.span)))
+    }
+
+    fn enum_decl(&mut self) -> Output<ast::EnumDecl> {
+        let start = self.peek_span();
+        self.expect_token(Token::Enum)?;
+        self.expect_token(Token::Ident)?;
+        let name = self.slice;
+        self.expect_token(Token::LBrace)?;
+        let mut variants = Vec::new();
+        while let Token::Ident = self.peek_token() {
+            self.next_token();
+            let variant_start = self.span;
+            let variant = self.slice;
+            let mut fields = Vec::new();
+            if let Token::LParen = self.peek_token() {
+                self.next_token();
+                while self.peek_token() != Token::RParen {
+                    fields.push(self.type_decl()?);
+                    if let Token::Comma = self.peek_token() {
+                        self.next_token();
+                    } else {
+                        break;
+                    }
+                }
+                self.expect_token(Token::RParen)?;
+            }
+            variants.push(
+                ast::VariantDecl::new(variant, fields).with_span(variant_start.to(self.span)),
+            );
+            if let Token::Comma = self.peek_token() {
+                self.next_token();
+            } else {
+                break;
+            }
+        }
+        self.expect_token(Token::RBrace)?;
+
+        Ok(ast::EnumDecl::new(name, variants).with_span(start.to(self.span)))
+    }
+
+    fn class_decl(&mut self) -> Output<ast::ClassDecl> {
+        let start = self.peek_span();
+        self.expect_token(Token::Class)?;
+        self.expect_token(Token::Ident)?;
+        let name = self.slice;
+        self.expect_token(Token::LBrace)?;
+        let fields = self.arg_decls()?;
+        let mut constructor: Option<ast::FuncDecl> = None;
+        let mut methods = Vec::new();
+        while let Token::Fn = self.peek_token() {
+            match self.method_decl(name)? {
+                (Some(mutable), func_decl) => {
+                    methods.push(ast::MethodDecl::new(func_decl, mutable))
+                }
+                (None, func_decl) => {
+                    if let Some(existing) = &constructor {
+                        return Err(error::ParserError::error(
+                            "A class can only have one constructor",
+                            func_decl.span,
+                        )
+                        .with_label(existing.span, "first defined here"));
+                    }
+                    constructor = Some(func_decl);
+                }
+            }
+        }
+        self.expect_token(Token::RBrace)?;
+
+        Ok(ast::ClassDecl::new(name, fields, constructor, methods).with_span(start.to(self.span)))
+    }
+
+    /// Parses a method, or the constructor `new` when there is no `self` argument.
+    /// For methods, also returns whether `self` is mutable.
+    fn method_decl(&mut self, class: &str) -> Output<(Option<bool>, ast::FuncDecl)> {
+        let start = self.peek_span();
+        self.expect_token(Token::Fn)?;
+        self.expect_token(Token::Ident)?;
+        let name = self.slice;
+        let name_span = self.span;
+        self.expect_token(Token::LParen)?;
+        let receiver = match self.peek_token() {
+            Token::Mut => {
+                self.next_token();
+                self.expect_token(Token::SelfValue)?;
+                Some(true)
+            }
+            Token::SelfValue => {
+                self.next_token();
+                Some(false)
+            }
+            _ => None,
+        };
+        if receiver.is_some() && self.peek_token() == Token::Comma {
+            self.next_token();
+        }
+        let args = self.arg_decls()?;
+        self.expect_token(Token::RParen)?;
+        let mut return_type = self.return_decl()?;
+        if receiver.is_some() && name == "new" {
+            return Err(error::ParserError::error(
+                "The constructor can not take `self`",
+                name_span,
+            ));
+        }
+        if receiver.is_none() {
+            if name != "new" {
+                return Err(error::ParserError::error(
+                    "Methods must take `self` as their first argument",
+                    name_span,
+                )
+                .with_note("only the constructor, `new`, has no `self`"));
+            }
+            // The constructor results in the new object
+            if return_type == ast::Type::Void {
+                return_type = ast::Type::UserDefined(String::from(class));
+            }
+        }
         let body = self.block()?;
 
-        Ok(ast::FuncDecl::new(name, args, return_type, body))
+        let func_decl = ast::FuncDecl::new(name, args, return_type, body);
+        Ok((receiver, func_decl.with_span(start.to(self.span))))
+    }
+
+    fn return_decl(&mut self) -> Output<ast::Type> {
+        if let Token::Arrow = self.peek_token() {
+            self.next_token();
+            self.type_decl()
+        } else {
+            Ok(ast::Type::Void)
+        }
     }
 
     fn arg_decls(&mut self) -> Output<Vec<ast::ArgDecl>> {
         let mut list = Vec::new();
         while let Token::Ident = self.peek_token() {
             self.next_token();
+            let start = self.span;
             let name = self.slice;
             self.expect_token(Token::Colon)?;
             let arg_type = self.type_decl()?;
-            list.push(ast::ArgDecl::new(name, arg_type));
+            list.push(ast::ArgDecl::new(name, arg_type).with_span(start.to(self.span)));
             if let Token::Comma = self.peek_token() {
                 self.next_token();
             } else {
@@ -228,19 +497,33 @@ where
             Token::FloatType => Ok(ast::Type::Float),
             Token::DoubleType => Ok(ast::Type::Double),
             Token::BooleanType => Ok(ast::Type::Boolean),
+            Token::StringType => Ok(ast::Type::String),
             Token::VoidType => Ok(ast::Type::Void),
             Token::Ident => Ok(ast::Type::UserDefined(String::from(self.slice))),
+            Token::LBracket => {
+                let element_type = self.type_decl()?;
+                self.expect_token(Token::Semicolon)?;
+                self.expect_token(Token::Int)?;
+                let literal = self.number_literal(1);
+                let length = literal.parse::<u32>().map_err(|_| {
+                    error::ParserError::array_length_out_of_range(&literal, self.span)
+                })?;
+                self.expect_token(Token::RBracket)?;
+                Ok(ast::Type::Array(Box::new(element_type), length as usize))
+            }
             token => Err(error::ParserError::expected(
                 vec![
                     Token::IntType,
                     Token::FloatType,
                     Token::DoubleType,
                     Token::BooleanType,
+                    Token::StringType,
                     Token::VoidType,
                     Token::Ident,
+                    Token::LBracket,
                 ],
                 token,
-                self.range,
+                self.span,
             )),
         }
     }
@@ -248,25 +531,58 @@ where
     fn block(&mut self) -> Output<ast::Block> {
         //println!("Parsing block, next token: {:?}", self.peek_token());
         self.expect_token(Token::LBrace)?;
+        let start = self.span;
         let mut statements = Vec::new();
-        while {
-            statements.push(self.statement()?);
+        loop {
+            let statement = match self.statement() {
+                Ok(statement) => statement,
+                Err(error) => {
+                    let span = error.span();
+                    self.errors.push(error);
+                    self.synchronize_statement();
+                    ast::Statement::Error(span.to(self.span))
+                }
+            };
+            let recovered = statement.is_error();
+            statements.push(statement);
             //println!(
             //    "Parsing next statement in block. Next token: {:?}",
             //    self.peek_token()
             //);
-            match self.next_token() {
-                Token::Semicolon => true,
-                Token::RBrace => false,
+            match self.peek_token() {
+                Token::Semicolon => {
+                    self.next_token();
+                }
+                Token::RBrace => {
+                    self.next_token();
+                    break;
+                }
                 token => {
-                    return Err(error::ParserError::error(
-                        &format!("Expected ; or }}, found: {:?} ({})", token, self.slice),
-                        self.range,
-                    ))
+                    if !recovered {
+                        let span = self.peek_span();
+                        self.errors.push(error::ParserError::expected(
+                            vec![Token::Semicolon, Token::RBrace],
+                            token,
+                            span,
+                        ));
+                        self.synchronize_statement();
+                    }
+                    match self.peek_token() {
+                        Token::Semicolon => {
+                            self.next_token();
+                        }
+                        Token::RBrace => {
+                            self.next_token();
+                            break;
+                        }
+                        Token::Let => (),
+                        // The block is never closed
+                        _ => break,
+                    }
                 }
             }
-        } {} // This a rust hack for do while.
-        Ok(ast::Block::new(statements))
+        }
+        Ok(ast::Block::new(statements).with_span(start.to(self.span)))
     }
 
     fn statement(&mut self) -> Output<ast::Statement> {
@@ -277,14 +593,26 @@ where
             | Token::LParen
             | Token::Minus
             | Token::Ident
+            | Token::SelfValue
             | Token::Int
+            | Token::Float
             | Token::String
             | Token::If
+            | Token::While
+            | Token::Loop
+            | Token::Break
+            | Token::Continue
+            | Token::Return
             | Token::Not
             | Token::True
-            | Token::False => Ok(ast::Statement::Expression(self.expression(0)?)),
+            | Token::False
+            | Token::LBracket
+            | Token::Match => Ok(ast::Statement::Expression(self.expression(0)?)),
             Token::RBrace => Ok(ast::Statement::Empty),
-            _ => Err(error::ParserError::error("Unsupported token.", self.range)),
+            _ => Err(error::ParserError::error(
+                "Unsupported token.",
+                self.peek_span(),
+            )),
         }
         //println!(
         //    "Finished parsing statement, next token: {:?}",
@@ -300,25 +628,77 @@ where
 
     fn var_decl(&mut self) -> Output<ast::VarDecl> {
         //println!("Parsing VarDecl, next token: {:?}", self.peek_token());
+        let start = self.peek_span();
         self.expect_token(Token::Let)?;
+        let mutable = match self.peek_token() {
+            Token::Mut => {
+                self.next_token();
+                true
+            }
+            _ => false,
+        };
+        self.binding(start, mutable)
+    }
+
+    fn const_decl(&mut self) -> Output<ast::VarDecl> {
+        let start = self.peek_span();
+        self.expect_token(Token::Const)?;
+        self.binding(start, false)
+    }
+
+    /// The part of variable and constant declarations after the keywords
+    fn binding(&mut self, start: Span, mutable: bool) -> Output<ast::VarDecl> {
         let identifier = self.identifier()?;
+        let var_type = match self.peek_token() {
+            Token::Colon => {
+                self.next_token();
+                Some(self.type_decl()?)
+            }
+            _ => None,
+        };
         self.expect_token(Token::Equal)?;
-        let expression = self.expression(0)?;
-        Ok(ast::VarDecl::new(identifier, expression))
+        // Keep the declaration when the value is malformed, so the variable is still known
+        let expression = match self.expression(0) {
+            Ok(expression) => expression,
+            Err(error) => {
+                let span = error.span();
+                self.errors.push(error);
+                self.synchronize_statement();
+                ast::Expression::new(ast::ExpressionKind::Error, span.to(self.span))
+            }
+        };
+        let mut var_decl = ast::VarDecl::new(identifier, expression).with_span(start.to(self.span));
+        if mutable {
+            var_decl = var_decl.mutable();
+        }
+        Ok(match var_type {
+            Some(var_type) => var_decl.with_type(var_type),
+            None => var_decl,
+        })
     }
 
     /// rbp in this context means right binding power
     fn expression(&mut self, rbp: usize) -> Output<ast::Expression> {
         //println!("Parsing expression, next token: {:?}", self.peek_token());
-        let mut left = ast::Expression::nud(self)?;
+        let mut left = ast::ExpressionKind::nud(self)?;
         let mut token = self.peek_token();
         if token == Token::End {
             return Ok(left);
         }
 
-        while ast::Expression::bp(token) > rbp {
+        while ast::ExpressionKind::bp(token) > rbp {
             self.next_token();
-            left = ast::Expression::led(left, token, self.expression(ast::Expression::bp(token))?)?;
+            left = if let Token::Period = token {
+                self.field(left)?
+            } else if let Token::LBracket = token {
+                self.index(left)?
+            } else {
+                ast::ExpressionKind::led(
+                    left,
+                    token,
+                    self.expression(ast::ExpressionKind::right_bp(token))?,
+                )?
+            };
             token = self.peek_token();
             if token == Token::End {
                 return Ok(left);
@@ -332,9 +712,58 @@ where
         Ok(left)
     }
 
+    /// Parses the field name, or the method call, after `.`
+    fn field(&mut self, value: ast::Expression) -> Output<ast::Expression> {
+        self.expect_token(Token::Ident)?;
+        if let Token::LParen = self.peek_token() {
+            let call = self.function_call()?;
+            let span = value.span.to(self.span);
+            return Ok(ast::Expression::new(
+                ast::ExpressionKind::MethodCall(Box::new(value), call),
+                span,
+            ));
+        }
+        let span = value.span.to(self.span);
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::Field(Box::new(value), String::from(self.slice)),
+            span,
+        ))
+    }
+
+    /// Parses the index and `]` after `[`
+    fn index(&mut self, value: ast::Expression) -> Output<ast::Expression> {
+        let index = self.allowing_struct_literals(|parser| parser.expression(0))?;
+        self.expect_token(Token::RBracket)?;
+        let span = value.span.to(self.span);
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::Index(Box::new(value), Box::new(index)),
+            span,
+        ))
+    }
+
+    /// Parses the condition of `if` and `while`, where `{` starts the body
+    fn condition(&mut self) -> Output<ast::Expression> {
+        let struct_literals = std::mem::replace(&mut self.struct_literals, false);
+        let condition = self.expression(0);
+        self.struct_literals = struct_literals;
+        condition
+    }
+
+    /// Parses with struct literals allowed, e.g. inside parentheses in a condition
+    fn allowing_struct_literals<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Output<T>,
+    ) -> Output<T> {
+        let struct_literals = std::mem::replace(&mut self.struct_literals, true);
+        let result = parse(self);
+        self.struct_literals = struct_literals;
+        result
+    }
+
     fn if_expression(&mut self) -> Output<ast::IfExpression> {
+        let start = self.peek_span();
         self.expect_token(Token::If)?;
-        let condition = self.expression(0)?;
+        let condition = self.condition()?;
         let body = self.block()?;
 
         let mut else_expression = ast::ElseExpression::None;
@@ -349,58 +778,217 @@ where
                     return Err(error::ParserError::expected(
                         vec![Token::LBrace, Token::If],
                         token,
-                        self.range,
+                        self.span,
                     ))
                 }
             }
         }
-        Ok(ast::IfExpression::new(condition, body, else_expression))
+        Ok(ast::IfExpression::new(condition, body, else_expression).with_span(start.to(self.span)))
+    }
+
+    fn match_expression(&mut self) -> Output<ast::MatchExpression> {
+        let start = self.peek_span();
+        self.expect_token(Token::Match)?;
+        let value = self.condition()?;
+        self.expect_token(Token::LBrace)?;
+        let mut arms = Vec::new();
+        while self.peek_token() != Token::RBrace {
+            let pattern = self.pattern()?;
+            self.expect_token(Token::FatArrow)?;
+            let body = self.allowing_struct_literals(|parser| parser.expression(0))?;
+            let span = pattern.span.to(self.span);
+            let is_block = matches!(body.kind, ast::ExpressionKind::Block(_));
+            arms.push(ast::MatchArm::new(pattern, body).with_span(span));
+            // Arms with a block as the body need no comma
+            match self.peek_token() {
+                Token::Comma => {
+                    self.next_token();
+                }
+                Token::RBrace => break,
+                _ if is_block => (),
+                token => {
+                    return Err(error::ParserError::expected(
+                        vec![Token::Comma, Token::RBrace],
+                        token,
+                        self.peek_span(),
+                    ))
+                }
+            }
+        }
+        self.expect_token(Token::RBrace)?;
+
+        Ok(ast::MatchExpression::new(value, arms).with_span(start.to(self.span)))
+    }
+
+    fn pattern(&mut self) -> Output<ast::Pattern> {
+        let start = self.peek_span();
+        let kind = match self.next_token() {
+            Token::Ident if self.slice == "_" => ast::PatternKind::Wildcard,
+            Token::Ident if self.peek_token() == Token::DoubleColon => {
+                let enum_name = self.slice;
+                self.next_token();
+                self.expect_token(Token::Ident)?;
+                let variant = self.slice;
+                let mut patterns = Vec::new();
+                if let Token::LParen = self.peek_token() {
+                    self.next_token();
+                    while self.peek_token() != Token::RParen {
+                        patterns.push(self.pattern()?);
+                        if let Token::Comma = self.peek_token() {
+                            self.next_token();
+                        } else {
+                            break;
+                        }
+                    }
+                    self.expect_token(Token::RParen)?;
+                }
+                ast::PatternKind::Variant(String::from(enum_name), String::from(variant), patterns)
+            }
+            Token::Ident => ast::PatternKind::Binding(String::from(self.slice)),
+            token @ Token::Minus
+            | token @ Token::Int
+            | token @ Token::Float
+            | token @ Token::String
+            | token @ Token::True
+            | token @ Token::False => ast::PatternKind::Literal(self.literal(token)?),
+            token => {
+                return Err(error::ParserError::expected(
+                    vec![
+                        Token::Ident,
+                        Token::Int,
+                        Token::Float,
+                        Token::String,
+                        Token::True,
+                        Token::False,
+                    ],
+                    token,
+                    self.span,
+                ))
+            }
+        };
+        Ok(ast::Pattern::new(kind, start.to(self.span)))
     }
 
     fn value(&mut self) -> Output<ast::Value> {
         match self.next_token() {
             token @ Token::Minus
             | token @ Token::Int
+            | token @ Token::Float
             | token @ Token::String
             | token @ Token::True
             | token @ Token::False => Ok(ast::Value::Literal(self.literal(token)?)),
+            Token::SelfValue => Ok(ast::Value::Variable(String::from("self"))),
             Token::Ident => {
                 if let Token::LParen = self.peek_token() {
-                    let identifier = self.slice;
-                    self.expect_token(Token::LParen)?;
-                    let mut arguments = Vec::new();
-                    while match self.peek_token() {
-                        Token::RParen => false,
-                        Token::Comma => {
-                            self.next_token();
-                            true
-                        }
-                        _ => true,
-                    } {
-                        arguments.push(self.expression(0)?);
-                    }
-                    self.expect_token(Token::RParen)?;
-                    Ok(ast::Value::FunctionCall(ast::FunctionCall::new(
-                        identifier, arguments,
-                    )))
+                    Ok(ast::Value::FunctionCall(self.function_call()?))
+                } else if let Token::DoubleColon = self.peek_token() {
+                    Ok(ast::Value::Variant(self.variant_literal()?))
+                } else if self.peek_token() == Token::LBrace && self.struct_literals {
+                    Ok(ast::Value::Struct(self.struct_literal()?))
                 } else {
                     Ok(ast::Value::Variable(String::from(self.slice)))
                 }
             }
+            Token::LBracket => {
+                let mut elements = Vec::new();
+                while match self.peek_token() {
+                    Token::RBracket => false,
+                    Token::Comma => {
+                        self.next_token();
+                        true
+                    }
+                    _ => true,
+                } {
+                    elements.push(self.allowing_struct_literals(|parser| parser.expression(0))?);
+                }
+                self.expect_token(Token::RBracket)?;
+                Ok(ast::Value::Array(elements))
+            }
             token => Err(error::ParserError::expected(
                 vec![
                     Token::Minus,
                     Token::Int,
+                    Token::Float,
                     Token::True,
                     Token::False,
                     Token::Ident,
+                    Token::LBracket,
                 ],
                 token,
-                self.range,
+                self.span,
             )),
         }
     }
 
+    /// Parses the arguments of a call, after the name of the function
+    fn function_call(&mut self) -> Output<ast::FunctionCall> {
+        let start = self.span;
+        let identifier = self.slice;
+        self.expect_token(Token::LParen)?;
+        let mut arguments = Vec::new();
+        while match self.peek_token() {
+            Token::RParen => false,
+            Token::Comma => {
+                self.next_token();
+                true
+            }
+            _ => true,
+        } {
+            arguments.push(self.expression(0)?);
+        }
+        self.expect_token(Token::RParen)?;
+
+        Ok(ast::FunctionCall::new(identifier, arguments).with_span(start.to(self.span)))
+    }
+
+    /// Parses `::Variant(values)` after the name of an enum
+    fn variant_literal(&mut self) -> Output<ast::VariantLiteral> {
+        let start = self.span;
+        let enum_name = self.slice;
+        self.expect_token(Token::DoubleColon)?;
+        self.expect_token(Token::Ident)?;
+        let variant = self.slice;
+        let mut arguments = Vec::new();
+        if let Token::LParen = self.peek_token() {
+            self.next_token();
+            while self.peek_token() != Token::RParen {
+                arguments.push(self.allowing_struct_literals(|parser| parser.expression(0))?);
+                if let Token::Comma = self.peek_token() {
+                    self.next_token();
+                } else {
+                    break;
+                }
+            }
+            self.expect_token(Token::RParen)?;
+        }
+
+        Ok(ast::VariantLiteral::new(enum_name, variant, arguments).with_span(start.to(self.span)))
+    }
+
+    /// Parses the fields of a struct literal, after the name
+    fn struct_literal(&mut self) -> Output<ast::StructLiteral> {
+        let start = self.span;
+        let name = self.slice;
+        self.expect_token(Token::LBrace)?;
+        let mut fields = Vec::new();
+        while let Token::Ident = self.peek_token() {
+            self.next_token();
+            let field_start = self.span;
+            let field = self.slice;
+            self.expect_token(Token::Colon)?;
+            let value = self.allowing_struct_literals(|parser| parser.expression(0))?;
+            fields.push(ast::FieldValue::new(field, value).with_span(field_start.to(self.span)));
+            if let Token::Comma = self.peek_token() {
+                self.next_token();
+            } else {
+                break;
+            }
+        }
+        self.expect_token(Token::RBrace)?;
+
+        Ok(ast::StructLiteral::new(name, fields).with_span(start.to(self.span)))
+    }
+
     fn literal(&mut self, mut token: Token) -> Output<ast::Literal> {
         let sign = if let Token::Minus = token {
             token = self.next_token();
@@ -414,43 +1002,105 @@ where
             Token::String => Ok(ast::Literal::String(String::from(
                 &self.slice[1..self.slice.len() - 1],
             ))),
-            Token::Int => Ok(ast::Literal::Number(self.number(token, sign)?)),
+            Token::Int | Token::Float => Ok(ast::Literal::Number(self.number(token, sign)?)),
             token => Err(error::ParserError::expected(
-                vec![Token::True, Token::False, Token::Int],
+                vec![Token::True, Token::False, Token::Int, Token::Float],
                 token,
-                self.range,
+                self.span,
             )),
         }
     }
 
     fn number(&mut self, token: Token, sign: isize) -> Output<ast::Number> {
         match token {
-            Token::Int => Ok(ast::Number::Int(
-                sign * str::from_utf8(self.slice.as_bytes())
-                    .expect("Could not parse byte array")
+            Token::Int => {
+                // The sign is parsed along with the digits, so the smallest integer fits
+                let literal = self.number_literal(sign);
+                literal
                     .parse::<isize>()
-                    .expect("Parsing of integer failed"),
-            )),
+                    .map(ast::Number::Int)
+                    .map_err(|_| error::ParserError::integer_out_of_range(&literal, self.span))
+            }
+            Token::Float => {
+                let literal = self.number_literal(sign);
+                let number = if literal.ends_with('f') || literal.ends_with('F') {
+                    literal[..literal.len() - 1]
+                        .parse::<f32>()
+                        .map(ast::Number::Float)
+                        .ok()
+                } else {
+                    literal.parse::<f64>().map(ast::Number::Double).ok()
+                };
+                number.ok_or_else(|| {
+                    error::ParserError::error(
+                        format!("Invalid floating point literal: {}", literal),
+                        self.span,
+                    )
+                })
+            }
             token => Err(error::ParserError::expected(
-                vec![Token::Int],
+                vec![Token::Int, Token::Float],
                 token,
-                self.range,
+                self.span,
             )),
         }
     }
 
+    /// The current number literal with the sign prepended and `_` separators removed
+    fn number_literal(&self, sign: isize) -> String {
+        let digits = self.slice.chars().filter(|c| *c != '_');
+        if sign < 0 {
+            std::iter::once('-').chain(digits).collect()
+        } else {
+            digits.collect()
+        }
+    }
+
     fn next_token(&mut self) -> Token {
         if let Some(token_item) = self.lexer.next() {
-            self.range = self.range_converter.to_line_and_pos(token_item.range());
+            self.span = Span::new(self.file, token_item.range());
             self.slice = token_item.slice();
             //println!("Consuming token: {:?} ({})", token_item.token, self.slice);
             token_item.token
         } else {
+            self.span = Span::new(self.file, self.span.end..self.span.end);
             self.slice = "EOF";
             Token::End
         }
     }
 
+    /// Skips tokens until the next token is one of `tokens` or the end of the file.
+    /// Tokens inside braces opened while skipping are skipped as well,
+    /// so the stopping token is at the same nesting level as where the error occurred.
+    fn synchronize(&mut self, tokens: &[Token]) {
+        let mut depth = 0usize;
+        loop {
+            match self.peek_token() {
+                Token::End => return,
+                token if depth == 0 && tokens.contains(&token) => return,
+                Token::LBrace => depth += 1,
+                Token::RBrace if depth > 0 => depth -= 1,
+                _ => (),
+            }
+            self.next_token();
+        }
+    }
+
+    /// Skips to the end of the current statement, or to the start of the next declaration
+    fn synchronize_statement(&mut self) {
+        let mut tokens = vec![Token::Semicolon, Token::RBrace];
+        tokens.extend_from_slice(DECL_START);
+        self.synchronize(&tokens);
+    }
+
+    /// The span of the next token, or an empty span after the current token at the end of the file
+    fn peek_span(&mut self) -> Span {
+        match self.lexer.peek() {
+            Some(token_item) => Span::new(self.file, token_item.range()),
+            None => Span::new(self.file, self.span.end..self.span.end),
+        }
+    }
+
     fn peek_token(&mut self) -> Token {
         if let Some(token_item) = self.lexer.peek() {
             token_item.token
@@ -467,7 +1117,7 @@ where
             Err(error::ParserError::expected(
                 vec![expected],
                 token,
-                self.range,
+                self.span,
             ))
         }
     }
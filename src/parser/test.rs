@@ -1,12 +1,10 @@
 use super::ast::*;
 use super::*;
 use logos::Logos;
+use std::convert::TryFrom;
 
-fn parser(program: &'static str) -> Parser<&'static str> {
-    Parser::new(
-        LexerWrapper(Token::lexer(program)),
-        RangeConverter::new(program),
-    )
+fn parser(program: &'static str) -> Parser<'static> {
+    Parser::new(LexerWrapper(Token::lexer(program)))
 }
 
 #[test]
@@ -14,19 +12,23 @@ fn expression_precedence_multiplication_last_test() {
     let mut parser = parser("3 + 4 * 5");
     let expression = parser.expression(0);
 
-    let expected = Expression::Addition(
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(3),
-        )))),
-        ExpressionContainer::new(Expression::Multiplication(
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(4),
-            )))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(5),
-            )))),
-        )),
-    );
+    let expected: Expression = ExpressionKind::Addition(
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(3)))).into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Multiplication(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(4)))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+                ),
+            )
+            .into(),
+        ),
+    )
+    .into();
 
     println!("{:?}", expression);
     assert_eq!(expression.unwrap(), expected);
@@ -37,19 +39,23 @@ fn expression_precedence_multiplication_first_test() {
     let mut parser = parser("3 * 4 + 5");
     let expression = parser.expression(0);
 
-    let expected = Expression::Addition(
-        ExpressionContainer::new(Expression::Multiplication(
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(3),
-            )))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(4),
-            )))),
-        )),
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(5),
-        )))),
-    );
+    let expected: Expression = ExpressionKind::Addition(
+        ExpressionContainer::new(
+            ExpressionKind::Multiplication(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(3)))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(4)))).into(),
+                ),
+            )
+            .into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+        ),
+    )
+    .into();
 
     println!("{:?}", expression);
     assert_eq!(expression.unwrap(), expected);
@@ -60,19 +66,23 @@ fn expression_precedence_parentheses_test() {
     let mut parser = parser("3 * (4 + 5)");
     let expression = parser.expression(0);
 
-    let expected = Expression::Multiplication(
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(3),
-        )))),
-        ExpressionContainer::new(Expression::Addition(
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(4),
-            )))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(5),
-            )))),
-        )),
-    );
+    let expected: Expression = ExpressionKind::Multiplication(
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(3)))).into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Addition(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(4)))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+                ),
+            )
+            .into(),
+        ),
+    )
+    .into();
 
     println!("{:?}", expression);
     assert_eq!(expression.unwrap(), expected);
@@ -83,24 +93,33 @@ fn expression_precedence_double_parentheses_test() {
     let mut parser = parser("(3 * (4 + 5)) + 6");
     let expression = parser.expression(0);
 
-    let expected = Expression::Addition(
-        ExpressionContainer::new(Expression::Multiplication(
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(3),
-            )))),
-            ExpressionContainer::new(Expression::Addition(
-                ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                    Number::Int(4),
-                )))),
-                ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                    Number::Int(5),
-                )))),
-            )),
-        )),
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(6),
-        )))),
-    );
+    let expected: Expression = ExpressionKind::Addition(
+        ExpressionContainer::new(
+            ExpressionKind::Multiplication(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(3)))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Addition(
+                        ExpressionContainer::new(
+                            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(4))))
+                                .into(),
+                        ),
+                        ExpressionContainer::new(
+                            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5))))
+                                .into(),
+                        ),
+                    )
+                    .into(),
+                ),
+            )
+            .into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(6)))).into(),
+        ),
+    )
+    .into();
 
     println!("{:?}", expression);
     assert_eq!(expression.unwrap(), expected);
@@ -111,32 +130,91 @@ fn expression_multiplication_and_unary_minus_test() {
     let mut parser = parser("3 * -4");
     let expression = parser.expression(0);
 
-    let expected = Expression::Multiplication(
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(3),
-        )))),
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(-4),
-        )))),
-    );
+    let expected: Expression = ExpressionKind::Multiplication(
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(3)))).into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(-4)))).into(),
+        ),
+    )
+    .into();
 
     println!("{:?}", expression);
     assert_eq!(expression.unwrap(), expected);
 }
 
+#[test]
+fn expression_negation_binds_tighter_than_binary_operators() {
+    let mut parser = parser("-x * 2 - -(a + b)");
+    let expression = parser.expression(0);
+
+    let variable = |name: &str| -> Expression {
+        ExpressionKind::Value(Value::Variable(String::from(name))).into()
+    };
+    let expected: Expression = ExpressionKind::Subtraction(
+        ExpressionContainer::new(
+            ExpressionKind::Multiplication(
+                ExpressionContainer::new(
+                    ExpressionKind::Neg(ExpressionContainer::new(variable("x"))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+                ),
+            )
+            .into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Neg(ExpressionContainer::new(
+                ExpressionKind::Addition(
+                    ExpressionContainer::new(variable("a")),
+                    ExpressionContainer::new(variable("b")),
+                )
+                .into(),
+            ))
+            .into(),
+        ),
+    )
+    .into();
+
+    assert_eq!(expression.unwrap(), expected);
+}
+
+#[test]
+fn expression_not_applies_to_the_whole_expression() {
+    let mut parser = parser("!a < b");
+    let expression = parser.expression(0);
+
+    let expected: Expression = ExpressionKind::Not(ExpressionContainer::new(
+        ExpressionKind::LessThan(
+            ExpressionContainer::new(
+                ExpressionKind::Value(Value::Variable(String::from("a"))).into(),
+            ),
+            ExpressionContainer::new(
+                ExpressionKind::Value(Value::Variable(String::from("b"))).into(),
+            ),
+        )
+        .into(),
+    ))
+    .into();
+
+    assert_eq!(expression.unwrap(), expected);
+}
+
 #[test]
 fn expression_unary_minus_first_then_multiplication_test() {
     let mut parser = parser("-3 * 4");
     let expression = parser.expression(0);
 
-    let expected = Expression::Multiplication(
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(-3),
-        )))),
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(4),
-        )))),
-    );
+    let expected: Expression = ExpressionKind::Multiplication(
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(-3)))).into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(4)))).into(),
+        ),
+    )
+    .into();
 
     println!("{:?}", expression);
     assert_eq!(expression.unwrap(), expected);
@@ -147,19 +225,23 @@ fn expression_precedence_modulus_first_test() {
     let mut parser = parser("3 % 4 + 5");
     let expression = parser.expression(0);
 
-    let expected = Expression::Modulus(
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(3),
-        )))),
-        ExpressionContainer::new(Expression::Addition(
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(4),
-            )))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(5),
-            )))),
-        )),
-    );
+    let expected: Expression = ExpressionKind::Modulus(
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(3)))).into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Addition(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(4)))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+                ),
+            )
+            .into(),
+        ),
+    )
+    .into();
 
     println!("{:?}", expression);
     assert_eq!(expression.unwrap(), expected);
@@ -170,19 +252,23 @@ fn expression_precedence_modulus_last_test() {
     let mut parser = parser("3 + 4 % 5");
     let expression = parser.expression(0);
 
-    let expected = Expression::Modulus(
-        ExpressionContainer::new(Expression::Addition(
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(3),
-            )))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(4),
-            )))),
-        )),
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(5),
-        )))),
-    );
+    let expected: Expression = ExpressionKind::Modulus(
+        ExpressionContainer::new(
+            ExpressionKind::Addition(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(3)))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(4)))).into(),
+                ),
+            )
+            .into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+        ),
+    )
+    .into();
 
     println!("{:?}", expression);
     assert_eq!(expression.unwrap(), expected);
@@ -193,12 +279,13 @@ fn expression_with_variable() {
     let mut parser = parser("x + 2");
     let expression = parser.expression(0);
 
-    let expected = Expression::Addition(
-        ExpressionContainer::new(Expression::Value(Value::Variable(String::from("x")))),
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(2),
-        )))),
-    );
+    let expected: Expression = ExpressionKind::Addition(
+        ExpressionContainer::new(ExpressionKind::Value(Value::Variable(String::from("x"))).into()),
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+        ),
+    )
+    .into();
 
     println!("{:?}", expression);
     assert_eq!(expression.unwrap(), expected);
@@ -209,15 +296,15 @@ fn expression_with_function_call_without_arguments() {
     let mut parser = parser("x() + 2");
     let expression = parser.expression(0);
 
-    let expected = Expression::Addition(
-        ExpressionContainer::new(Expression::Value(Value::FunctionCall(FunctionCall::new(
-            "x",
-            Vec::new(),
-        )))),
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(2),
-        )))),
-    );
+    let expected: Expression = ExpressionKind::Addition(
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::FunctionCall(FunctionCall::new("x", Vec::new()))).into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+        ),
+    )
+    .into();
 
     println!("{:?}", expression);
     assert_eq!(expression.unwrap(), expected);
@@ -228,22 +315,29 @@ fn expression_with_function_call_with_single_argument() {
     let mut parser = parser("x(2 + 2) + 2");
     let expression = parser.expression(0);
 
-    let expected = Expression::Addition(
-        ExpressionContainer::new(Expression::Value(Value::FunctionCall(FunctionCall::new(
-            "x",
-            vec![Expression::Addition(
-                ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                    Number::Int(2),
-                )))),
-                ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                    Number::Int(2),
-                )))),
-            )],
-        )))),
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(2),
-        )))),
-    );
+    let expected: Expression = ExpressionKind::Addition(
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::FunctionCall(FunctionCall::new(
+                "x",
+                vec![ExpressionKind::Addition(
+                    ExpressionContainer::new(
+                        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2))))
+                            .into(),
+                    ),
+                    ExpressionContainer::new(
+                        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2))))
+                            .into(),
+                    ),
+                )
+                .into()],
+            )))
+            .into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+        ),
+    )
+    .into();
 
     println!("{:?}", expression);
     assert_eq!(expression.unwrap(), expected);
@@ -254,26 +348,33 @@ fn expression_with_function_call_with_multiple_arguments() {
     let mut parser = parser("x(2 + 2, y, z) + 2");
     let expression = parser.expression(0);
 
-    let expected = Expression::Addition(
-        ExpressionContainer::new(Expression::Value(Value::FunctionCall(FunctionCall::new(
-            "x",
-            vec![
-                Expression::Addition(
-                    ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                        Number::Int(2),
-                    )))),
-                    ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                        Number::Int(2),
-                    )))),
-                ),
-                Expression::Value(Value::Variable(String::from("y"))),
-                Expression::Value(Value::Variable(String::from("z"))),
-            ],
-        )))),
-        ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-            Number::Int(2),
-        )))),
-    );
+    let expected: Expression = ExpressionKind::Addition(
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::FunctionCall(FunctionCall::new(
+                "x",
+                vec![
+                    ExpressionKind::Addition(
+                        ExpressionContainer::new(
+                            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2))))
+                                .into(),
+                        ),
+                        ExpressionContainer::new(
+                            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2))))
+                                .into(),
+                        ),
+                    )
+                    .into(),
+                    ExpressionKind::Value(Value::Variable(String::from("y"))).into(),
+                    ExpressionKind::Value(Value::Variable(String::from("z"))).into(),
+                ],
+            )))
+            .into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+        ),
+    )
+    .into();
 
     println!("{:?}", expression);
     assert_eq!(expression.unwrap(), expected);
@@ -286,9 +387,10 @@ fn var_decl_with_string() {
 
     let expected = VarDecl::new(
         "x".to_string(),
-        Expression::Value(Value::Literal(Literal::String(String::from(
+        ExpressionKind::Value(Value::Literal(Literal::String(String::from(
             "hello, world!",
-        )))),
+        ))))
+        .into(),
     );
 
     println!("{:?}", var_decl);
@@ -302,13 +404,27 @@ fn var_decl() {
 
     let expected = VarDecl::new(
         "x".to_string(),
-        Expression::Value(Value::Literal(Literal::Number(Number::Int(5)))),
+        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
     );
 
     println!("{:?}", var_decl);
     assert_eq!(var_decl.unwrap(), expected);
 }
 
+#[test]
+fn var_decl_with_type() {
+    let mut parser = parser("let x: bool = true");
+    let var_decl = parser.var_decl();
+
+    let expected = VarDecl::new(
+        "x".to_string(),
+        ExpressionKind::Value(Value::Literal(Literal::Boolean(true))).into(),
+    )
+    .with_type(Type::Boolean);
+
+    assert_eq!(var_decl.unwrap(), expected);
+}
+
 #[test]
 fn block_single_statement_with_return() {
     let mut parser = parser("{ let x = 5 }");
@@ -316,7 +432,7 @@ fn block_single_statement_with_return() {
 
     let expected = Block::new(vec![Statement::VarDecl(VarDecl::new(
         "x".to_string(),
-        Expression::Value(Value::Literal(Literal::Number(Number::Int(5)))),
+        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
     ))]);
 
     println!("{:?}", block);
@@ -331,7 +447,7 @@ fn block_single_statement_without_return() {
     let expected = Block::new(vec![
         Statement::VarDecl(VarDecl::new(
             "x".to_string(),
-            Expression::Value(Value::Literal(Literal::Number(Number::Int(5)))),
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
         )),
         Statement::Empty,
     ]);
@@ -348,14 +464,17 @@ fn block_double_statement_with_return() {
     let expected = Block::new(vec![
         Statement::VarDecl(VarDecl::new(
             "x".to_string(),
-            Expression::Value(Value::Literal(Literal::Number(Number::Int(5)))),
-        )),
-        Statement::Expression(Expression::Addition(
-            Box::new(Expression::Value(Value::Variable(String::from("x")))),
-            Box::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(5),
-            )))),
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
         )),
+        Statement::Expression(
+            ExpressionKind::Addition(
+                Box::new(ExpressionKind::Value(Value::Variable(String::from("x"))).into()),
+                Box::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+                ),
+            )
+            .into(),
+        ),
     ]);
 
     println!("{:?}", block);
@@ -367,20 +486,25 @@ fn block_with_inner_block() {
     let mut parser = parser("{ { let x = 5; x + 5 } }");
     let block = parser.block();
 
-    let expected = Block::new(vec![Statement::Expression(Expression::Block(Block::new(
-        vec![
+    let expected = Block::new(vec![Statement::Expression(
+        ExpressionKind::Block(Block::new(vec![
             Statement::VarDecl(VarDecl::new(
                 "x".to_string(),
-                Expression::Value(Value::Literal(Literal::Number(Number::Int(5)))),
-            )),
-            Statement::Expression(Expression::Addition(
-                Box::new(Expression::Value(Value::Variable(String::from("x")))),
-                Box::new(Expression::Value(Value::Literal(Literal::Number(
-                    Number::Int(5),
-                )))),
+                ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
             )),
-        ],
-    )))]);
+            Statement::Expression(
+                ExpressionKind::Addition(
+                    Box::new(ExpressionKind::Value(Value::Variable(String::from("x"))).into()),
+                    Box::new(
+                        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5))))
+                            .into(),
+                    ),
+                )
+                .into(),
+            ),
+        ]))
+        .into(),
+    )]);
 
     println!("{:?}", block);
     assert_eq!(block.unwrap(), expected);
@@ -421,14 +545,17 @@ fn func_decl_without_args_without_return_type() {
         "function",
         vec![],
         Type::Void,
-        Block::new(vec![Statement::Expression(Expression::Addition(
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(2),
-            )))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(1),
-            )))),
-        ))]),
+        Block::new(vec![Statement::Expression(
+            ExpressionKind::Addition(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(1)))).into(),
+                ),
+            )
+            .into(),
+        )]),
     );
 
     println!("{:?}", function);
@@ -447,14 +574,17 @@ fn func_decl_with_args_without_return_type() {
             ArgDecl::new("second", Type::Boolean),
         ],
         Type::Void,
-        Block::new(vec![Statement::Expression(Expression::Addition(
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(2),
-            )))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(1),
-            )))),
-        ))]),
+        Block::new(vec![Statement::Expression(
+            ExpressionKind::Addition(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(1)))).into(),
+                ),
+            )
+            .into(),
+        )]),
     );
 
     println!("{:?}", function);
@@ -470,14 +600,17 @@ fn func_decl_without_args_with_return_type() {
         "function",
         vec![],
         Type::Int,
-        Block::new(vec![Statement::Expression(Expression::Addition(
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(2),
-            )))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(1),
-            )))),
-        ))]),
+        Block::new(vec![Statement::Expression(
+            ExpressionKind::Addition(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(1)))).into(),
+                ),
+            )
+            .into(),
+        )]),
     );
 
     println!("{:?}", function);
@@ -496,14 +629,17 @@ fn func_decl_with_args_with_return_type() {
             ArgDecl::new("second", Type::Boolean),
         ],
         Type::Int,
-        Block::new(vec![Statement::Expression(Expression::Addition(
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(2),
-            )))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(1),
-            )))),
-        ))]),
+        Block::new(vec![Statement::Expression(
+            ExpressionKind::Addition(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(1)))).into(),
+                ),
+            )
+            .into(),
+        )]),
     );
 
     println!("{:?}", function);
@@ -519,14 +655,17 @@ fn decl_func_decl() {
         "function",
         vec![],
         Type::Void,
-        Block::new(vec![Statement::Expression(Expression::Addition(
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(2),
-            )))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(1),
-            )))),
-        ))]),
+        Block::new(vec![Statement::Expression(
+            ExpressionKind::Addition(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(1)))).into(),
+                ),
+            )
+            .into(),
+        )]),
     ));
 
     println!("{:?}", decl);
@@ -540,30 +679,50 @@ fn decl_var_decl() {
 
     let expected = Decl::VarDecl(VarDecl::new(
         String::from("x"),
-        Expression::Value(Value::Literal(Literal::Number(Number::Int(5)))),
+        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
     ));
     println!("{:?}", decl);
     assert_eq!(decl.unwrap(), expected);
 }
 
+#[test]
+fn decl_const_decl() {
+    let mut parser = parser("const x: int = 5;");
+    let decl = parser.decl();
+
+    let expected = Decl::ConstDecl(
+        VarDecl::new(
+            String::from("x"),
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+        )
+        .with_type(Type::Int),
+    );
+    assert_eq!(decl.unwrap(), expected);
+}
+
 #[test]
 fn program_with_single_function() {
     let mut parser = parser("fn main() { 2 + 1 }");
-    let program = parser.program();
+    let program = parser.parse();
 
     let expected = Program::Decl(
         Decl::FuncDecl(FuncDecl::new(
             "main",
             vec![],
             Type::Void,
-            Block::new(vec![Statement::Expression(Expression::Addition(
-                ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                    Number::Int(2),
-                )))),
-                ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                    Number::Int(1),
-                )))),
-            ))]),
+            Block::new(vec![Statement::Expression(
+                ExpressionKind::Addition(
+                    ExpressionContainer::new(
+                        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2))))
+                            .into(),
+                    ),
+                    ExpressionContainer::new(
+                        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(1))))
+                            .into(),
+                    ),
+                )
+                .into(),
+            )]),
         )),
         ProgramContainer::new(Program::Empty),
     );
@@ -578,15 +737,18 @@ fn if_expression_no_else() {
     let if_expression = parser.if_expression();
 
     let expected = IfExpression::new(
-        Expression::LessThan(
-            ExpressionContainer::new(Expression::Value(Value::Variable(String::from("x")))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(3),
-            )))),
-        ),
-        Block::new(vec![Statement::Expression(Expression::Value(
-            Value::Literal(Literal::Number(Number::Int(5))),
-        ))]),
+        ExpressionKind::LessThan(
+            ExpressionContainer::new(
+                ExpressionKind::Value(Value::Variable(String::from("x"))).into(),
+            ),
+            ExpressionContainer::new(
+                ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(3)))).into(),
+            ),
+        )
+        .into(),
+        Block::new(vec![Statement::Expression(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+        )]),
         ElseExpression::None,
     );
 
@@ -600,18 +762,21 @@ fn if_expression_with_else_block() {
     let if_expression = parser.if_expression();
 
     let expected = IfExpression::new(
-        Expression::LessThan(
-            ExpressionContainer::new(Expression::Value(Value::Variable(String::from("x")))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(3),
-            )))),
-        ),
-        Block::new(vec![Statement::Expression(Expression::Value(
-            Value::Literal(Literal::Number(Number::Int(5))),
-        ))]),
-        ElseExpression::Block(Block::new(vec![Statement::Expression(Expression::Value(
-            Value::Literal(Literal::Number(Number::Int(2))),
-        ))])),
+        ExpressionKind::LessThan(
+            ExpressionContainer::new(
+                ExpressionKind::Value(Value::Variable(String::from("x"))).into(),
+            ),
+            ExpressionContainer::new(
+                ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(3)))).into(),
+            ),
+        )
+        .into(),
+        Block::new(vec![Statement::Expression(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+        )]),
+        ElseExpression::Block(Block::new(vec![Statement::Expression(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+        )])),
     );
 
     println!("{:?}", if_expression);
@@ -624,25 +789,31 @@ fn if_expression_with_else_if() {
     let if_expression = parser.if_expression();
 
     let expected = IfExpression::new(
-        Expression::LessThan(
-            ExpressionContainer::new(Expression::Value(Value::Variable(String::from("x")))),
-            ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                Number::Int(3),
-            )))),
-        ),
-        Block::new(vec![Statement::Expression(Expression::Value(
-            Value::Literal(Literal::Number(Number::Int(5))),
-        ))]),
-        ElseExpression::IfExpression(IfExpressionContainer::new(IfExpression::new(
-            Expression::GreaterThan(
-                ExpressionContainer::new(Expression::Value(Value::Variable(String::from("x")))),
-                ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                    Number::Int(2),
-                )))),
+        ExpressionKind::LessThan(
+            ExpressionContainer::new(
+                ExpressionKind::Value(Value::Variable(String::from("x"))).into(),
+            ),
+            ExpressionContainer::new(
+                ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(3)))).into(),
             ),
-            Block::new(vec![Statement::Expression(Expression::Value(
-                Value::Literal(Literal::Number(Number::Int(2))),
-            ))]),
+        )
+        .into(),
+        Block::new(vec![Statement::Expression(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+        )]),
+        ElseExpression::IfExpression(IfExpressionContainer::new(IfExpression::new(
+            ExpressionKind::GreaterThan(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Variable(String::from("x"))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+                ),
+            )
+            .into(),
+            Block::new(vec![Statement::Expression(
+                ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+            )]),
             ElseExpression::None,
         ))),
     );
@@ -658,20 +829,575 @@ fn statement_with_if_expression_no_else() {
 
     let expected = Statement::VarDecl(VarDecl::new(
         String::from("x"),
-        Expression::If(IfExpressionContainer::new(IfExpression::new(
-            Expression::LessThan(
-                ExpressionContainer::new(Expression::Value(Value::Variable(String::from("x")))),
-                ExpressionContainer::new(Expression::Value(Value::Literal(Literal::Number(
-                    Number::Int(3),
-                )))),
-            ),
-            Block::new(vec![Statement::Expression(Expression::Value(
-                Value::Literal(Literal::Number(Number::Int(5))),
-            ))]),
+        ExpressionKind::If(IfExpressionContainer::new(IfExpression::new(
+            ExpressionKind::LessThan(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Variable(String::from("x"))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(3)))).into(),
+                ),
+            )
+            .into(),
+            Block::new(vec![Statement::Expression(
+                ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+            )]),
             ElseExpression::None,
-        ))),
+        )))
+        .into(),
     ));
 
     println!("{:?}", if_expression);
     assert_eq!(if_expression.unwrap(), expected);
 }
+
+#[test]
+fn expression_spans() {
+    let mut parser = parser("(3 + 4) * foo(5)");
+    let expression = parser.expression(0).unwrap();
+
+    assert_eq!(expression.span.range(), 0..16);
+    match expression.kind {
+        ExpressionKind::Multiplication(left, right) => {
+            assert_eq!(left.span.range(), 0..7);
+            assert_eq!(right.span.range(), 10..16);
+            match right.kind {
+                ExpressionKind::Value(Value::FunctionCall(call)) => {
+                    assert_eq!(call.span.range(), 10..16);
+                    assert_eq!(call.arguments[0].span.range(), 14..15);
+                }
+                kind => panic!("Expected function call, found: {:?}", kind),
+            }
+        }
+        kind => panic!("Expected multiplication, found: {:?}", kind),
+    }
+}
+
+#[test]
+fn func_decl_spans() {
+    let program = "fn add(a: int, b: int) -> int {\n    let c = a + b;\n    c\n}";
+    let mut parser = Parser::new_with_file(LexerWrapper(Token::lexer(program)), FileId::default());
+    let func_decl = parser.func_decl().unwrap();
+
+    assert_eq!(func_decl.span.range(), 0..program.len());
+    assert_eq!(func_decl.args[1].span.range(), 15..21);
+    assert_eq!(func_decl.body.span.range(), 30..program.len());
+    assert_eq!(func_decl.body[0].span().range(), 36..49);
+}
+
+#[test]
+fn parse_reports_multiple_errors() {
+    let mut parser = parser("fn main() { let x = 5 y; let z = ; x }\nfn other() { 1 + }");
+    let (program, errors) = parser.parse_recovering();
+
+    assert_eq!(errors.len(), 3);
+    assert_eq!(errors[0].span().range(), 22..23);
+    assert_eq!(errors[1].span().range(), 33..34);
+    assert_eq!(errors[2].span().range(), 56..57);
+    match program {
+        Program::Decl(Decl::FuncDecl(main), rest) => {
+            assert_eq!(main.name, "main");
+            assert_eq!(
+                main.body[0],
+                Statement::VarDecl(VarDecl::new(
+                    String::from("x"),
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into()
+                ))
+            );
+            assert_eq!(
+                main.body[1],
+                Statement::VarDecl(VarDecl::new(
+                    String::from("z"),
+                    ExpressionKind::Error.into()
+                ))
+            );
+            assert_eq!(
+                main.body[2],
+                Statement::Expression(
+                    ExpressionKind::Value(Value::Variable(String::from("x"))).into()
+                )
+            );
+            match *rest {
+                Program::Decl(Decl::FuncDecl(other), _) => {
+                    assert_eq!(other.name, "other");
+                    assert!(other.body[0].is_error());
+                }
+                program => panic!("Expected function declaration, found: {:?}", program),
+            }
+        }
+        program => panic!("Expected function declaration, found: {:?}", program),
+    }
+}
+
+#[test]
+fn parse_recovers_at_top_level() {
+    let source = "} fn main() { 1 }";
+    let mut parser = parser(source);
+    let (program, errors) = parser.parse_recovering();
+
+    assert_eq!(errors.len(), 1);
+    match program {
+        Program::Decl(Decl::Error(span), rest) => {
+            assert_eq!(span.range(), 0..1);
+            assert!(matches!(*rest, Program::Decl(Decl::FuncDecl(_), _)));
+        }
+        program => panic!("Expected error declaration, found: {:?}", program),
+    }
+    // `parse` reports the same error instead of the recovered program
+    let fresh = self::parser(source).parse().unwrap_err();
+    assert_eq!(fresh.len(), 1);
+    assert_eq!(fresh[0].message(), errors[0].message());
+    assert_eq!(fresh[0].span(), errors[0].span());
+}
+
+#[test]
+fn literal_integer_out_of_range() {
+    let mut parser = parser("let x = 99999999999999999999;");
+    let errors = parser.parse().unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].code(), error::ParserErrorCode::E0003));
+    assert_eq!(errors[0].span().range(), 8..28);
+}
+
+#[test]
+fn array_length_out_of_range() {
+    let errors = parser("fn f(a: [int; 4294967296]) {}").parse().unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].code(), error::ParserErrorCode::E0004));
+    assert_eq!(errors[0].span().range(), 14..24);
+
+    assert!(parser("fn f(a: [int; 4294967295]) {}").parse().is_ok());
+}
+
+#[test]
+fn literal_smallest_integer() {
+    let mut parser = parser("-9223372036854775808");
+    let expression = parser.expression(0);
+
+    let expected: Expression =
+        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(isize::MIN)))).into();
+    assert_eq!(expression.unwrap(), expected);
+}
+
+#[test]
+fn literal_integer_with_underscores() {
+    let mut parser = parser("1_000_000");
+    let expression = parser.expression(0);
+
+    let expected: Expression =
+        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(1_000_000)))).into();
+    assert_eq!(expression.unwrap(), expected);
+}
+
+#[test]
+fn type_try_from_parser() {
+    let mut parser = parser("int ;");
+
+    assert_eq!(Type::try_from(&mut parser).unwrap(), Type::Int);
+    assert!(matches!(
+        Type::try_from(&mut parser).unwrap_err().code(),
+        error::ParserErrorCode::E0002
+    ));
+}
+
+#[test]
+fn number_try_from_parser() {
+    let mut parser = parser("-42 true");
+
+    assert_eq!(Number::try_from(&mut parser).unwrap(), Number::Int(-42));
+    assert!(Number::try_from(&mut parser).is_err());
+}
+
+#[test]
+fn literal_floats() {
+    let mut parser = parser("1.5 * -2.0e-3f");
+    let expression = parser.expression(0);
+
+    let expected: Expression = ExpressionKind::Multiplication(
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Double(1.5)))).into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Float(-2.0e-3)))).into(),
+        ),
+    )
+    .into();
+    assert_eq!(expression.unwrap(), expected);
+}
+
+#[test]
+fn while_expression() {
+    let mut parser = parser("while x { continue }");
+    let expression = parser.expression(0);
+
+    let expected: Expression = ExpressionKind::While(
+        ExpressionContainer::new(ExpressionKind::Value(Value::Variable(String::from("x"))).into()),
+        Block::new(vec![Statement::Expression(ExpressionKind::Continue.into())]),
+    )
+    .into();
+
+    assert_eq!(expression.unwrap(), expected);
+}
+
+#[test]
+fn loop_expression_with_breaks() {
+    let mut parser = parser("loop { break; break 5 }");
+    let expression = parser.expression(0);
+
+    let expected: Expression = ExpressionKind::Loop(LoopExpression::new(Block::new(vec![
+        Statement::Expression(ExpressionKind::Break(None).into()),
+        Statement::Expression(
+            ExpressionKind::Break(Some(ExpressionContainer::new(
+                ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+            )))
+            .into(),
+        ),
+    ])))
+    .into();
+
+    assert_eq!(expression.unwrap(), expected);
+}
+
+#[test]
+fn var_decl_mutable() {
+    let mut parser = parser("let mut x = 5");
+    let var_decl = parser.var_decl();
+
+    let expected = VarDecl::new(
+        "x".to_string(),
+        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+    )
+    .mutable();
+
+    assert_eq!(var_decl.unwrap(), expected);
+}
+
+#[test]
+fn compound_assignment() {
+    let mut parser = parser("x += 2 * 3");
+    let expression = parser.expression(0);
+
+    let expected: Expression = ExpressionKind::CompoundAssignment(
+        AssignmentOp::Add,
+        ExpressionContainer::new(ExpressionKind::Value(Value::Variable("x".into())).into()),
+        ExpressionContainer::new(
+            ExpressionKind::Multiplication(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(3)))).into(),
+                ),
+            )
+            .into(),
+        ),
+    )
+    .into();
+
+    assert_eq!(expression.unwrap(), expected);
+}
+
+#[test]
+fn assignment_is_right_associative() {
+    let mut parser = parser("a = b += c");
+    let expression = parser.expression(0);
+
+    let expected: Expression = ExpressionKind::Assignment(
+        ExpressionContainer::new(ExpressionKind::Value(Value::Variable("a".into())).into()),
+        ExpressionContainer::new(
+            ExpressionKind::CompoundAssignment(
+                AssignmentOp::Add,
+                ExpressionContainer::new(ExpressionKind::Value(Value::Variable("b".into())).into()),
+                ExpressionContainer::new(ExpressionKind::Value(Value::Variable("c".into())).into()),
+            )
+            .into(),
+        ),
+    )
+    .into();
+
+    assert_eq!(expression.unwrap(), expected);
+}
+
+#[test]
+fn return_expression() {
+    let mut parser = parser("{ return; return x }");
+    let block = parser.block();
+
+    let expected = Block::new(vec![
+        Statement::Expression(ExpressionKind::Return(None).into()),
+        Statement::Expression(
+            ExpressionKind::Return(Some(ExpressionContainer::new(
+                ExpressionKind::Value(Value::Variable(String::from("x"))).into(),
+            )))
+            .into(),
+        ),
+    ]);
+
+    assert_eq!(block.unwrap(), expected);
+}
+
+#[test]
+fn extern_decl() {
+    let mut parser =
+        parser("extern fn puts(s: string) -> int; extern fn printf(format: string, ...) -> int;");
+
+    assert_eq!(
+        parser.extern_decl().unwrap(),
+        ExternDecl::new("puts", vec![ArgDecl::new("s", Type::String)], Type::Int)
+    );
+    assert_eq!(
+        parser.extern_decl().unwrap(),
+        ExternDecl::new(
+            "printf",
+            vec![ArgDecl::new("format", Type::String)],
+            Type::Int
+        )
+        .variadic()
+    );
+}
+
+#[test]
+fn struct_decl() {
+    let mut parser = parser("struct Point { x: int, y: double }");
+
+    assert_eq!(
+        parser.struct_decl().unwrap(),
+        StructDecl::new(
+            "Point",
+            vec![
+                ArgDecl::new("x", Type::Int),
+                ArgDecl::new("y", Type::Double)
+            ]
+        )
+    );
+}
+
+#[test]
+fn struct_literal_and_field_access() {
+    let mut parser = parser("Point { x: 1, y: a }.x");
+    let expression = parser.expression(0);
+
+    let expected: Expression = ExpressionKind::Field(
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Struct(StructLiteral::new(
+                "Point",
+                vec![
+                    FieldValue::new(
+                        "x",
+                        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(1))))
+                            .into(),
+                    ),
+                    FieldValue::new(
+                        "y",
+                        ExpressionKind::Value(Value::Variable(String::from("a"))).into(),
+                    ),
+                ],
+            )))
+            .into(),
+        ),
+        String::from("x"),
+    )
+    .into();
+
+    assert_eq!(expression.unwrap(), expected);
+}
+
+#[test]
+fn conditions_are_not_struct_literals() {
+    let mut parser = parser("if p { 5 }");
+    let if_expression = parser.if_expression();
+
+    let expected = IfExpression::new(
+        ExpressionKind::Value(Value::Variable(String::from("p"))).into(),
+        Block::new(vec![Statement::Expression(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(5)))).into(),
+        )]),
+        ElseExpression::None,
+    );
+
+    assert_eq!(if_expression.unwrap(), expected);
+}
+
+#[test]
+fn array_type() {
+    let mut parser = parser("[[int; 3]; 2]");
+
+    assert_eq!(
+        parser.type_decl().unwrap(),
+        Type::Array(Box::new(Type::Array(Box::new(Type::Int), 3)), 2)
+    );
+}
+
+#[test]
+fn array_literal_and_index() {
+    let mut parser = parser("[1, x][i] = 2");
+    let expression = parser.expression(0);
+
+    let expected: Expression = ExpressionKind::Assignment(
+        ExpressionContainer::new(
+            ExpressionKind::Index(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Array(vec![
+                        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(1))))
+                            .into(),
+                        ExpressionKind::Value(Value::Variable(String::from("x"))).into(),
+                    ]))
+                    .into(),
+                ),
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Variable(String::from("i"))).into(),
+                ),
+            )
+            .into(),
+        ),
+        ExpressionContainer::new(
+            ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(2)))).into(),
+        ),
+    )
+    .into();
+
+    assert_eq!(expression.unwrap(), expected);
+}
+
+#[test]
+fn enum_decl() {
+    let mut parser = parser("enum Shape { Circle(double), Rect(int, int), Empty }");
+
+    assert_eq!(
+        parser.enum_decl().unwrap(),
+        EnumDecl::new(
+            "Shape",
+            vec![
+                VariantDecl::new("Circle", vec![Type::Double]),
+                VariantDecl::new("Rect", vec![Type::Int, Type::Int]),
+                VariantDecl::new("Empty", Vec::new()),
+            ]
+        )
+    );
+}
+
+#[test]
+fn match_expression() {
+    let mut parser = parser("match shape { Shape::Rect(w, _) => { w } 0 => 1, x => Shape::Empty }");
+    let expression = parser.expression(0);
+
+    let variable = |name: &str| -> Expression {
+        ExpressionKind::Value(Value::Variable(String::from(name))).into()
+    };
+    let expected: Expression =
+        ExpressionKind::Match(MatchExpressionContainer::new(MatchExpression::new(
+            variable("shape"),
+            vec![
+                MatchArm::new(
+                    PatternKind::Variant(
+                        String::from("Shape"),
+                        String::from("Rect"),
+                        vec![
+                            PatternKind::Binding(String::from("w")).into(),
+                            PatternKind::Wildcard.into(),
+                        ],
+                    )
+                    .into(),
+                    ExpressionKind::Block(Block::new(vec![Statement::Expression(variable("w"))]))
+                        .into(),
+                ),
+                MatchArm::new(
+                    PatternKind::Literal(Literal::Number(Number::Int(0))).into(),
+                    ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(1)))).into(),
+                ),
+                MatchArm::new(
+                    PatternKind::Binding(String::from("x")).into(),
+                    ExpressionKind::Value(Value::Variant(VariantLiteral::new(
+                        "Shape",
+                        "Empty",
+                        Vec::new(),
+                    )))
+                    .into(),
+                ),
+            ],
+        )))
+        .into();
+
+    assert_eq!(expression.unwrap(), expected);
+}
+
+#[test]
+fn class_decl() {
+    let mut parser = parser(
+        "class Counter { count: int, fn new() { 0 } fn get(self) -> int { 1 } fn add(mut self, n: int) {} }",
+    );
+
+    let int = |value: isize| -> Expression {
+        ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(value)))).into()
+    };
+    assert_eq!(
+        parser.class_decl().unwrap(),
+        ClassDecl::new(
+            "Counter",
+            vec![ArgDecl::new("count", Type::Int)],
+            Some(FuncDecl::new(
+                "new",
+                Vec::new(),
+                Type::UserDefined(String::from("Counter")),
+                Block::new(vec![Statement::Expression(int(0))]),
+            )),
+            vec![
+                MethodDecl::new(
+                    FuncDecl::new(
+                        "get",
+                        Vec::new(),
+                        Type::Int,
+                        Block::new(vec![Statement::Expression(int(1))]),
+                    ),
+                    false,
+                ),
+                MethodDecl::new(
+                    FuncDecl::new(
+                        "add",
+                        vec![ArgDecl::new("n", Type::Int)],
+                        Type::Void,
+                        Block::new(vec![Statement::Empty]),
+                    ),
+                    true,
+                ),
+            ],
+        )
+    );
+}
+
+#[test]
+fn methods_need_self() {
+    let mut parser = parser("class Counter { fn get() -> int { 1 } }");
+
+    assert!(parser.class_decl().is_err());
+}
+
+#[test]
+fn method_call() {
+    let mut parser = parser("self.counter.add(1, x)");
+    let expression = parser.expression(0);
+
+    let expected: Expression = ExpressionKind::MethodCall(
+        ExpressionContainer::new(
+            ExpressionKind::Field(
+                ExpressionContainer::new(
+                    ExpressionKind::Value(Value::Variable(String::from("self"))).into(),
+                ),
+                String::from("counter"),
+            )
+            .into(),
+        ),
+        FunctionCall::new(
+            "add",
+            vec![
+                ExpressionKind::Value(Value::Literal(Literal::Number(Number::Int(1)))).into(),
+                ExpressionKind::Value(Value::Variable(String::from("x"))).into(),
+            ],
+        ),
+    )
+    .into();
+
+    assert_eq!(expression.unwrap(), expected);
+}
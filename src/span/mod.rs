@@ -0,0 +1,135 @@
+//! This module contains the types used for keeping track of where in the
+//! source files things come from.
+
+use std::fmt;
+use std::ops::Range;
+
+#[cfg(test)]
+mod test;
+
+/// Identifies a file in a [`SourceMap`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FileId(usize);
+
+/// A range of bytes in a source file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// The file the span is in
+    pub file: FileId,
+    /// Byte offset of the first character
+    pub start: usize,
+    /// Byte offset after the last character
+    pub end: usize,
+}
+
+impl Span {
+    /// Creates a new span covering `range` in `file`
+    pub fn new(file: FileId, range: Range<usize>) -> Self {
+        Self {
+            file,
+            start: range.start,
+            end: range.end,
+        }
+    }
+
+    /// Creates a span starting at the start of this span, and ending at the end of `other`
+    pub fn to(self, other: Span) -> Self {
+        Self {
+            file: self.file,
+            start: self.start,
+            end: other.end.max(self.end),
+        }
+    }
+
+    /// The range of bytes covered by the span
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// A single source file, along with the information needed for
+/// converting byte offsets into lines and columns
+pub struct SourceFile {
+    name: String,
+    source: String,
+    /// Byte offset of the start of every line
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    /// Creates a new source file
+    pub fn new<N: Into<String>, S: Into<String>>(name: N, source: S) -> Self {
+        let source = source.into();
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            name: name.into(),
+            source,
+            line_starts,
+        }
+    }
+
+    /// Name of the file, usually the path it was read from
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The content of the file
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Converts a byte offset into a (line, column) tuple, both starting from 1
+    /// Offsets at the end of a line, i.e. pointing at the line ending, belong to that line.
+    pub fn line_and_column(&self, offset: usize) -> (usize, usize) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        (line + 1, offset - self.line_starts[line] + 1)
+    }
+
+    /// Returns the content of the line, starting from 1, without the line ending
+    pub fn line(&self, line: usize) -> &str {
+        let start = match self.line_starts.get(line.saturating_sub(1)) {
+            Some(start) => *start,
+            None => return "",
+        };
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.source.len(), |next| next - 1);
+        self.source[start..end].trim_end_matches('\r')
+    }
+}
+
+/// Collection of all the source files in a compilation
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// Creates an empty source map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file to the map, returning the id used for referring to it in spans
+    pub fn add<N: Into<String>, S: Into<String>>(&mut self, name: N, source: S) -> FileId {
+        self.files.push(SourceFile::new(name, source));
+        FileId(self.files.len() - 1)
+    }
+
+    /// Gets the file with the given id
+    pub fn get(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file.0)
+    }
+}
@@ -0,0 +1,54 @@
+use super::*;
+
+#[test]
+fn source_map_add_and_get() {
+    // Given
+    let mut source_map = SourceMap::new();
+
+    // When
+    let first = source_map.add("first.poop", "fn main() {}");
+    let second = source_map.add("second.poop", "let x = 5;");
+
+    // Then
+    assert_ne!(first, second);
+    assert_eq!(source_map.get(first).unwrap().name(), "first.poop");
+    assert_eq!(source_map.get(second).unwrap().source(), "let x = 5;");
+}
+
+#[test]
+fn line_and_column_second_line() {
+    // Given
+    let file = SourceFile::new("test.poop", "let x = 5;\nlet y = x;");
+
+    // When
+    let line_and_column = file.line_and_column(15);
+
+    // Then
+    assert_eq!(line_and_column, (2, 5));
+    assert_eq!(file.line(2), "let y = x;");
+}
+
+#[test]
+fn span_to() {
+    // Given
+    let start = Span::new(FileId::default(), 2..4);
+    let end = Span::new(FileId::default(), 8..12);
+
+    // When
+    let span = start.to(end);
+
+    // Then
+    assert_eq!(span.range(), 2..12);
+}
+
+#[test]
+fn line_and_column_end_of_line() {
+    // Given
+    let file = SourceFile::new("test.poop", "let x = 5\nlet y = x;");
+
+    // When
+    let line_and_column = file.line_and_column(9);
+
+    // Then
+    assert_eq!(line_and_column, (1, 10));
+}
@@ -0,0 +1,29 @@
+use super::CodegenError;
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// Links the object files into an executable at `output`
+/// using the system C compiler as the linker driver, so the C runtime and libc are included.
+///
+/// The linker can be overridden by setting the `CC` environment variable.
+pub fn link(objects: &[&Path], output: &Path) -> Result<(), CodegenError> {
+    let linker = env::var("CC").unwrap_or_else(|_| String::from("cc"));
+    let status = Command::new(&linker)
+        .args(objects)
+        .arg("-o")
+        .arg(output)
+        .status()
+        .map_err(|err| {
+            CodegenError::error(format!("Could not run the linker '{}': {}", linker, err))
+        })?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(CodegenError::error(format!(
+            "The linker '{}' failed with {}",
+            linker, status
+        )))
+    }
+}
@@ -0,0 +1,139 @@
+//! This module takes care of compiling the llvm IR into native object files
+//! and assembly for the host machine, which can then be linked into a
+//! standalone executable using [`link`].
+
+use llvm::core::*;
+use llvm::prelude::*;
+use llvm::target::*;
+use llvm::target_machine::*;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::path::Path;
+use std::ptr;
+
+use super::codegen::{error::CodegenError, module::Module, optimization::OptimizationLevel};
+
+mod linker;
+
+pub use linker::link;
+
+/// The kinds of files the target machine can emit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileType {
+    /// Native object file (`.o`)
+    Object,
+    /// Native assembly (`.s`)
+    Assembly,
+}
+
+impl FileType {
+    fn to_llvm(self) -> LLVMCodeGenFileType {
+        match self {
+            FileType::Object => LLVMCodeGenFileType::LLVMObjectFile,
+            FileType::Assembly => LLVMCodeGenFileType::LLVMAssemblyFile,
+        }
+    }
+}
+
+/// Wrapper around an LLVM target machine for the host triple
+pub struct TargetMachine {
+    machine: LLVMTargetMachineRef,
+    triple: CString,
+}
+
+impl TargetMachine {
+    /// Creates a target machine for the host triple, generating code at the given optimization level.
+    /// The generic CPU is used, so the output can run on any machine with the same triple.
+    pub fn host(optimization: OptimizationLevel) -> Result<Self, CodegenError> {
+        unsafe {
+            LLVM_InitializeNativeTarget();
+            LLVM_InitializeNativeAsmPrinter();
+
+            let triple_ptr = LLVMGetDefaultTargetTriple();
+            let triple = CStr::from_ptr(triple_ptr).to_owned();
+            LLVMDisposeMessage(triple_ptr);
+
+            let mut target = ptr::null_mut();
+            let mut message = ptr::null_mut();
+            if LLVMGetTargetFromTriple(triple.as_ptr(), &mut target, &mut message) != 0 {
+                return Err(CodegenError::error(take_message(message)));
+            }
+
+            let cpu = CString::new("generic").unwrap();
+            let features = CString::new("").unwrap();
+            let machine = LLVMCreateTargetMachine(
+                target,
+                triple.as_ptr(),
+                cpu.as_ptr(),
+                features.as_ptr(),
+                optimization.codegen_level(),
+                LLVMRelocMode::LLVMRelocPIC,
+                LLVMCodeModel::LLVMCodeModelDefault,
+            );
+
+            Ok(Self { machine, triple })
+        }
+    }
+
+    /// The target triple of this machine, e.g. `x86_64-pc-linux-gnu`
+    pub fn triple(&self) -> &str {
+        self.triple.to_str().unwrap_or("")
+    }
+
+    /// Compiles the module and writes the result to `path`
+    pub fn emit_to_file(
+        &self,
+        module: &Module,
+        path: &Path,
+        file_type: FileType,
+    ) -> Result<(), CodegenError> {
+        let c_path = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| CodegenError::error("Output path contains a null byte"))?;
+        unsafe {
+            self.prepare(module.module);
+            let mut message = ptr::null_mut();
+            if LLVMTargetMachineEmitToFile(
+                self.machine,
+                module.module,
+                c_path.as_ptr() as *mut c_char,
+                file_type.to_llvm(),
+                &mut message,
+            ) != 0
+            {
+                return Err(CodegenError::error(format!(
+                    "Could not write '{}': {}",
+                    path.display(),
+                    take_message(message)
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the triple and data layout of the module to the ones of this machine,
+    /// which is required before emitting code for it.
+    unsafe fn prepare(&self, module: LLVMModuleRef) {
+        LLVMSetTarget(module, self.triple.as_ptr());
+        let data_layout = LLVMCreateTargetDataLayout(self.machine);
+        LLVMSetModuleDataLayout(module, data_layout);
+        LLVMDisposeTargetData(data_layout);
+    }
+}
+
+impl Drop for TargetMachine {
+    fn drop(&mut self) {
+        unsafe {
+            LLVMDisposeTargetMachine(self.machine);
+        }
+    }
+}
+
+/// Converts an LLVM owned message into a `String` and disposes of it
+unsafe fn take_message(message: *mut c_char) -> String {
+    if message.is_null() {
+        return String::from("Unknown error");
+    }
+    let string = CStr::from_ptr(message).to_string_lossy().into_owned();
+    LLVMDisposeMessage(message);
+    string
+}
@@ -1,30 +1,55 @@
+//! Errors found while checking the types of a program.
+
 #[cfg(feature = "type-system-debug")]
 use backtrace::Backtrace;
 
 use super::Type;
+use crate::diagnostic::{Diagnostic, Label};
+use crate::span::Span;
 use std::fmt;
 
+/// An error found by the type system, reported as a diagnostic
 pub struct TypeSystemError {
     message: String,
     code: TypeSystemErrorCode,
+    span: Option<Span>,
+    labels: Vec<Label>,
+    notes: Vec<String>,
+    /// Caused by another error, so it is not reported itself
+    follow_up: bool,
+    // Boxed to keep the results of every function returning the error small
     #[cfg(feature = "type-system-debug")]
-    backtrace: Backtrace,
+    backtrace: Box<Backtrace>,
 }
 
 impl TypeSystemError {
+    /// Creates an error with the given message and code
     pub fn new<T: Into<String>>(message: T, code: TypeSystemErrorCode) -> Self {
         Self {
             message: message.into(),
             code,
+            span: None,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            follow_up: false,
             #[cfg(feature = "type-system-debug")]
-            backtrace: Backtrace::new_unresolved(),
+            backtrace: Box::new(Backtrace::new_unresolved()),
         }
     }
 
+    /// An error without a code of its own, only used for code that can not be checked
     pub fn error<T: Into<String>>(message: T) -> Self {
         Self::new(message, TypeSystemErrorCode::E9999)
     }
 
+    /// A variable is used whose value has errors, which are reported instead
+    pub fn follow_up(name: &str) -> Self {
+        let mut error = Self::error(format!("The value of '{}' has errors", name));
+        error.follow_up = true;
+        error
+    }
+
+    /// The value is of none of the expected types
     pub fn type_mismatch(expected: Vec<Type>, found: Type) -> Self {
         Self::new(
             format!("Expected types: {:?}, found: {}", expected, found),
@@ -32,13 +57,323 @@ impl TypeSystemError {
         )
     }
 
+    /// No variable with the name is declared
+    pub fn unknown_variable(name: &str) -> Self {
+        Self::new(
+            format!("Unknown variable '{}'", name),
+            TypeSystemErrorCode::E0002,
+        )
+    }
+
+    /// No function with the name is declared
+    pub fn unknown_function(name: &str) -> Self {
+        Self::new(
+            format!("Unknown function '{}'", name),
+            TypeSystemErrorCode::E0002,
+        )
+    }
+
+    /// A function is called with the wrong number of arguments
+    pub fn wrong_number_of_arguments(name: &str, expected: usize, found: usize) -> Self {
+        Self::new(
+            format!(
+                "Function '{}' takes {} arguments, but {} were given",
+                name, expected, found
+            ),
+            TypeSystemErrorCode::E0003,
+        )
+    }
+
+    /// An immutable variable is assigned to
+    pub fn immutable_variable(name: &str) -> Self {
+        Self::new(
+            format!("Can not assign twice to immutable variable '{}'", name),
+            TypeSystemErrorCode::E0005,
+        )
+    }
+
+    /// A function is declared more than once
+    pub fn duplicate_function(name: &str) -> Self {
+        Self::new(
+            format!("Function '{}' is defined more than once", name),
+            TypeSystemErrorCode::E0006,
+        )
+    }
+
+    /// A global variable is declared more than once
+    pub fn duplicate_global(name: &str) -> Self {
+        Self::new(
+            format!("Global variable '{}' is defined more than once", name),
+            TypeSystemErrorCode::E0006,
+        )
+    }
+
+    /// Only arrays can be indexed
+    pub fn expected_array(found: &Type) -> Self {
+        Self::new(
+            format!("Expected an array, found: {}", found),
+            TypeSystemErrorCode::E0001,
+        )
+    }
+
+    /// A struct, class or enum is declared more than once
+    pub fn duplicate_type(name: &str) -> Self {
+        Self::new(
+            format!("Type '{}' is defined more than once", name),
+            TypeSystemErrorCode::E0006,
+        )
+    }
+
+    /// An enum declares the same variant more than once
+    pub fn duplicate_variant(name: &str) -> Self {
+        Self::new(
+            format!("Variant '{}' is defined more than once", name),
+            TypeSystemErrorCode::E0006,
+        )
+    }
+
+    /// A pattern binds the same name more than once
+    pub fn duplicate_binding(name: &str) -> Self {
+        Self::new(
+            format!(
+                "Binding '{}' is used more than once in the same pattern",
+                name
+            ),
+            TypeSystemErrorCode::E0006,
+        )
+    }
+
+    /// A struct literal gives the same field more than once
+    pub fn duplicate_field(name: &str) -> Self {
+        Self::new(
+            format!("Field '{}' is given more than once", name),
+            TypeSystemErrorCode::E0006,
+        )
+    }
+
+    /// No struct with the name is declared
+    pub fn unknown_struct(name: &str) -> Self {
+        Self::new(
+            format!("Unknown struct '{}'", name),
+            TypeSystemErrorCode::E0002,
+        )
+    }
+
+    /// No enum with the name is declared
+    pub fn unknown_enum(name: &str) -> Self {
+        Self::new(
+            format!("Unknown enum '{}'", name),
+            TypeSystemErrorCode::E0002,
+        )
+    }
+
+    /// The enum has no variant with the name
+    pub fn unknown_variant(enum_name: &str, name: &str) -> Self {
+        Self::new(
+            format!("Enum '{}' has no variant '{}'", enum_name, name),
+            TypeSystemErrorCode::E0002,
+        )
+    }
+
+    /// A variant is given the wrong number of values
+    pub fn wrong_number_of_values(
+        enum_name: &str,
+        name: &str,
+        expected: usize,
+        found: usize,
+    ) -> Self {
+        Self::new(
+            format!(
+                "Variant '{}::{}' holds {} values, but {} were given",
+                enum_name, name, expected, found
+            ),
+            TypeSystemErrorCode::E0003,
+        )
+    }
+
+    /// A match does not match every value, e.g. the variant `missing`
+    pub fn non_exhaustive(missing: &str) -> Self {
+        Self::new(
+            format!("Match is not exhaustive, `{}` is not matched", missing),
+            TypeSystemErrorCode::E0009,
+        )
+    }
+
+    /// A match arm can never be reached
+    pub fn unreachable_arm() -> Self {
+        Self::new(
+            "Match arm is unreachable, as the arms before it match all its values",
+            TypeSystemErrorCode::E0010,
+        )
+    }
+
+    /// The type has no field with the name
+    pub fn unknown_field(found: &Type, name: &str) -> Self {
+        Self::new(
+            format!("Type '{}' has no field '{}'", found, name),
+            TypeSystemErrorCode::E0002,
+        )
+    }
+
+    /// The type has no method with the name
+    pub fn unknown_method(found: &Type, name: &str) -> Self {
+        Self::new(
+            format!("Type '{}' has no method '{}'", found, name),
+            TypeSystemErrorCode::E0002,
+        )
+    }
+
+    /// A struct literal does not give every field
+    pub fn missing_fields(name: &str, fields: &[&str]) -> Self {
+        Self::new(
+            format!(
+                "Missing fields in '{}': {}",
+                name,
+                fields
+                    .iter()
+                    .map(|field| format!("'{}'", field))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            TypeSystemErrorCode::E0008,
+        )
+    }
+
+    /// The value of a constant is not known at compile time
+    pub fn not_constant() -> Self {
+        Self::new(
+            "Constants can only be computed from literals and other constants",
+            TypeSystemErrorCode::E0007,
+        )
+    }
+
+    /// `keyword` is used outside of a loop
+    pub fn outside_loop(keyword: &str) -> Self {
+        Self::new(
+            format!("`{}` can only be used inside a loop", keyword),
+            TypeSystemErrorCode::E0004,
+        )
+    }
+
+    /// `return` is used outside of a function
+    pub fn outside_function() -> Self {
+        Self::new(
+            "`return` can only be used inside a function",
+            TypeSystemErrorCode::E0004,
+        )
+    }
+
+    /// The program has no main function
+    pub fn missing_main() -> Self {
+        Self::new("There must be a main function", TypeSystemErrorCode::E0011)
+    }
+
+    /// The main function has a signature the program can not be started with
+    pub fn invalid_main() -> Self {
+        Self::new(
+            "The program can not be started with this main function",
+            TypeSystemErrorCode::E0017,
+        )
+    }
+
+    /// A struct or enum contains itself
+    pub fn infinite_size(kind: &str, name: &str) -> Self {
+        Self::new(
+            format!(
+                "{} '{}' contains itself, so it would have an infinite size",
+                kind, name
+            ),
+            TypeSystemErrorCode::E0012,
+        )
+    }
+
+    /// Arrays can not hold values of the type
+    pub fn invalid_element_type(element_type: &Type) -> Self {
+        Self::new(
+            format!("Arrays can not contain '{}'", element_type),
+            TypeSystemErrorCode::E0013,
+        )
+    }
+
+    /// Variables can not hold values of the type
+    pub fn invalid_variable_type(name: &str, var_type: &Type) -> Self {
+        Self::new(
+            format!("Variable '{}' can not be {}", name, var_type),
+            TypeSystemErrorCode::E0013,
+        )
+    }
+
+    /// `break` with a value outside of `loop`
+    pub fn break_value_outside_loop() -> Self {
+        Self::new(
+            "Only `loop` can break with a value",
+            TypeSystemErrorCode::E0014,
+        )
+    }
+
+    /// An array literal without any elements, whose type is unknown
+    pub fn empty_array() -> Self {
+        Self::new(
+            "Array literals need at least one element",
+            TypeSystemErrorCode::E0015,
+        )
+    }
+
+    /// Something other than a variable, field or element is assigned to
+    pub fn not_assignable() -> Self {
+        Self::new(
+            "Only variables, their fields and elements can be assigned to",
+            TypeSystemErrorCode::E0016,
+        )
+    }
+
+    /// Sets the span of the code causing the error
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Adds a secondary label pointing at related code
+    pub fn with_label<T: Into<String>>(mut self, span: Span, message: T) -> Self {
+        self.labels.push(Label::secondary(span, message));
+        self
+    }
+
+    /// Adds a note explaining the error
+    pub fn with_note<T: Into<String>>(mut self, note: T) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// The message describing the error
     pub fn message(&self) -> &str {
         &self.message
     }
 
+    /// The code identifying the kind of error
     pub fn code(&self) -> &TypeSystemErrorCode {
         &self.code
     }
+
+    /// The span of the code causing the error, if known
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// Whether the error is caused by another error, and should not be reported
+    pub fn is_follow_up(&self) -> bool {
+        self.follow_up
+    }
+}
+
+impl From<&TypeSystemError> for Diagnostic {
+    fn from(error: &TypeSystemError) -> Self {
+        Diagnostic::error(error.message.as_str())
+            .with_code(format!("{:?}", error.code))
+            .with_labels(error.span.map(|span| Label::primary(span, "")))
+            .with_labels(error.labels.iter().cloned())
+            .with_notes(error.notes.iter().cloned())
+    }
 }
 
 impl fmt::Debug for TypeSystemError {
@@ -54,10 +389,43 @@ impl fmt::Debug for TypeSystemError {
     }
 }
 
+/// Codes identifying the kinds of type errors, so they can be looked up
 #[derive(Debug)]
 pub enum TypeSystemErrorCode {
     /// Type mismatch
     E0001,
-    /// Lazy developer
+    /// Unknown identifier
+    E0002,
+    /// Wrong number of arguments
+    E0003,
+    /// Break or continue outside of a loop, or return outside of a function
+    E0004,
+    /// Assignment to an immutable variable
+    E0005,
+    /// Function, global variable, type, field, variant or binding defined more than once
+    E0006,
+    /// Constant value not known at compile time
+    E0007,
+    /// Struct literal without a value for every field
+    E0008,
+    /// Match without an arm for every value
+    E0009,
+    /// Match arm that can never be reached
+    E0010,
+    /// Program without a main function
+    E0011,
+    /// Struct, class or enum containing itself
+    E0012,
+    /// Variable or array element of a type without values, like `void`
+    E0013,
+    /// Break with a value out of a loop other than `loop`
+    E0014,
+    /// Array literal without elements, so its type is unknown
+    E0015,
+    /// Assignment to something other than a variable, field or element
+    E0016,
+    /// Main function with a signature the program can not be started with
+    E0017,
+    /// Code with syntax errors or using variables with errors, which has no type
     E9999,
 }
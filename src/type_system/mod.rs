@@ -1,115 +1,1110 @@
+//! Type checking of the AST.
+//!
+//! The checker first registers the user defined types and the signatures of all functions,
+//! then walks the program
+//! in order, keeping a stack of scopes with the types of the variables.
+//! Errors are collected, so several of them can be reported at once.
+//!
+//! The inferred types of variables, if and match expressions are stored in the AST,
+//! which the codegen uses to pick the types of stack slots and phi nodes.
+
 use crate::parser::ast::*;
+use crate::span::Span;
+use std::collections::HashMap;
+
+pub mod error;
+mod patterns;
 
-mod error;
+#[cfg(test)]
+mod test;
 
+/// Short hand type of result that returns a `TypeSystemError`
 pub type Output<Out = ()> = Result<Out, error::TypeSystemError>;
 
-pub struct TypeSystem {}
+/// The signature of a function, used for checking calls
+#[derive(Clone)]
+struct Signature {
+    args: Vec<Type>,
+    /// Where each argument is declared
+    arg_spans: Vec<Span>,
+    return_type: Type,
+    variadic: bool,
+    /// Whether the function is a method taking `mut self`
+    mutable_self: bool,
+    span: Span,
+}
+
+impl Signature {
+    /// Whether the program can be started by calling the function as `main`,
+    /// which is given the arguments of the program like in C
+    fn can_be_main(&self) -> bool {
+        let args = match self.args.as_slice() {
+            [] => true,
+            [Type::Int, Type::Array(element_type, _)] => **element_type == Type::String,
+            _ => false,
+        };
+        let returns = matches!(self.return_type, Type::Int | Type::Void);
+        args && returns && !self.variadic
+    }
+}
+
+/// A struct or class type, used for checking literals and field accesses
+struct StructType {
+    fields: Vec<Field>,
+    span: Span,
+}
+
+/// A field of a struct
+struct Field {
+    name: String,
+    field_type: Type,
+    span: Span,
+}
+
+/// An enum type, used for checking variants and patterns
+struct EnumType {
+    variants: Vec<VariantType>,
+    span: Span,
+}
+
+/// A variant of an enum
+struct VariantType {
+    name: String,
+    fields: Vec<Type>,
+    span: Span,
+}
+
+/// A variable in scope
+struct Variable {
+    var_type: Type,
+    mutable: bool,
+    /// Only constants can be used in the values of other constants
+    constant: bool,
+    /// Where the variable is declared
+    span: Span,
+}
+
+/// A loop being checked, used for `break` and `continue`
+struct LoopContext {
+    /// Only `loop` can result in a value
+    accepts_value: bool,
+    /// Type and location of the first `break`
+    break_type: Option<(Type, Span)>,
+}
+
+/// Checks the types of a program, inferring the types left out and reporting errors
+pub struct TypeSystem {
+    functions: HashMap<String, Signature>,
+    structs: HashMap<String, StructType>,
+    enums: HashMap<String, EnumType>,
+    scopes: Vec<HashMap<String, Variable>>,
+    /// Variables of the blocks that have ended in the current function,
+    /// used to explain why they are unknown
+    ended: HashMap<String, Span>,
+    loops: Vec<LoopContext>,
+    /// Return type and span of the function being checked
+    return_type: Option<(Type, Span)>,
+    errors: Vec<error::TypeSystemError>,
+}
 
 impl TypeSystem {
-    pub fn analyze(_ast: &Program) {
-        unimplemented!();
-    }
-
-    fn statement(&mut self, _statement: &Statement) -> Output<Type> {
-        unimplemented!();
-    }
-
-    fn expression(&mut self, expr: &Expression) -> Output<Type> {
-        match expr {
-            Expression::Addition(left, right)
-            | Expression::Subtraction(left, right)
-            | Expression::Multiplication(left, right)
-            | Expression::Division(left, right)
-            | Expression::Modulus(left, right) => match self.expression(&left)? {
-                left @ Type::Int | left @ Type::Float | left @ Type::Double => {
-                    match self.expression(&right)? {
-                        right @ Type::Int | right @ Type::Float | right @ Type::Double => {
-                            if left == right {
-                                Ok(left)
-                            } else {
-                                Err(error::TypeSystemError::error(format!(
-                                    "Type mismatch. Left: {}, Right: {}",
-                                    left, right
-                                )))
-                            }
-                        }
-                        right => Err(error::TypeSystemError::type_mismatch(
-                            vec![Type::Int, Type::Float, Type::Double],
-                            right,
-                        )),
+    /// Checks the types of the whole program, returning all the errors found
+    pub fn analyze(ast: &mut Program) -> Result<(), Vec<error::TypeSystemError>> {
+        let mut type_system = Self {
+            functions: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            // The outermost scope holds the global variables
+            scopes: vec![HashMap::new()],
+            ended: HashMap::new(),
+            loops: Vec::new(),
+            return_type: None,
+            errors: Vec::new(),
+        };
+        // Types are declared first, as any other declaration can use them
+        type_system.declare_types(ast);
+
+        // Then all functions, so they can be called before their definition
+        let mut declared = Vec::new();
+        let mut program = &*ast;
+        while let Program::Decl(decl, rest) = program {
+            match type_system.declare(decl) {
+                Ok(()) => declared.push(true),
+                Err(error) => {
+                    type_system.errors.push(error);
+                    declared.push(false);
+                }
+            }
+            program = rest;
+        }
+
+        // Then the global variables, so they can be used in any function
+        for &globals in [true, false].iter() {
+            let mut program = &mut *ast;
+            let mut declared = declared.iter();
+            while let Program::Decl(decl, rest) = program {
+                let global = matches!(decl, Decl::VarDecl(_) | Decl::ConstDecl(_));
+                if declared.next() == Some(&true) && global == globals {
+                    if let Err(error) = type_system.decl(decl) {
+                        type_system.errors.push(error);
                     }
                 }
-                left => Err(error::TypeSystemError::type_mismatch(
+                program = rest;
+            }
+        }
+
+        match type_system.functions.get("main") {
+            None => type_system
+                .errors
+                .push(error::TypeSystemError::missing_main()),
+            Some(main) if !main.can_be_main() => {
+                let error = error::TypeSystemError::invalid_main()
+                    .with_span(main.span)
+                    .with_note(
+                        "main takes no arguments or `argc: int, argv: [string; N]`, \
+                        and returns `int` or nothing",
+                    );
+                type_system.errors.push(error)
+            }
+            Some(_) => (),
+        }
+
+        // Only the errors causing them are reported
+        type_system.errors.retain(|error| !error.is_follow_up());
+        if type_system.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(type_system.errors)
+        }
+    }
+
+    fn decl(&mut self, decl: &mut Decl) -> Output {
+        match decl {
+            Decl::FuncDecl(func_decl) => self.func_decl(func_decl, None),
+            Decl::ClassDecl(class_decl) => {
+                if let Some(constructor) = &mut class_decl.constructor {
+                    if let Err(error) = self.func_decl(constructor, None) {
+                        self.errors.push(error);
+                    }
+                }
+                for method in class_decl.methods.iter_mut() {
+                    let receiver = Variable {
+                        var_type: Type::UserDefined(class_decl.name.clone()),
+                        mutable: method.mutable,
+                        constant: false,
+                        span: method.func_decl.span,
+                    };
+                    if let Err(error) = self.func_decl(&mut method.func_decl, Some(receiver)) {
+                        self.errors.push(error);
+                    }
+                }
+                Ok(())
+            }
+            Decl::ExternDecl(_) | Decl::StructDecl(_) | Decl::EnumDecl(_) => Ok(()),
+            Decl::VarDecl(var_decl) => self.global(var_decl, false),
+            Decl::ConstDecl(var_decl) => self.global(var_decl, true),
+            Decl::Error(span) => Err(error::TypeSystemError::error(
+                "Declarations with syntax errors have no type",
+            )
+            .with_span(*span)),
+        }
+    }
+
+    /// Registers all structs, classes and enums, checking the types of their fields
+    fn declare_types(&mut self, ast: &Program) {
+        let mut struct_decls = Vec::new();
+        let mut enum_decls = Vec::new();
+        let mut program = ast;
+        while let Program::Decl(decl, rest) = program {
+            // The fields are added once all the names are known
+            match decl {
+                Decl::StructDecl(struct_decl)
+                    if self.new_type(&struct_decl.name, struct_decl.span) =>
+                {
+                    let struct_type = StructType {
+                        fields: Vec::new(),
+                        span: struct_decl.span,
+                    };
+                    self.structs.insert(struct_decl.name.clone(), struct_type);
+                    struct_decls.push((
+                        "Struct",
+                        &struct_decl.name,
+                        &struct_decl.fields,
+                        struct_decl.span,
+                    ));
+                }
+                // The objects of a class are structs
+                Decl::ClassDecl(class_decl) if self.new_type(&class_decl.name, class_decl.span) => {
+                    let struct_type = StructType {
+                        fields: Vec::new(),
+                        span: class_decl.span,
+                    };
+                    self.structs.insert(class_decl.name.clone(), struct_type);
+                    struct_decls.push((
+                        "Class",
+                        &class_decl.name,
+                        &class_decl.fields,
+                        class_decl.span,
+                    ));
+                }
+                Decl::EnumDecl(enum_decl) if self.new_type(&enum_decl.name, enum_decl.span) => {
+                    let enum_type = EnumType {
+                        variants: Vec::new(),
+                        span: enum_decl.span,
+                    };
+                    self.enums.insert(enum_decl.name.clone(), enum_type);
+                    enum_decls.push(enum_decl);
+                }
+                _ => (),
+            }
+            program = rest;
+        }
+
+        for (_, name, field_decls, _) in struct_decls.iter() {
+            let mut fields: Vec<Field> = Vec::new();
+            for field in field_decls.iter() {
+                if let Some(existing) = fields.iter().find(|other| other.name == field.name) {
+                    self.errors.push(
+                        error::TypeSystemError::duplicate_field(&field.name)
+                            .with_span(field.span)
+                            .with_label(existing.span, "first defined here"),
+                    );
+                    continue;
+                }
+                if let Err(error) = self.check_type(&field.arg_type, field.span) {
+                    self.errors.push(error);
+                }
+                fields.push(Field {
+                    name: field.name.clone(),
+                    field_type: field.arg_type.clone(),
+                    span: field.span,
+                });
+            }
+            if let Some(struct_type) = self.structs.get_mut(*name) {
+                struct_type.fields = fields;
+            }
+        }
+
+        for enum_decl in enum_decls.iter() {
+            let mut variants: Vec<VariantType> = Vec::new();
+            for variant in enum_decl.variants.iter() {
+                if let Some(existing) = variants.iter().find(|other| other.name == variant.name) {
+                    self.errors.push(
+                        error::TypeSystemError::duplicate_variant(&variant.name)
+                            .with_span(variant.span)
+                            .with_label(existing.span, "first defined here"),
+                    );
+                    continue;
+                }
+                for field_type in variant.fields.iter() {
+                    if let Err(error) = self.check_type(field_type, variant.span) {
+                        self.errors.push(error);
+                    }
+                }
+                variants.push(VariantType {
+                    name: variant.name.clone(),
+                    fields: variant.fields.clone(),
+                    span: variant.span,
+                });
+            }
+            if let Some(enum_type) = self.enums.get_mut(&enum_decl.name) {
+                enum_type.variants = variants;
+            }
+        }
+
+        let declared = struct_decls
+            .iter()
+            .map(|&(kind, name, _, span)| (kind, name, span))
+            .chain(
+                enum_decls
+                    .iter()
+                    .map(|enum_decl| ("Enum", &enum_decl.name, enum_decl.span)),
+            );
+        for (kind, name, span) in declared {
+            if self.contains(name, name, &mut Vec::new()) {
+                self.errors
+                    .push(error::TypeSystemError::infinite_size(kind, name).with_span(span));
+            }
+        }
+    }
+
+    /// Reports an error if a type named `name` is already defined,
+    /// returning whether the name is free
+    fn new_type(&mut self, name: &str, span: Span) -> bool {
+        let existing = match (self.structs.get(name), self.enums.get(name)) {
+            (Some(struct_type), _) => struct_type.span,
+            (_, Some(enum_type)) => enum_type.span,
+            (None, None) => return true,
+        };
+        self.errors.push(
+            error::TypeSystemError::duplicate_type(name)
+                .with_span(span)
+                .with_label(existing, "first defined here"),
+        );
+        false
+    }
+
+    /// Whether the type `name` contains the type `target` in one of its fields,
+    /// directly or through other types
+    fn contains(&self, name: &str, target: &str, visited: &mut Vec<String>) -> bool {
+        if visited.iter().any(|other| other == name) {
+            return false;
+        }
+        visited.push(name.to_string());
+        self.field_types(name)
+            .into_iter()
+            .any(|field_type| match element_type(field_type) {
+                Type::UserDefined(other)
+                    if self.structs.contains_key(other) || self.enums.contains_key(other) =>
+                {
+                    other == target || self.contains(other, target, visited)
+                }
+                _ => false,
+            })
+    }
+
+    /// The types of all the fields of a struct, or all the values held by the variants of an enum
+    fn field_types(&self, name: &str) -> Vec<&Type> {
+        match (self.structs.get(name), self.enums.get(name)) {
+            (Some(struct_type), _) => struct_type
+                .fields
+                .iter()
+                .map(|field| &field.field_type)
+                .collect(),
+            (_, Some(enum_type)) => enum_type
+                .variants
+                .iter()
+                .flat_map(|variant| variant.fields.iter())
+                .collect(),
+            (None, None) => Vec::new(),
+        }
+    }
+
+    /// Registers the signature of a function, or the constructor and methods of a class,
+    /// checking the types they use
+    fn declare(&mut self, decl: &Decl) -> Output {
+        match decl {
+            Decl::FuncDecl(func_decl) => self.declare_function(
+                &func_decl.name,
+                &func_decl.args,
+                &func_decl.return_type,
+                false,
+                false,
+                func_decl.span,
+            ),
+            Decl::ExternDecl(extern_decl) => self.declare_function(
+                &extern_decl.name,
+                &extern_decl.args,
+                &extern_decl.return_type,
+                extern_decl.variadic,
+                false,
+                extern_decl.span,
+            ),
+            Decl::ClassDecl(class_decl) => {
+                // The constructor is called like a function named after the class
+                if let Some(constructor) = &class_decl.constructor {
+                    let class = Type::UserDefined(class_decl.name.clone());
+                    let result = if constructor.return_type != class {
+                        Err(error::TypeSystemError::type_mismatch(
+                            vec![class],
+                            constructor.return_type.clone(),
+                        )
+                        .with_span(constructor.span)
+                        .with_note("the constructor has to result in an object of its class"))
+                    } else {
+                        self.declare_function(
+                            &class_decl.name,
+                            &constructor.args,
+                            &constructor.return_type,
+                            false,
+                            false,
+                            constructor.span,
+                        )
+                    };
+                    if let Err(error) = result {
+                        self.errors.push(error);
+                    }
+                }
+                for method in class_decl.methods.iter() {
+                    let func_decl = &method.func_decl;
+                    if let Err(error) = self.declare_function(
+                        &ClassDecl::mangle(&class_decl.name, &func_decl.name),
+                        &func_decl.args,
+                        &func_decl.return_type,
+                        false,
+                        method.mutable,
+                        func_decl.span,
+                    ) {
+                        self.errors.push(error);
+                    }
+                }
+                Ok(())
+            }
+            // Checked after all functions are declared
+            Decl::VarDecl(_) | Decl::ConstDecl(_) | Decl::Error(_) => Ok(()),
+            Decl::StructDecl(_) | Decl::EnumDecl(_) => Ok(()),
+        }
+    }
+
+    fn declare_function(
+        &mut self,
+        name: &str,
+        args: &[ArgDecl],
+        return_type: &Type,
+        variadic: bool,
+        mutable_self: bool,
+        span: Span,
+    ) -> Output {
+        for arg in args.iter() {
+            self.check_type(&arg.arg_type, arg.span)?;
+        }
+        self.check_type(return_type, span)?;
+        if let Some(existing) = self.functions.get(name) {
+            return Err(error::TypeSystemError::duplicate_function(name)
+                .with_span(span)
+                .with_label(existing.span, "first defined here"));
+        }
+
+        self.functions.insert(
+            name.to_string(),
+            Signature {
+                args: args.iter().map(|arg| arg.arg_type.clone()).collect(),
+                arg_spans: args.iter().map(|arg| arg.span).collect(),
+                return_type: return_type.clone(),
+                variadic,
+                mutable_self,
+                span,
+            },
+        );
+        Ok(())
+    }
+
+    fn global(&mut self, var_decl: &mut VarDecl, constant: bool) -> Output {
+        if let Some(existing) = self.scopes[0].get(&var_decl.identifier) {
+            return Err(
+                error::TypeSystemError::duplicate_global(&var_decl.identifier)
+                    .with_span(var_decl.span)
+                    .with_label(existing.span, "first defined here"),
+            );
+        }
+        // The constant is declared even when its value is rejected,
+        // so later uses of it don't report it as unknown
+        let not_constant = if constant {
+            self.check_constant(&var_decl.expression).err()
+        } else {
+            None
+        };
+        let result = self.var_decl(var_decl, constant);
+        match not_constant {
+            Some(error) => {
+                if let Err(other) = result {
+                    self.errors.push(other);
+                }
+                Err(error)
+            }
+            None => result,
+        }
+    }
+
+    /// Checks that the value can be computed at compile time, i.e. it only consists of
+    /// literals, operators and other constants
+    fn check_constant(&self, expr: &Expression) -> Output {
+        match &expr.kind {
+            ExpressionKind::Addition(lhs, rhs)
+            | ExpressionKind::Subtraction(lhs, rhs)
+            | ExpressionKind::Multiplication(lhs, rhs)
+            | ExpressionKind::Division(lhs, rhs)
+            | ExpressionKind::Modulus(lhs, rhs)
+            | ExpressionKind::Equality(lhs, rhs)
+            | ExpressionKind::NotEq(lhs, rhs)
+            | ExpressionKind::LessThan(lhs, rhs)
+            | ExpressionKind::GreaterThan(lhs, rhs)
+            | ExpressionKind::LessEq(lhs, rhs)
+            | ExpressionKind::GreaterEq(lhs, rhs)
+            | ExpressionKind::And(lhs, rhs)
+            | ExpressionKind::Or(lhs, rhs) => {
+                self.check_constant(lhs)?;
+                self.check_constant(rhs)
+            }
+            ExpressionKind::Not(expr) | ExpressionKind::Neg(expr) => self.check_constant(expr),
+            ExpressionKind::Value(Value::Literal(_)) => Ok(()),
+            ExpressionKind::Value(Value::Array(elements)) => elements
+                .iter()
+                .try_for_each(|element| self.check_constant(element)),
+            ExpressionKind::Value(Value::Variable(name)) => {
+                let variable = self.lookup(name, expr.span)?;
+                if variable.constant {
+                    Ok(())
+                } else {
+                    Err(error::TypeSystemError::not_constant()
+                        .with_span(expr.span)
+                        .with_label(variable.span, "not a constant"))
+                }
+            }
+            _ => Err(error::TypeSystemError::not_constant().with_span(expr.span)),
+        }
+    }
+
+    /// Checks the body of a function, or of a method when given the object as `receiver`
+    fn func_decl(&mut self, func_decl: &mut FuncDecl, receiver: Option<Variable>) -> Output {
+        self.ended.clear();
+        let mut arguments: HashMap<String, Variable> = func_decl
+            .args
+            .iter()
+            .map(|arg| {
+                let variable = Variable {
+                    var_type: arg.arg_type.clone(),
+                    mutable: false,
+                    constant: false,
+                    span: arg.span,
+                };
+                (arg.name.clone(), variable)
+            })
+            .collect();
+        if let Some(receiver) = receiver {
+            arguments.insert(String::from("self"), receiver);
+        }
+        self.scopes.push(arguments);
+        self.return_type = Some((func_decl.return_type.clone(), func_decl.span));
+        let body_type = self.block(&mut func_decl.body);
+        self.return_type = None;
+        self.scopes.pop();
+
+        let body_type = body_type?;
+        if func_decl.return_type != Type::Void
+            && unify(&body_type, &func_decl.return_type).is_none()
+        {
+            let span = match func_decl.body.last() {
+                Some(statement @ Statement::Expression(_)) => statement.span(),
+                _ => func_decl.body.span,
+            };
+            return Err(error::TypeSystemError::type_mismatch(
+                vec![func_decl.return_type.clone()],
+                body_type,
+            )
+            .with_span(span)
+            .with_label(
+                func_decl.span,
+                format!("the function returns {}", func_decl.return_type),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Only the built in types and declared structs and enums can be used
+    fn check_type(&self, var_type: &Type, span: Span) -> Output {
+        match var_type {
+            Type::UserDefined(name)
+                if !self.structs.contains_key(name) && !self.enums.contains_key(name) =>
+            {
+                Err(error::TypeSystemError::new(
+                    format!("Unknown type '{}'", name),
+                    error::TypeSystemErrorCode::E0002,
+                )
+                .with_span(span))
+            }
+            Type::Array(element_type, _) => match **element_type {
+                Type::Void | Type::Never => {
+                    Err(error::TypeSystemError::invalid_element_type(element_type).with_span(span))
+                }
+                _ => self.check_type(element_type, span),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn statement(&mut self, statement: &mut Statement) -> Output<Type> {
+        match statement {
+            Statement::VarDecl(var_decl) => {
+                self.var_decl(var_decl, false)?;
+                Ok(Type::Void)
+            }
+            Statement::Expression(expr) => self.expression(expr),
+            Statement::Empty => Ok(Type::Void),
+            Statement::Error(span) => Err(error::TypeSystemError::error(
+                "Statements with syntax errors have no type",
+            )
+            .with_span(*span)),
+        }
+    }
+
+    /// Checks the value of the variable and adds it to the innermost scope
+    fn var_decl(&mut self, var_decl: &mut VarDecl, constant: bool) -> Output {
+        let mut annotated = None;
+        let checked = match &var_decl.var_type {
+            Some(var_type) => {
+                // Points at the declaration up to the value, including the annotation
+                let annotation = Span {
+                    end: var_decl.expression.span.start,
+                    ..var_decl.span
+                };
+                match self.check_type(var_type, annotation) {
+                    Ok(()) => {
+                        annotated = Some(var_type.clone());
+                        self.expect(&mut var_decl.expression, var_type.clone(), annotation)
+                    }
+                    Err(error) => Err(error),
+                }
+            }
+            None => self.expression(&mut var_decl.expression),
+        };
+        let result = match checked {
+            Ok(var_type) if var_type == Type::Void || var_type == Type::Never => Err(
+                error::TypeSystemError::invalid_variable_type(&var_decl.identifier, &var_type)
+                    .with_span(var_decl.expression.span),
+            ),
+            checked => checked,
+        };
+        // The variable is declared even when its value is rejected, so uses of it don't
+        // report it as unknown. Without a valid annotation it gets the type `never`,
+        // which no value has, and its uses are not checked.
+        let var_type = match &result {
+            Ok(var_type) => var_type.clone(),
+            Err(_) => annotated
+                .filter(|var_type| *var_type != Type::Void && *var_type != Type::Never)
+                .unwrap_or(Type::Never),
+        };
+        self.scopes
+            .last_mut()
+            .expect("Variables are always inside a scope")
+            .insert(
+                var_decl.identifier.clone(),
+                Variable {
+                    var_type: var_type.clone(),
+                    mutable: var_decl.mutable,
+                    constant,
+                    span: var_decl.span,
+                },
+            );
+        var_decl.var_type = Some(var_type);
+        result.map(|_| ())
+    }
+
+    fn expression(&mut self, expr: &mut Expression) -> Output<Type> {
+        match &mut expr.kind {
+            ExpressionKind::Addition(left, right)
+            | ExpressionKind::Subtraction(left, right)
+            | ExpressionKind::Multiplication(left, right)
+            | ExpressionKind::Division(left, right)
+            | ExpressionKind::Modulus(left, right) => self.numeric_operands(left, right),
+            ExpressionKind::LessThan(left, right)
+            | ExpressionKind::GreaterThan(left, right)
+            | ExpressionKind::LessEq(left, right)
+            | ExpressionKind::GreaterEq(left, right) => {
+                self.numeric_operands(left, right)?;
+                Ok(Type::Boolean)
+            }
+            ExpressionKind::Equality(left, right) | ExpressionKind::NotEq(left, right) => {
+                let left_type = self.expression(left)?;
+                match left_type {
+                    Type::Int | Type::Float | Type::Double | Type::Boolean => {
+                        self.expect(right, left_type, left.span)?;
+                        Ok(Type::Boolean)
+                    }
+                    left_type => Err(error::TypeSystemError::type_mismatch(
+                        vec![Type::Int, Type::Float, Type::Double, Type::Boolean],
+                        left_type,
+                    )
+                    .with_span(left.span)),
+                }
+            }
+            ExpressionKind::And(left, right) | ExpressionKind::Or(left, right) => {
+                self.expect(left, Type::Boolean, expr.span)?;
+                self.expect(right, Type::Boolean, expr.span)?;
+                Ok(Type::Boolean)
+            }
+            ExpressionKind::Not(not) => {
+                self.expect(not, Type::Boolean, expr.span)?;
+                Ok(Type::Boolean)
+            }
+            ExpressionKind::Neg(value) => match self.expression(value)? {
+                value_type @ Type::Int | value_type @ Type::Float | value_type @ Type::Double => {
+                    Ok(value_type)
+                }
+                value_type => Err(error::TypeSystemError::type_mismatch(
                     vec![Type::Int, Type::Float, Type::Double],
-                    left,
-                )),
+                    value_type,
+                )
+                .with_span(value.span)),
             },
-            Expression::Equality(left, right)
-            | Expression::NotEq(left, right)
-            | Expression::LessThan(left, right)
-            | Expression::GreaterThan(left, right)
-            | Expression::LessEq(left, right)
-            | Expression::GreaterEq(left, right)
-            | Expression::And(left, right)
-            | Expression::Or(left, right) => {
-                // This is only for leveraging lazy evaluation
-                if {
-                    match self.expression(&left)? {
-                        Type::Boolean => true,
-                        left_type => {
-                            return Err(error::TypeSystemError::error(format!(
-                                "Type mismatch left hand side. Expected boolean, found: {}",
-                                left_type
-                            )))
-                        }
+            ExpressionKind::Field(value, name) => {
+                let value_type = self.expression(value)?;
+                match self.field(&value_type, name) {
+                    Some(field) => Ok(field.field_type.clone()),
+                    None => Err(error::TypeSystemError::unknown_field(&value_type, name)
+                        .with_span(expr.span)),
+                }
+            }
+            ExpressionKind::MethodCall(object, method_call) => {
+                self.method_call(object, method_call)
+            }
+            ExpressionKind::Index(value, index) => {
+                let value_type = self.expression(value)?;
+                self.expect(index, Type::Int, expr.span)?;
+                match value_type {
+                    Type::Array(element_type, _) => Ok(*element_type),
+                    value_type => {
+                        Err(error::TypeSystemError::expected_array(&value_type)
+                            .with_span(value.span))
                     }
-                } && {
-                    match self.expression(&right)? {
-                        Type::Boolean => true,
-                        right_type => {
-                            return Err(error::TypeSystemError::error(format!(
-                                "Type mismatch right hand side. Expected boolean, found: {}",
-                                right_type
-                            )))
-                        }
+                }
+            }
+            ExpressionKind::Assignment(target, value) => self.assignment(target, value),
+            ExpressionKind::CompoundAssignment(_, target, value) => {
+                self.compound_assignment(target, value)
+            }
+            ExpressionKind::If(if_expr) => self.if_expression(if_expr),
+            ExpressionKind::Match(match_expr) => self.match_expression(match_expr),
+            ExpressionKind::While(condition, body) => {
+                self.expect(condition, Type::Boolean, expr.span)?;
+                self.loop_body(body, false)?;
+                Ok(Type::Void)
+            }
+            ExpressionKind::Loop(loop_expr) => {
+                let result_type = self.loop_body(&mut loop_expr.body, true)?;
+                loop_expr.result_type = Some(result_type.clone());
+                Ok(result_type)
+            }
+            ExpressionKind::Break(value) => {
+                let found = match value {
+                    Some(value) => Some((self.expression(value)?, value.span)),
+                    None => None,
+                };
+                self.break_expression(found, expr.span)?;
+                Ok(Type::Never)
+            }
+            ExpressionKind::Continue => {
+                if self.loops.is_empty() {
+                    return Err(
+                        error::TypeSystemError::outside_loop("continue").with_span(expr.span)
+                    );
+                }
+                Ok(Type::Never)
+            }
+            ExpressionKind::Return(value) => {
+                let (expected, function) = match self.return_type.clone() {
+                    Some(return_type) => return_type,
+                    None => {
+                        return Err(error::TypeSystemError::outside_function().with_span(expr.span))
                     }
-                } {
-                    Ok(Type::Boolean)
-                } else {
-                    // This should never happen.
-                    // Only here to satisfy compiler
-                    Err(error::TypeSystemError::error(
-                        "Something went horribly wrong",
-                    ))
+                };
+                let (found, span) = match value {
+                    Some(value) => (self.expression(value)?, value.span),
+                    None => (Type::Void, expr.span),
+                };
+                if unify(&found, &expected).is_none() {
+                    return Err(error::TypeSystemError::type_mismatch(
+                        vec![expected.clone()],
+                        found,
+                    )
+                    .with_span(span)
+                    .with_label(function, format!("the function returns {}", expected)));
                 }
+                Ok(Type::Never)
             }
-            Expression::Not(not) => {
-                let not_type = self.expression(not)?;
-                if let Type::Boolean = not_type {
-                    Ok(Type::Boolean)
-                } else {
-                    Err(error::TypeSystemError::error(format!(
-                        "Type mismatch. Expected boolean, found: {}",
-                        not_type
-                    )))
+            ExpressionKind::Block(block) => self.block(block),
+            ExpressionKind::Value(value) => self.value(value, expr.span),
+            ExpressionKind::Error => Err(error::TypeSystemError::error(
+                "Expressions with syntax errors have no type",
+            )
+            .with_span(expr.span)),
+        }
+    }
+
+    /// Checks that both operands are numbers of the same type, returning that type
+    fn numeric_operands(&mut self, left: &mut Expression, right: &mut Expression) -> Output<Type> {
+        match self.expression(left)? {
+            left_type @ Type::Int | left_type @ Type::Float | left_type @ Type::Double => {
+                self.expect(right, left_type.clone(), left.span)?;
+                Ok(left_type)
+            }
+            left_type => Err(error::TypeSystemError::type_mismatch(
+                vec![Type::Int, Type::Float, Type::Double],
+                left_type,
+            )
+            .with_span(left.span)),
+        }
+    }
+
+    /// Checks that the expression has the expected type.
+    /// `reason` points at the code requiring the type.
+    fn expect(&mut self, expr: &mut Expression, expected: Type, reason: Span) -> Output<Type> {
+        let found = self.expression(expr)?;
+        if let Some(found) = unify(&found, &expected) {
+            Ok(found)
+        } else {
+            Err(
+                error::TypeSystemError::type_mismatch(vec![expected.clone()], found)
+                    .with_span(expr.span)
+                    .with_label(reason, format!("expected {} because of this", expected)),
+            )
+        }
+    }
+
+    fn if_expression(&mut self, if_expr: &mut IfExpression) -> Output<Type> {
+        self.expect(&mut if_expr.condition, Type::Boolean, if_expr.span)?;
+        let body_type = self.block(&mut if_expr.body)?;
+        let (else_type, else_span) = match &mut if_expr.else_expression {
+            ElseExpression::Block(block) => (self.block(block)?, block.span),
+            ElseExpression::IfExpression(else_if) => (self.if_expression(else_if)?, else_if.span),
+            // Without an else branch there is no value when the condition is false
+            ElseExpression::None => (Type::Void, if_expr.span),
+        };
+
+        let result_type = match if_expr.else_expression {
+            ElseExpression::None => Some(Type::Void),
+            _ => unify(&body_type, &else_type),
+        };
+        if let Some(result_type) = result_type {
+            if_expr.result_type = Some(result_type.clone());
+            Ok(result_type)
+        } else {
+            Err(error::TypeSystemError::new(
+                format!(
+                    "If and else have incompatible types. If: {}, else: {}",
+                    body_type, else_type
+                ),
+                error::TypeSystemErrorCode::E0001,
+            )
+            .with_span(else_span)
+            .with_label(if_expr.body.span, format!("this is of type {}", body_type)))
+        }
+    }
+
+    fn match_expression(&mut self, match_expr: &mut MatchExpression) -> Output<Type> {
+        let value_type = self.expression(&mut match_expr.value)?;
+        let mut matrix = patterns::Matrix::new(value_type.clone());
+        // The type of the arms so far, and the first arm deciding it
+        let mut result: Option<(Type, Span)> = None;
+        for arm in match_expr.arms.iter_mut() {
+            let mut bindings = HashMap::new();
+            let pattern = self.pattern(&arm.pattern, &value_type, &mut bindings)?;
+            if !matrix.push(pattern, &self.enums) {
+                self.errors
+                    .push(error::TypeSystemError::unreachable_arm().with_span(arm.pattern.span));
+            }
+
+            self.scopes.push(bindings);
+            let body_type = self.expression(&mut arm.body);
+            if let Some(scope) = self.scopes.pop() {
+                self.ended.extend(
+                    scope
+                        .into_iter()
+                        .map(|(name, variable)| (name, variable.span)),
+                );
+            }
+            let body_type = body_type?;
+
+            result = match result {
+                None => Some((body_type, arm.body.span)),
+                Some((result_type, first)) => match unify(&result_type, &body_type) {
+                    // `never` arms do not decide the type
+                    Some(unified) if result_type == Type::Never => Some((unified, arm.body.span)),
+                    Some(unified) => Some((unified, first)),
+                    None => {
+                        return Err(error::TypeSystemError::type_mismatch(
+                            vec![result_type.clone()],
+                            body_type,
+                        )
+                        .with_span(arm.body.span)
+                        .with_label(first, format!("this is of type {}", result_type)))
+                    }
+                },
+            };
+        }
+
+        if let Some(missing) = matrix.missing(&self.enums) {
+            return Err(error::TypeSystemError::non_exhaustive(&missing)
+                .with_span(match_expr.value.span)
+                .with_note(format!(
+                    "add an arm for `{}`, or `_` to match any value",
+                    missing
+                )));
+        }
+        // Without any arms, there is no value to match
+        let result_type = result.map_or(Type::Never, |(result_type, _)| result_type);
+        match_expr.result_type = Some(result_type.clone());
+        Ok(result_type)
+    }
+
+    /// Checks that the pattern matches values of `expected`, adding the variables it binds.
+    /// Returns the pattern for the exhaustiveness checking.
+    fn pattern(
+        &self,
+        pattern: &Pattern,
+        expected: &Type,
+        bindings: &mut HashMap<String, Variable>,
+    ) -> Output<patterns::Pat> {
+        match &pattern.kind {
+            PatternKind::Wildcard => Ok(patterns::Pat::Wild),
+            PatternKind::Binding(name) => {
+                if let Some(existing) = bindings.get(name) {
+                    return Err(error::TypeSystemError::duplicate_binding(name)
+                        .with_span(pattern.span)
+                        .with_label(existing.span, "first bound here"));
+                }
+                let variable = Variable {
+                    var_type: expected.clone(),
+                    mutable: false,
+                    constant: false,
+                    span: pattern.span,
+                };
+                bindings.insert(name.clone(), variable);
+                Ok(patterns::Pat::Wild)
+            }
+            PatternKind::Literal(literal) => {
+                let (found, ctor) = match literal {
+                    Literal::Number(Number::Int(int)) => {
+                        (Type::Int, Some(patterns::Ctor::Int(*int)))
+                    }
+                    Literal::Boolean(boolean) => {
+                        (Type::Boolean, Some(patterns::Ctor::Bool(*boolean)))
+                    }
+                    Literal::Number(Number::Float(_)) => (Type::Float, None),
+                    Literal::Number(Number::Double(_)) => (Type::Double, None),
+                    Literal::String(_) => (Type::String, None),
+                };
+                if found != *expected {
+                    return Err(error::TypeSystemError::type_mismatch(
+                        vec![expected.clone()],
+                        found,
+                    )
+                    .with_span(pattern.span));
                 }
+                match ctor {
+                    Some(ctor) => Ok(patterns::Pat::Ctor(ctor, Vec::new())),
+                    None => Err(error::TypeSystemError::new(
+                        format!(
+                            "Only int and bool literals can be matched, found: {}",
+                            found
+                        ),
+                        error::TypeSystemErrorCode::E0001,
+                    )
+                    .with_span(pattern.span)),
+                }
+            }
+            PatternKind::Variant(enum_name, name, fields) => {
+                let enum_type = Type::UserDefined(enum_name.clone());
+                if enum_type != *expected {
+                    return Err(error::TypeSystemError::type_mismatch(
+                        vec![expected.clone()],
+                        enum_type,
+                    )
+                    .with_span(pattern.span));
+                }
+                let (index, variant) = self.variant(enum_name, name, pattern.span)?;
+                if fields.len() != variant.fields.len() {
+                    return Err(error::TypeSystemError::wrong_number_of_values(
+                        enum_name,
+                        name,
+                        variant.fields.len(),
+                        fields.len(),
+                    )
+                    .with_span(pattern.span)
+                    .with_label(variant.span, "variant defined here"));
+                }
+                let fields = fields
+                    .iter()
+                    .zip(variant.fields.iter())
+                    .map(|(field, field_type)| self.pattern(field, field_type, bindings))
+                    .collect::<Output<_>>()?;
+                Ok(patterns::Pat::Ctor(patterns::Ctor::Variant(index), fields))
             }
-            Expression::If(if_expr) => self.if_expression(if_expr),
-            Expression::Block(block) => self.block(block),
-            Expression::Value(value) => self.value(value),
         }
     }
 
-    fn if_expression(&mut self, if_expr: &IfExpression) -> Output<Type> {
-        unimplemented!();
+    /// Finds the variant `name` of the enum `enum_name`, with its index
+    fn variant(&self, enum_name: &str, name: &str, span: Span) -> Output<(usize, &VariantType)> {
+        let enum_type = self
+            .enums
+            .get(enum_name)
+            .ok_or_else(|| error::TypeSystemError::unknown_enum(enum_name).with_span(span))?;
+        enum_type
+            .variants
+            .iter()
+            .enumerate()
+            .find(|(_, variant)| variant.name == name)
+            .ok_or_else(|| {
+                error::TypeSystemError::unknown_variant(enum_name, name)
+                    .with_span(span)
+                    .with_label(enum_type.span, "enum defined here")
+            })
+    }
+
+    /// Checks the body of a loop, returning the type of the values given to `break`
+    fn loop_body(&mut self, body: &mut Block, accepts_value: bool) -> Output<Type> {
+        self.loops.push(LoopContext {
+            accepts_value,
+            break_type: None,
+        });
+        let body_type = self.block(body);
+        let context = self.loops.pop().expect("The loop was just pushed");
+        body_type?;
+        // Without any `break`, the loop never ends
+        Ok(context
+            .break_type
+            .map_or(Type::Never, |(break_type, _)| break_type))
+    }
+
+    /// Checks that `break` is inside a loop, and that all the `break`s of a loop agree
+    fn break_expression(&mut self, value: Option<(Type, Span)>, span: Span) -> Output {
+        let context = match self.loops.last_mut() {
+            Some(context) => context,
+            None => return Err(error::TypeSystemError::outside_loop("break").with_span(span)),
+        };
+        if value.is_some() && !context.accepts_value {
+            return Err(error::TypeSystemError::break_value_outside_loop().with_span(span));
+        }
+
+        let (found, span) = value.unwrap_or((Type::Void, span));
+        match &context.break_type {
+            Some((expected, _)) if *expected == found => Ok(()),
+            Some((expected, first)) => Err(error::TypeSystemError::type_mismatch(
+                vec![expected.clone()],
+                found,
+            )
+            .with_span(span)
+            .with_label(*first, format!("the loop results in {} here", expected))),
+            None => {
+                context.break_type = Some((found, span));
+                Ok(())
+            }
+        }
     }
 
-    fn block(&mut self, block: &Block) -> Output<Type> {
-        unimplemented!();
+    fn block(&mut self, block: &mut Block) -> Output<Type> {
+        self.scopes.push(HashMap::new());
+        let mut block_type = Ok(Type::Void);
+        let mut diverges = false;
+        let last = block.len().saturating_sub(1);
+        for (i, statement) in block.statements.iter_mut().enumerate() {
+            // Errors are reported for each statement, only the last one decides the type
+            match self.statement(statement) {
+                result if i == last => block_type = result,
+                Err(error) => {
+                    self.errors.push(error);
+                    // Whether the statement ends the block is unknown, so avoid more errors
+                    diverges = true;
+                }
+                Ok(Type::Never) => diverges = true,
+                Ok(_) => (),
+            }
+        }
+        if let Some(scope) = self.scopes.pop() {
+            self.ended.extend(
+                scope
+                    .into_iter()
+                    .map(|(name, variable)| (name, variable.span)),
+            );
+        }
+        match block_type {
+            // E.g. `{ return x; }`, where the end of the block is never reached
+            Ok(Type::Void) if diverges => Ok(Type::Never),
+            block_type => block_type,
+        }
     }
 
-    fn value(&mut self, value: &Value) -> Output<Type> {
+    fn value(&mut self, value: &mut Value, span: Span) -> Output<Type> {
         match value {
             Value::Literal(literal) => match literal {
                 Literal::Number(Number::Int(_)) => Ok(Type::Int),
@@ -118,16 +1113,337 @@ impl TypeSystem {
                 Literal::Boolean(_) => Ok(Type::Boolean),
                 Literal::String(_) => Ok(Type::String),
             },
-            Value::Variable(name) => self.variable(name),
+            Value::Variable(name) => self.variable(name, span),
             Value::FunctionCall(func_call) => self.function_call(func_call),
+            Value::Struct(literal) => self.struct_literal(literal),
+            Value::Array(elements) => self.array_literal(elements, span),
+            Value::Variant(literal) => self.variant_literal(literal),
+        }
+    }
+
+    fn array_literal(&mut self, elements: &mut [Expression], span: Span) -> Output<Type> {
+        let length = elements.len();
+        let (first, rest) = match elements.split_first_mut() {
+            Some(elements) => elements,
+            None => return Err(error::TypeSystemError::empty_array().with_span(span)),
+        };
+        let element_type = self.expression(first)?;
+        let array_type = Type::Array(Box::new(element_type.clone()), length);
+        self.check_type(&array_type, first.span)?;
+        for element in rest.iter_mut() {
+            self.expect(element, element_type.clone(), first.span)?;
+        }
+        Ok(array_type)
+    }
+
+    fn variant_literal(&mut self, literal: &mut VariantLiteral) -> Output<Type> {
+        let (_, variant) = self.variant(&literal.enum_name, &literal.variant, literal.span)?;
+        let (fields, declaration) = (variant.fields.clone(), variant.span);
+        if literal.arguments.len() != fields.len() {
+            return Err(error::TypeSystemError::wrong_number_of_values(
+                &literal.enum_name,
+                &literal.variant,
+                fields.len(),
+                literal.arguments.len(),
+            )
+            .with_span(literal.span)
+            .with_label(declaration, "variant defined here"));
+        }
+        for (argument, field_type) in literal.arguments.iter_mut().zip(fields) {
+            self.expect(argument, field_type, declaration)?;
         }
+        Ok(Type::UserDefined(literal.enum_name.clone()))
     }
 
-    fn variable(&mut self, var: &str) -> Output<Type> {
-        unimplemented!();
+    fn struct_literal(&mut self, literal: &mut StructLiteral) -> Output<Type> {
+        let fields: Vec<(String, Type, Span)> = match self.structs.get(&literal.name) {
+            Some(struct_type) => struct_type
+                .fields
+                .iter()
+                .map(|field| (field.name.clone(), field.field_type.clone(), field.span))
+                .collect(),
+            None => {
+                return Err(
+                    error::TypeSystemError::unknown_struct(&literal.name).with_span(literal.span)
+                )
+            }
+        };
+        let struct_type = Type::UserDefined(literal.name.clone());
+
+        let mut given: Vec<&FieldValue> = Vec::new();
+        for field_value in literal.fields.iter_mut() {
+            if let Some(existing) = given.iter().find(|other| other.name == field_value.name) {
+                return Err(error::TypeSystemError::duplicate_field(&field_value.name)
+                    .with_span(field_value.span)
+                    .with_label(existing.span, "first given here"));
+            }
+            match fields.iter().find(|(name, _, _)| *name == field_value.name) {
+                Some((_, field_type, declaration)) => {
+                    self.expect(&mut field_value.value, field_type.clone(), *declaration)?;
+                }
+                None => {
+                    return Err(error::TypeSystemError::unknown_field(
+                        &struct_type,
+                        &field_value.name,
+                    )
+                    .with_span(field_value.span))
+                }
+            }
+            given.push(field_value);
+        }
+
+        let missing: Vec<&str> = fields
+            .iter()
+            .filter(|(name, _, _)| !given.iter().any(|field_value| field_value.name == *name))
+            .map(|(name, _, _)| name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(
+                error::TypeSystemError::missing_fields(&literal.name, &missing)
+                    .with_span(literal.span),
+            );
+        }
+        Ok(struct_type)
+    }
+
+    /// Finds the field `name` if `value_type` is a struct having it
+    fn field(&self, value_type: &Type, name: &str) -> Option<&Field> {
+        match value_type {
+            Type::UserDefined(struct_name) => self
+                .structs
+                .get(struct_name)?
+                .fields
+                .iter()
+                .find(|field| field.name == name),
+            _ => None,
+        }
+    }
+
+    fn variable(&mut self, var: &str, span: Span) -> Output<Type> {
+        self.lookup(var, span)
+            .map(|variable| variable.var_type.clone())
+    }
+
+    /// Finds the variable in the innermost scope declaring it
+    fn lookup(&self, var: &str, span: Span) -> Output<&Variable> {
+        let variable = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(var))
+            .ok_or_else(|| {
+                let error = error::TypeSystemError::unknown_variable(var).with_span(span);
+                match self.ended.get(var) {
+                    Some(declaration) => error.with_label(
+                        *declaration,
+                        "declared here, but only valid until the end of its block",
+                    ),
+                    None => error,
+                }
+            })?;
+        if variable.var_type == Type::Never {
+            // The value of the variable has errors, which are already reported
+            return Err(error::TypeSystemError::follow_up(var));
+        }
+        Ok(variable)
+    }
+
+    fn assignment(&mut self, target: &mut Expression, value: &mut Expression) -> Output<Type> {
+        let (var_type, declaration) = self.place(target)?;
+        self.assigned_value(value, var_type, declaration)?;
+        Ok(Type::Void)
+    }
+
+    /// Like `target = target op value`, so the target has to be a number
+    fn compound_assignment(
+        &mut self,
+        target: &mut Expression,
+        value: &mut Expression,
+    ) -> Output<Type> {
+        match self.place(target)? {
+            (var_type @ Type::Int, declaration)
+            | (var_type @ Type::Float, declaration)
+            | (var_type @ Type::Double, declaration) => {
+                self.assigned_value(value, var_type, declaration)?;
+                Ok(Type::Void)
+            }
+            (var_type, _) => Err(error::TypeSystemError::type_mismatch(
+                vec![Type::Int, Type::Float, Type::Double],
+                var_type,
+            )
+            .with_span(target.span)),
+        }
+    }
+
+    /// Checks the value assigned to a place of `var_type`, which is declared at `declaration`
+    fn assigned_value(
+        &mut self,
+        value: &mut Expression,
+        var_type: Type,
+        declaration: Span,
+    ) -> Output {
+        let chained = matches!(
+            value.kind,
+            ExpressionKind::Assignment(..) | ExpressionKind::CompoundAssignment(..)
+        );
+        match self.expect(value, var_type, declaration) {
+            Err(error) if chained => {
+                Err(error.with_note("assignments have no value, so they can not be chained"))
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    /// Checks that `target` can be assigned to, returning its type and where it is declared
+    fn place(&mut self, target: &mut Expression) -> Output<(Type, Span)> {
+        match &mut target.kind {
+            ExpressionKind::Value(Value::Variable(name)) => {
+                let variable = self.lookup(name, target.span)?;
+                if !variable.mutable {
+                    let note = if name == "self" {
+                        String::from("take `mut self` to change the object")
+                    } else {
+                        format!("declare it with `let mut {}`", name)
+                    };
+                    return Err(error::TypeSystemError::immutable_variable(name)
+                        .with_span(target.span)
+                        .with_label(variable.span, "declared here")
+                        .with_note(note));
+                }
+                Ok((variable.var_type.clone(), variable.span))
+            }
+            ExpressionKind::Field(value, name) => {
+                let (value_type, _) = self.place(value)?;
+                match self.field(&value_type, name) {
+                    Some(field) => Ok((field.field_type.clone(), field.span)),
+                    None => Err(error::TypeSystemError::unknown_field(&value_type, name)
+                        .with_span(target.span)),
+                }
+            }
+            ExpressionKind::Index(value, index) => {
+                let (value_type, declaration) = self.place(value)?;
+                self.expect(index, Type::Int, target.span)?;
+                match value_type {
+                    Type::Array(element_type, _) => Ok((*element_type, declaration)),
+                    value_type => {
+                        Err(error::TypeSystemError::expected_array(&value_type)
+                            .with_span(value.span))
+                    }
+                }
+            }
+            _ => Err(error::TypeSystemError::not_assignable().with_span(target.span)),
+        }
+    }
+
+    fn function_call(&mut self, func_call: &mut FunctionCall) -> Output<Type> {
+        let signature = match self.functions.get(&func_call.name) {
+            Some(signature) => signature.clone(),
+            None if func_call.name == "len" => return self.len(func_call),
+            None => {
+                return Err(error::TypeSystemError::unknown_function(&func_call.name)
+                    .with_span(func_call.span))
+            }
+        };
+
+        self.arguments(func_call, &signature)?;
+        Ok(signature.return_type)
+    }
+
+    /// Calls of a method are checked like calls of the function implementing it,
+    /// with the object as `self`
+    fn method_call(&mut self, object: &mut Expression, call: &mut FunctionCall) -> Output<Type> {
+        let object_type = self.expression(object)?;
+        let signature = match &object_type {
+            Type::UserDefined(class) => self.functions.get(&ClassDecl::mangle(class, &call.name)),
+            _ => None,
+        };
+        let signature = match signature {
+            Some(signature) => signature.clone(),
+            None => {
+                return Err(
+                    error::TypeSystemError::unknown_method(&object_type, &call.name)
+                        .with_span(call.span),
+                )
+            }
+        };
+
+        // Methods changing the object can only be called on places that can be assigned to.
+        // Temporary objects can always be changed.
+        let place = matches!(
+            object.kind,
+            ExpressionKind::Value(Value::Variable(_))
+                | ExpressionKind::Field(_, _)
+                | ExpressionKind::Index(_, _)
+        );
+        if signature.mutable_self && place {
+            self.place(object)?;
+        }
+
+        self.arguments(call, &signature)?;
+        Ok(signature.return_type)
     }
 
-    fn function_call(&mut self, func_call: &FunctionCall) -> Output<Type> {
-        unimplemented!();
+    /// Checks the number and types of the arguments of a call
+    fn arguments(&mut self, func_call: &mut FunctionCall, signature: &Signature) -> Output {
+        let count = func_call.arguments.len();
+        let expected = signature.args.len();
+        if count < expected || (!signature.variadic && count > expected) {
+            return Err(error::TypeSystemError::wrong_number_of_arguments(
+                &func_call.name,
+                expected,
+                count,
+            )
+            .with_span(func_call.span)
+            .with_label(signature.span, "function defined here"));
+        }
+
+        for (i, argument) in func_call.arguments.iter_mut().enumerate() {
+            match signature.args.get(i) {
+                Some(arg_type) => {
+                    self.expect(argument, arg_type.clone(), signature.arg_spans[i])?;
+                }
+                // Variadic arguments can be of any type
+                None => {
+                    self.expression(argument)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The `len` intrinsic, giving the length of an array.
+    /// It is only used when no function is named `len`.
+    fn len(&mut self, func_call: &mut FunctionCall) -> Output<Type> {
+        if func_call.arguments.len() != 1 {
+            return Err(error::TypeSystemError::wrong_number_of_arguments(
+                &func_call.name,
+                1,
+                func_call.arguments.len(),
+            )
+            .with_span(func_call.span));
+        }
+        match self.expression(&mut func_call.arguments[0])? {
+            Type::Array(_, _) => Ok(Type::Int),
+            found => Err(error::TypeSystemError::expected_array(&found)
+                .with_span(func_call.arguments[0].span)),
+        }
+    }
+}
+
+/// The type of the innermost elements if `value_type` is an array, or else `value_type` itself
+fn element_type(value_type: &Type) -> &Type {
+    match value_type {
+        Type::Array(element_type, _) => self::element_type(element_type),
+        value_type => value_type,
+    }
+}
+
+/// Finds the type two types agree on. `never` agrees with any other type,
+/// as the value never exists.
+fn unify(left: &Type, right: &Type) -> Option<Type> {
+    match (left, right) {
+        (Type::Never, other) | (other, Type::Never) => Some(other.clone()),
+        (left, right) if left == right => Some(left.clone()),
+        _ => None,
     }
 }
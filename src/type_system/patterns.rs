@@ -0,0 +1,205 @@
+//! Exhaustiveness and reachability of match arms.
+//!
+//! Uses the usefulness algorithm from "Warnings for pattern matching" (Maranget, 2007):
+//! a pattern is useful after some rows if it matches a value none of the rows match.
+//! An arm is unreachable if its pattern is not useful after the arms before it,
+//! and a match is exhaustive if `_` is not useful after all of its arms.
+
+use super::EnumType;
+use crate::parser::ast::Type;
+use std::collections::HashMap;
+
+/// A checked pattern, where bindings are the same as `_`
+#[derive(Debug, Clone)]
+pub enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+}
+
+/// The ways to construct a value of a type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ctor {
+    /// The variant of an enum, by index
+    Variant(usize),
+    Bool(bool),
+    Int(isize),
+}
+
+/// The patterns of the arms of a match
+pub struct Matrix {
+    value_type: Type,
+    rows: Vec<Vec<Pat>>,
+}
+
+impl Matrix {
+    pub fn new(value_type: Type) -> Self {
+        Self {
+            value_type,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Adds the pattern of the next arm, returning whether it matches any value
+    /// the arms before it do not
+    pub fn push(&mut self, pattern: Pat, enums: &HashMap<String, EnumType>) -> bool {
+        let row = vec![pattern];
+        let useful = Usefulness { enums }
+            .useful(&self.rows, &row, std::slice::from_ref(&self.value_type))
+            .is_some();
+        self.rows.push(row);
+        useful
+    }
+
+    /// A value not matched by any arm, shown as a pattern, if there is one
+    pub fn missing(&self, enums: &HashMap<String, EnumType>) -> Option<String> {
+        let usefulness = Usefulness { enums };
+        let witness = usefulness.useful(
+            &self.rows,
+            &[Pat::Wild],
+            std::slice::from_ref(&self.value_type),
+        )?;
+        Some(usefulness.show(&witness[0], &self.value_type))
+    }
+}
+
+struct Usefulness<'a> {
+    enums: &'a HashMap<String, EnumType>,
+}
+
+impl<'a> Usefulness<'a> {
+    /// Finds values matched by `row`, but not by any of `rows`.
+    /// `types` are the types of the columns.
+    fn useful(&self, rows: &[Vec<Pat>], row: &[Pat], types: &[Type]) -> Option<Vec<Pat>> {
+        let (first, rest) = match row.split_first() {
+            Some(split) => split,
+            None if rows.is_empty() => return Some(Vec::new()),
+            None => return None,
+        };
+        match first {
+            Pat::Ctor(ctor, _) => self.useful_ctor(rows, row, types, *ctor),
+            Pat::Wild => {
+                let used: Vec<Ctor> = rows
+                    .iter()
+                    .filter_map(|row| match &row[0] {
+                        Pat::Ctor(ctor, _) => Some(*ctor),
+                        Pat::Wild => None,
+                    })
+                    .collect();
+                let all = self.constructors(&types[0]);
+                let missing: Option<Ctor> = all
+                    .as_ref()
+                    .and_then(|all| all.iter().find(|ctor| !used.contains(ctor)).copied());
+                match (all, missing) {
+                    // Every constructor is used, so each of them has to be checked
+                    (Some(all), None) => all
+                        .into_iter()
+                        .find_map(|ctor| self.useful_ctor(rows, row, types, ctor)),
+                    // Otherwise only the rows starting with `_` match the missing values
+                    (_, missing) => {
+                        let default: Vec<Vec<Pat>> = rows
+                            .iter()
+                            .filter(|row| matches!(row[0], Pat::Wild))
+                            .map(|row| row[1..].to_vec())
+                            .collect();
+                        let mut witness = self.useful(&default, rest, &types[1..])?;
+                        let head = match missing {
+                            Some(ctor) if !used.is_empty() => {
+                                let arity = self.field_types(&types[0], ctor).len();
+                                Pat::Ctor(ctor, vec![Pat::Wild; arity])
+                            }
+                            _ => Pat::Wild,
+                        };
+                        witness.insert(0, head);
+                        Some(witness)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Usefulness for the values built with `ctor`
+    fn useful_ctor(
+        &self,
+        rows: &[Vec<Pat>],
+        row: &[Pat],
+        types: &[Type],
+        ctor: Ctor,
+    ) -> Option<Vec<Pat>> {
+        let field_types = self.field_types(&types[0], ctor);
+        let arity = field_types.len();
+        let rows: Vec<Vec<Pat>> = rows
+            .iter()
+            .filter_map(|row| specialize(row, ctor, arity))
+            .collect();
+        let row = specialize(row, ctor, arity)?;
+        let types: Vec<Type> = field_types
+            .into_iter()
+            .chain(types[1..].iter().cloned())
+            .collect();
+
+        let mut witness = self.useful(&rows, &row, &types)?;
+        let rest = witness.split_off(arity);
+        let mut result = vec![Pat::Ctor(ctor, witness)];
+        result.extend(rest);
+        Some(result)
+    }
+
+    /// All the constructors of the type, if it has a finite number of them
+    fn constructors(&self, value_type: &Type) -> Option<Vec<Ctor>> {
+        match value_type {
+            Type::Boolean => Some(vec![Ctor::Bool(true), Ctor::Bool(false)]),
+            Type::UserDefined(name) => self
+                .enums
+                .get(name)
+                .map(|enum_type| (0..enum_type.variants.len()).map(Ctor::Variant).collect()),
+            _ => None,
+        }
+    }
+
+    /// The types of the values held by `ctor`
+    fn field_types(&self, value_type: &Type, ctor: Ctor) -> Vec<Type> {
+        match (value_type, ctor) {
+            (Type::UserDefined(name), Ctor::Variant(index)) => {
+                self.enums[name].variants[index].fields.clone()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Shows the pattern like it is written in a match arm
+    fn show(&self, pattern: &Pat, value_type: &Type) -> String {
+        match pattern {
+            Pat::Wild => String::from("_"),
+            Pat::Ctor(Ctor::Bool(boolean), _) => boolean.to_string(),
+            Pat::Ctor(Ctor::Int(int), _) => int.to_string(),
+            Pat::Ctor(ctor @ Ctor::Variant(index), fields) => {
+                let name = match value_type {
+                    Type::UserDefined(name) => name,
+                    _ => unreachable!("Only enums have variants"),
+                };
+                let variant = &self.enums[name].variants[*index].name;
+                if fields.is_empty() {
+                    return format!("{}::{}", name, variant);
+                }
+                let fields: Vec<String> = fields
+                    .iter()
+                    .zip(self.field_types(value_type, *ctor).iter())
+                    .map(|(field, field_type)| self.show(field, field_type))
+                    .collect();
+                format!("{}::{}({})", name, variant, fields.join(", "))
+            }
+        }
+    }
+}
+
+/// The rest of the row if its first pattern matches values built with `ctor`,
+/// with the patterns of the held values in front
+fn specialize(row: &[Pat], ctor: Ctor, arity: usize) -> Option<Vec<Pat>> {
+    let mut result = match &row[0] {
+        Pat::Ctor(other, fields) if *other == ctor => fields.clone(),
+        Pat::Ctor(_, _) => return None,
+        Pat::Wild => vec![Pat::Wild; arity],
+    };
+    result.extend(row[1..].iter().cloned());
+    Some(result)
+}
@@ -0,0 +1,609 @@
+use super::error::{TypeSystemError, TypeSystemErrorCode};
+use super::*;
+use crate::lexer::{wrapper::LexerWrapper, Token};
+use crate::parser::Parser;
+use logos::Logos;
+
+fn analyze(program: &'static str) -> Result<(), Vec<TypeSystemError>> {
+    let mut program = Parser::new(LexerWrapper(Token::lexer(program)))
+        .parse()
+        .expect("The program should parse");
+    TypeSystem::analyze(&mut program)
+}
+
+fn codes(program: &'static str) -> Vec<String> {
+    analyze(program)
+        .expect_err("The program should not type check")
+        .iter()
+        .map(|err| format!("{:?}", err.code()))
+        .collect()
+}
+
+#[test]
+fn well_typed_program() {
+    // Given
+    let program = "
+        extern fn printf(format: string, ...) -> int;
+        fn fac(n: int) -> int {
+            if n <= 1 { 1 } else { n * fac(n - 1) }
+        }
+        fn main() {
+            let x = fac(5);
+            let y = 2.5 * 2.0;
+            printf(\"%d %f\\n\", x, y);
+        }";
+
+    // When
+    let result = analyze(program);
+
+    // Then
+    assert!(result.is_ok(), "{:?}", result);
+}
+
+#[test]
+fn sample_program_checks() {
+    // Given
+    let program = include_str!("../../test.poop");
+
+    // When
+    let result = analyze(program);
+
+    // Then
+    assert!(result.is_ok(), "{:?}", result);
+}
+
+#[test]
+fn missing_main() {
+    assert_eq!(codes("fn foo() {}"), vec!["E0011"]);
+}
+
+#[test]
+fn main_signature() {
+    assert!(analyze("fn main() {}").is_ok());
+    assert!(analyze("fn main(argc: int, argv: [string; 4]) -> int { argc }").is_ok());
+    assert_eq!(codes("fn main() -> double { 1.0 }"), vec!["E0017"]);
+    assert_eq!(
+        codes("fn main(argc: int, argv: int) -> int { 0 }"),
+        vec!["E0017"]
+    );
+    assert_eq!(codes("fn main(argc: int) -> int { argc }"), vec!["E0017"]);
+}
+
+#[test]
+fn values_need_storable_types_and_places() {
+    assert_eq!(
+        codes(
+            "fn nothing() {}
+            fn main() {
+                let x = nothing();
+                let a: [void; 2] = [nothing(), nothing()];
+                1 = 2;
+            }"
+        ),
+        vec!["E0013", "E0013", "E0016"]
+    );
+}
+
+#[test]
+fn unknown_variable() {
+    let errors = analyze("fn main() { let x = y + 1; }").unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message(), "Unknown variable 'y'");
+    assert_eq!(errors[0].span().map(|span| span.range()), Some(20..21));
+}
+
+#[test]
+fn variables_are_scoped_to_their_block() {
+    assert_eq!(codes("fn main() { { let x = 1; x; }; x; }"), vec!["E0002"]);
+}
+
+#[test]
+fn unknown_function() {
+    assert_eq!(codes("fn main() { foo(); }"), vec!["E0002"]);
+}
+
+#[test]
+fn wrong_number_of_arguments() {
+    assert_eq!(
+        codes(
+            "extern fn printf(format: string, ...) -> int;
+            fn foo(a: int) -> int { a }
+            fn main() { foo(1, 2); printf(); }"
+        ),
+        vec!["E0003", "E0003"]
+    );
+}
+
+#[test]
+fn wrong_argument_type() {
+    assert_eq!(
+        codes("fn foo(a: int) -> int { a } fn main() { foo(true); }"),
+        vec!["E0001"]
+    );
+}
+
+#[test]
+fn wrong_return_type() {
+    assert_eq!(codes("fn main() -> int { true }"), vec!["E0001"]);
+}
+
+#[test]
+fn if_and_else_must_agree() {
+    // Given
+    let program = "fn main() -> int { if true { 1 } else { 2.0 } }";
+
+    // When
+    let errors = analyze(program).unwrap_err();
+
+    // Then
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].code(), TypeSystemErrorCode::E0001));
+    assert_eq!(errors[0].span().map(|span| span.range()), Some(38..45));
+}
+
+#[test]
+fn if_condition_must_be_boolean() {
+    assert_eq!(codes("fn main() { if 1 { 2 }; }"), vec!["E0001"]);
+}
+
+#[test]
+fn mixed_numeric_operands() {
+    assert_eq!(
+        codes("fn main() { 1 + 2.0; 1 < 2.0f; }"),
+        vec!["E0001", "E0001"]
+    );
+}
+
+#[test]
+fn block_type_is_last_statement() {
+    assert!(analyze("fn main() -> int { let x = { let y = 2; y * 2 }; x }").is_ok());
+    assert_eq!(codes("fn main() -> int { 1; }"), vec!["E0001"]);
+}
+
+#[test]
+fn variables_with_errors_are_still_declared() {
+    assert_eq!(
+        codes("fn main() { let x = 1 + true; let y = x * 2; let z = x; }"),
+        vec!["E0001"]
+    );
+    // The annotated type is used when there is one
+    assert_eq!(
+        codes("fn main() { let x: int = true; let y: bool = x; let z = x * 2; }"),
+        vec!["E0001", "E0001"]
+    );
+}
+
+#[test]
+fn annotated_variables() {
+    assert!(analyze("fn f() -> bool { let x: bool = true; x } fn main() {}").is_ok());
+
+    let errors = analyze("fn main() { let x: int = 2.0; }").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].code(), TypeSystemErrorCode::E0001));
+    assert_eq!(errors[0].span().map(|span| span.range()), Some(25..28));
+}
+
+#[test]
+fn inferred_types_are_stored() {
+    // Given
+    let mut program = Parser::new(LexerWrapper(Token::lexer(
+        "fn main() { let x = if true { 1.5f } else { 2.5f }; }",
+    )))
+    .parse()
+    .unwrap();
+
+    // When
+    TypeSystem::analyze(&mut program).unwrap();
+
+    // Then
+    let var_decl = match &program {
+        Program::Decl(Decl::FuncDecl(func_decl), _) => match &func_decl.body[0] {
+            Statement::VarDecl(var_decl) => var_decl,
+            statement => panic!("Expected a variable, found: {:?}", statement),
+        },
+        program => panic!("Expected a function, found: {:?}", program),
+    };
+    assert_eq!(var_decl.var_type, Some(Type::Float));
+    match &var_decl.expression.kind {
+        ExpressionKind::If(if_expr) => assert_eq!(if_expr.result_type, Some(Type::Float)),
+        kind => panic!("Expected an if expression, found: {:?}", kind),
+    }
+}
+
+#[test]
+fn loops() {
+    assert!(analyze(
+        "fn main() -> int {
+            while 1 < 2 { if true { continue; }; break; };
+            loop { break 5; }
+        }"
+    )
+    .is_ok());
+}
+
+#[test]
+fn break_outside_loop() {
+    assert_eq!(
+        codes("fn main() { break; continue; }"),
+        vec!["E0004", "E0004"]
+    );
+}
+
+#[test]
+fn breaks_must_agree() {
+    assert_eq!(
+        codes("fn main() { while true { break 1; }; loop { break 1; break 2.0; }; }"),
+        vec!["E0014", "E0001"]
+    );
+}
+
+#[test]
+fn assignment() {
+    assert!(analyze("fn main() { let mut x = 1; x = 2; x += 3; }").is_ok());
+    assert_eq!(
+        codes("fn f(a: int) { let x = 1; x = 2; a += 1; let mut y = 1; y = 2.0; } fn main() {}"),
+        vec!["E0005", "E0005", "E0001"]
+    );
+
+    // Assignments have no value, so they can not be chained
+    let errors = analyze("fn main() { let mut a = 1; let mut b = 2; a = b = 3; }").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(crate::diagnostic::Diagnostic::from(&errors[0])
+        .notes
+        .iter()
+        .any(|note| note.contains("can not be chained")));
+}
+
+#[test]
+fn returns() {
+    assert!(analyze(
+        "fn sign(n: int) -> int {
+            if n < 0 { return -1; };
+            if n == 0 { return 0; } else { 1 }
+        }
+        fn early() -> int { return 7; }
+        fn forever() -> int { loop { return 3; } }
+        fn main() { if true { return; }; }"
+    )
+    .is_ok());
+}
+
+#[test]
+fn return_type_mismatch() {
+    assert_eq!(
+        codes("fn f() -> int { return true; } fn main() { return 1; }"),
+        vec!["E0001", "E0001"]
+    );
+}
+
+#[test]
+fn extern_functions() {
+    assert!(analyze(
+        "extern fn puts(s: string) -> int;
+        fn main() -> int { puts(\"hello\") }"
+    )
+    .is_ok());
+    assert_eq!(
+        codes(
+            "extern fn puts(s: string) -> int;
+            fn main() { puts(1); puts(\"a\", \"b\"); printf(\"c\"); }"
+        ),
+        vec!["E0001", "E0003", "E0002"]
+    );
+}
+
+#[test]
+fn functions_can_be_used_before_their_definition() {
+    assert!(analyze(
+        "fn main() { is_even(10); }
+        fn is_even(n: int) -> bool { if n == 0 { true } else { is_odd(n - 1) } }
+        fn is_odd(n: int) -> bool { if n == 0 { false } else { is_even(n - 1) } }"
+    )
+    .is_ok());
+}
+
+#[test]
+fn functions_defined_twice() {
+    // Given
+    let program = "fn foo() {} fn main() { foo(); } fn foo() -> int { 1 }";
+
+    // When
+    let errors = analyze(program).unwrap_err();
+
+    // Then
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].code(), TypeSystemErrorCode::E0006));
+    assert_eq!(errors[0].span().map(|span| span.range()), Some(33..54));
+}
+
+#[test]
+fn global_variables() {
+    assert!(analyze(
+        "const LIMIT = 10 * 2;
+        let mut counter = twice(LIMIT);
+        fn twice(n: int) -> int { n * 2 }
+        fn main() -> int { counter += 1; counter + LIMIT }"
+    )
+    .is_ok());
+    assert_eq!(
+        codes("const A = 1; fn main() { A = 2; x; } let x = true;"),
+        vec!["E0005"]
+    );
+}
+
+#[test]
+fn constants_are_known_at_compile_time() {
+    assert_eq!(
+        codes(
+            "let a = 1;
+            const B = a + 1;
+            const C = f();
+            const D = B;
+            fn f() -> int { 1 }
+            fn main() {}"
+        ),
+        vec!["E0007", "E0007"]
+    );
+}
+
+#[test]
+fn negation() {
+    assert!(analyze(
+        "fn f() -> int { 2 } fn g() -> double { let x = -f(); -(1.5 * 2.0) } fn main() {}"
+    )
+    .is_ok());
+    assert_eq!(codes("fn main() { -true; }"), vec!["E0001"]);
+}
+
+#[test]
+fn shadowing() {
+    assert!(analyze(
+        "fn f(x: int) -> bool {
+            let x = x * 2;
+            let y = { let x = x < 3; x };
+            let x = 1.5;
+            y
+        }
+        fn main() {}"
+    )
+    .is_ok());
+}
+
+#[test]
+fn variables_of_ended_blocks() {
+    // Given
+    let program = "fn main() -> int { let x = { let y = 2; y }; x + y }";
+
+    // When
+    let errors = analyze(program).unwrap_err();
+
+    // Then
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message(), "Unknown variable 'y'");
+    assert_eq!(errors[0].span().map(|span| span.range()), Some(49..50));
+    let diagnostic = crate::diagnostic::Diagnostic::from(&errors[0]);
+    assert_eq!(diagnostic.labels[1].span.range(), 29..38);
+}
+
+#[test]
+fn structs() {
+    assert!(analyze(
+        "struct Line { from: Point, to: Point }
+        struct Point { x: int, y: int }
+        fn shift(p: Point) -> Point { Point { y: p.y, x: p.x + 1 } }
+        fn main() -> int {
+            let mut line = Line { from: Point { x: 0, y: 0 }, to: shift(Point { x: 1, y: 2 }) };
+            line.to.y = 5;
+            line.to.x + line.from.y
+        }"
+    )
+    .is_ok());
+}
+
+#[test]
+fn struct_errors() {
+    assert_eq!(
+        codes(
+            "struct Point { x: int, y: int }
+            struct Point { z: int }
+            fn main() {
+                let p = Point { x: 1, y: 2.0 };
+                Point { x: 1 };
+                Point { x: 1, x: 2, y: 3 };
+                Shape { x: 1 };
+                let q = Point { x: 1, y: 2 };
+                q.z;
+                q.x = 3;
+            }"
+        ),
+        vec!["E0006", "E0001", "E0008", "E0006", "E0002", "E0002", "E0005"]
+    );
+}
+
+#[test]
+fn structs_can_not_contain_themselves() {
+    assert_eq!(
+        codes("struct A { b: B } struct B { a: A, n: int } fn main() {}"),
+        vec!["E0012", "E0012"]
+    );
+}
+
+#[test]
+fn arrays() {
+    assert!(analyze(
+        "const SIZES = [1, 2, 3];
+        struct Row { cells: [double; 2] }
+        fn first(values: [int; 3]) -> int { values[0] }
+        fn main() -> int {
+            let mut rows = [Row { cells: [0.5, 1.5] }, Row { cells: [2.5, 3.5] }];
+            rows[1].cells[0] = 4.0;
+            let a: [int; 3] = [4, 5, 6];
+            first(a) + SIZES[2] + len(rows)
+        }"
+    )
+    .is_ok());
+}
+
+#[test]
+fn array_errors() {
+    assert_eq!(
+        codes(
+            "fn main() {
+                let a = [1, 2.0];
+                let b: [int; 2] = [1, 2, 3];
+                [];
+                let c = [1, 2];
+                c[true];
+                c[0] = 1;
+                len(1);
+                1[0];
+            }"
+        ),
+        vec!["E0001", "E0001", "E0015", "E0001", "E0005", "E0001", "E0001"]
+    );
+}
+
+#[test]
+fn enums_and_match() {
+    assert!(analyze(
+        "enum Shape { Circle(double), Rect(int, int), Empty }
+        enum Tree { Leaf(int), Pair(Shape, bool) }
+        fn area(shape: Shape) -> double {
+            match shape {
+                Shape::Circle(r) => 3.0 * r * r,
+                Shape::Rect(w, h) => { if w * h > 10 { 10.0 } else { 1.0 } }
+                Shape::Empty => 0.0,
+            }
+        }
+        fn size(tree: Tree) -> int {
+            match tree {
+                Tree::Leaf(0) => 1,
+                Tree::Leaf(n) => n,
+                Tree::Pair(Shape::Rect(w, _), true) => w,
+                Tree::Pair(_, false) => return 7,
+                Tree::Pair(_, true) => 8,
+            }
+        }
+        fn main() -> int {
+            let s = match 2 { 0 => Shape::Empty, n => Shape::Rect(n, 1) };
+            match area(s) > 1.0 { true => 1, false => 0 }
+        }"
+    )
+    .is_ok());
+}
+
+#[test]
+fn enum_errors() {
+    assert_eq!(
+        codes(
+            "enum Shape { Circle(double), Rect(int, int), Circle }
+            struct Shape { x: int }
+            enum List { Cons(int, List), Nil }
+            fn main() {
+                Shape::Rect(1);
+                Shape::Rect(1, 2.0);
+                Shape::Square;
+                Color::Red;
+                let s = Shape::Circle(1.0);
+                match s { Shape::Rect(a, a) => 1, _ => 2 };
+                match 1 { 1.5 => 1, _ => 2 };
+                match 1 { 1 => 1, 2 => true, _ => 3 };
+                match 1 { s => s.x };
+            }"
+        ),
+        vec![
+            "E0006", "E0006", "E0012", "E0003", "E0001", "E0002", "E0002", "E0006", "E0001",
+            "E0001", "E0002"
+        ]
+    );
+}
+
+#[test]
+fn matches_must_be_exhaustive() {
+    // Given
+    let program = "enum Shape { Circle(double), Rect(int, int), Empty }
+        fn main() -> int {
+            let s = Shape::Empty;
+            match s { Shape::Circle(_) => 1, Shape::Empty => 2 }
+        }";
+
+    // When
+    let errors = analyze(program).unwrap_err();
+
+    // Then
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].code(), TypeSystemErrorCode::E0009));
+    assert_eq!(
+        errors[0].message(),
+        "Match is not exhaustive, `Shape::Rect(_, _)` is not matched"
+    );
+    assert_eq!(
+        codes("fn main() { match true { true => 1 }; match 1 { 0 => 1, 1 => 2 }; }"),
+        vec!["E0009", "E0009"]
+    );
+}
+
+#[test]
+fn unreachable_arms() {
+    assert_eq!(
+        codes(
+            "enum Option { Some(int), None }
+            fn main() {
+                let o = Option::Some(1);
+                match o { _ => 1, Option::None => 2 };
+                match o { Option::Some(_) => 1, Option::None => 2, Option::Some(3) => 3 };
+                match true { true => 1, false => 2, _ => 3 };
+            }"
+        ),
+        vec!["E0010", "E0010", "E0010"]
+    );
+}
+
+#[test]
+fn classes() {
+    assert!(analyze(
+        "class Counter {
+            count: int,
+            fn new(start: int) { Counter { count: start } }
+            fn get(self) -> int { self.count }
+            fn add(mut self, n: int) { self.count += n; }
+            fn reset(mut self) -> int { let old = self.get(); self.count = 0; old }
+        }
+        struct Pair { first: Counter, second: Counter }
+        fn main() -> int {
+            let mut c = Counter(1);
+            c.add(2);
+            let mut pair = Pair { first: c, second: Counter(0) };
+            pair.second.add(c.get());
+            Counter(5).reset() + pair.first.get()
+        }"
+    )
+    .is_ok());
+}
+
+#[test]
+fn class_errors() {
+    assert_eq!(
+        codes(
+            "class Point {
+                x: int,
+                fn new() -> int { 0 }
+                fn set(self, x: int) { self.x = x; }
+                fn bump(mut self) { self.x += 1; }
+                fn get(self) -> int { self.x }
+                fn get(self) -> bool { true }
+            }
+            class Point { y: int }
+            fn main() {
+                let p = Point { x: 1 };
+                p.bump();
+                p.nothing();
+                p.get(1);
+                let n = 1;
+                n.get();
+            }"
+        ),
+        vec!["E0006", "E0001", "E0006", "E0005", "E0005", "E0002", "E0003", "E0002"]
+    );
+}